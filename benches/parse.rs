@@ -20,6 +20,7 @@ mod tests {
         b.iter(|| {
             let _ = yui::parser::section::parse_header(
                 raw,
+                ident.ident.encoding(),
                 ident.shoff as usize,
                 ident.shstrndx as usize,
                 ident.shnum as usize,
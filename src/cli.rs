@@ -0,0 +1,262 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::{LinkerError, Result};
+use crate::linker::Options;
+
+/// Command line arguments accepted by the `yui` binary.
+#[derive(Debug, Default)]
+pub struct Args {
+    /// Path of the output file.
+    pub output: PathBuf,
+    /// Paths of the input object files.
+    pub inputs: Vec<PathBuf>,
+    /// Options passed to the linker.
+    pub options: Options,
+}
+
+/// Parses command line arguments (excluding the program name).
+///
+/// The first positional argument is the output file and the remaining positional
+/// arguments are input files. Options may appear anywhere and accept both the
+/// `--option=value` and `--option value` forms.
+pub fn parse_args<I, S>(args: I) -> Result<Args>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut parsed = Args::default();
+    let mut positionals = Vec::new();
+    let mut args = args.into_iter().map(Into::into);
+
+    while let Some(arg) = args.next() {
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) if name.starts_with('-') => (name.to_string(), Some(value)),
+            _ => (arg.clone(), None),
+        };
+
+        let mut value = || -> Result<String> {
+            match inline_value {
+                Some(value) => Ok(value.to_string()),
+                None => args
+                    .next()
+                    .ok_or_else(|| LinkerError::invalid_argument(&name, "missing value")),
+            }
+        };
+
+        match name.as_str() {
+            "--wrap" | "-wrap" => {
+                parsed.options.wrap.insert(value()?);
+            }
+            "--defsym" | "-defsym" => {
+                parsed.options.defsym.push(value()?.parse()?);
+            }
+            "-u" | "--undefined" => {
+                parsed.options.undefined.push(value()?);
+            }
+            "--require-defined" => {
+                parsed.options.require_defined.push(value()?);
+            }
+            "-e" | "--entry" => {
+                parsed.options.entry = Some(value()?);
+            }
+            "-r" | "--relocatable" => parsed.options.relocatable = true,
+            "-pie" | "--pie" | "--pic-executable" => parsed.options.pie = true,
+            "-no-pie" | "--no-pie" | "--no-pic-executable" => parsed.options.pie = false,
+            "--no-dynamic-linker" => parsed.options.no_dynamic_linker = true,
+            "-I" | "--dynamic-linker" | "-dynamic-linker" => {
+                parsed.options.dynamic_linker = Some(value()?);
+            }
+            "-shared" | "--shared" | "-Bshareable" => parsed.options.shared = true,
+            "-soname" | "--soname" | "-h" => parsed.options.soname = Some(value()?),
+            "--version-script" | "-version-script" => {
+                let path = value()?;
+                let script = fs::read_to_string(&path).map_err(|e| LinkerError::Io {
+                    error: e,
+                    context: Some(format!("reading version script: {}", path)),
+                })?;
+                parsed.options.version_script = Some(script.parse()?);
+            }
+            "-Map" | "--Map" => parsed.options.map = Some(PathBuf::from(value()?)),
+            "--relax" => parsed.options.relax = true,
+            "--no-relax" => parsed.options.relax = false,
+            "--gc-sections" => parsed.options.gc_sections = true,
+            "--no-gc-sections" => parsed.options.gc_sections = false,
+            "--print-gc-sections" => parsed.options.print_gc_sections = true,
+            "--no-print-gc-sections" => parsed.options.print_gc_sections = false,
+            "--icf" => parsed.options.icf = value()?.parse()?,
+            "--print-icf-sections" => parsed.options.print_icf_sections = true,
+            "--no-print-icf-sections" => parsed.options.print_icf_sections = false,
+            _ if name.starts_with("-O") && name.len() > 2 => {
+                parsed.options.optimize = name[2..].parse().map_err(|_| {
+                    LinkerError::invalid_argument(&name, "invalid optimization level")
+                })?;
+            }
+            _ if name.starts_with("-u") && !name.starts_with("--") => {
+                parsed.options.undefined.push(name[2..].to_string());
+            }
+            _ if name.starts_with('-') && name.len() > 1 => {
+                return Err(LinkerError::invalid_argument(&name, "unknown option"));
+            }
+            _ => positionals.push(arg),
+        }
+    }
+
+    let mut positionals = positionals.into_iter();
+    let Some(output) = positionals.next() else {
+        return Err(LinkerError::invalid_argument(
+            "<output>",
+            "missing output file",
+        ));
+    };
+    parsed.output = PathBuf::from(output);
+    parsed.inputs = positionals.map(PathBuf::from).collect();
+
+    if parsed.inputs.is_empty() {
+        return Err(LinkerError::invalid_argument("<input>", "no input files"));
+    }
+
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_parse_positional_arguments() {
+        let args = parse_args(["a.out", "main.o", "sub.o"]).unwrap();
+        assert_eq!(args.output, PathBuf::from("a.out"));
+        assert_eq!(
+            args.inputs,
+            vec![PathBuf::from("main.o"), PathBuf::from("sub.o")]
+        );
+    }
+
+    #[test]
+    fn should_parse_wrap_option() {
+        let args = parse_args(["--wrap=malloc", "a.out", "main.o", "--wrap", "free"]).unwrap();
+        assert!(args.options.wrap.contains("malloc"));
+        assert!(args.options.wrap.contains("free"));
+        assert_eq!(args.inputs, vec![PathBuf::from("main.o")]);
+    }
+
+    #[test]
+    fn should_parse_defsym_option() {
+        let args = parse_args(["--defsym=version=0x1234", "a.out", "main.o"]).unwrap();
+        assert_eq!(args.options.defsym, vec!["version=0x1234".parse().unwrap()]);
+    }
+
+    #[test]
+    fn should_parse_undefined_options() {
+        let args = parse_args([
+            "-u",
+            "init_a",
+            "-uinit_b",
+            "--undefined=init_c",
+            "--require-defined=plugin_register",
+            "a.out",
+            "main.o",
+        ])
+        .unwrap();
+        assert_eq!(args.options.undefined, vec!["init_a", "init_b", "init_c"]);
+        assert_eq!(args.options.require_defined, vec!["plugin_register"]);
+    }
+
+    #[test]
+    fn should_parse_gc_sections_options() {
+        let args = parse_args([
+            "--gc-sections",
+            "--print-gc-sections",
+            "-e",
+            "main",
+            "a.out",
+            "main.o",
+        ])
+        .unwrap();
+        assert!(args.options.gc_sections);
+        assert!(args.options.print_gc_sections);
+        assert_eq!(args.options.entry_symbol(), "main");
+    }
+
+    #[test]
+    fn should_parse_icf_options() {
+        let args = parse_args(["--icf=safe", "--print-icf-sections", "a.out", "main.o"]).unwrap();
+        assert_eq!(args.options.icf, crate::linker::options::Icf::Safe);
+        assert!(args.options.print_icf_sections);
+
+        let err = parse_args(["--icf", "most", "a.out", "main.o"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid argument '--icf=most': expected one of none, safe or all"
+        );
+    }
+
+    #[test]
+    fn should_parse_map_option() {
+        let args = parse_args(["-Map", "out.map", "a.out", "main.o"]).unwrap();
+        assert_eq!(args.options.map, Some(PathBuf::from("out.map")));
+        let args = parse_args(["-Map=out.map", "a.out", "main.o"]).unwrap();
+        assert_eq!(args.options.map, Some(PathBuf::from("out.map")));
+    }
+
+    #[test]
+    fn should_parse_optimization_level() {
+        let args = parse_args(["-O2", "a.out", "main.o"]).unwrap();
+        assert_eq!(args.options.optimize, 2);
+    }
+
+    #[test]
+    fn should_parse_relocatable_option() {
+        let args = parse_args(["-r", "combined.o", "a.o", "b.o"]).unwrap();
+        assert!(args.options.relocatable);
+        assert_eq!(args.output, PathBuf::from("combined.o"));
+    }
+
+    #[test]
+    fn should_parse_pie_options() {
+        let args = parse_args(["-pie", "--no-dynamic-linker", "a.out", "main.o"]).unwrap();
+        assert!(args.options.pie);
+        assert!(args.options.no_dynamic_linker);
+
+        let args = parse_args(["-pie", "-no-pie", "a.out", "main.o"]).unwrap();
+        assert!(!args.options.pie);
+    }
+
+    #[test]
+    fn should_parse_dynamic_linker() {
+        let args = parse_args(["--dynamic-linker=/lib/ld.so", "a.out", "main.o"]).unwrap();
+        assert_eq!(args.options.dynamic_linker.as_deref(), Some("/lib/ld.so"));
+
+        let args = parse_args(["-I", "/lib/ld.so", "a.out", "main.o", "libc.so"]).unwrap();
+        assert_eq!(args.options.dynamic_linker.as_deref(), Some("/lib/ld.so"));
+        assert_eq!(args.inputs.len(), 2);
+    }
+
+    #[test]
+    fn should_parse_shared_options() {
+        let args = parse_args(["-shared", "-soname", "libfoo.so.1", "libfoo.so", "foo.o"]).unwrap();
+        assert!(args.options.shared);
+        assert_eq!(args.options.soname.as_deref(), Some("libfoo.so.1"));
+        assert_eq!(args.output, PathBuf::from("libfoo.so"));
+    }
+
+    #[test]
+    fn should_parse_relax() {
+        let args = parse_args(["--relax", "a.out", "main.o"]).unwrap();
+        assert!(args.options.relax);
+
+        let args = parse_args(["--relax", "--no-relax", "a.out", "main.o"]).unwrap();
+        assert!(!args.options.relax);
+    }
+
+    #[test]
+    fn should_error_unknown_option() {
+        let err = parse_args(["--frobnicate", "a.out", "main.o"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid argument '--frobnicate': unknown option"
+        );
+    }
+}
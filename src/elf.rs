@@ -1,3 +1,6 @@
+pub mod archive;
+pub mod dynamic;
+pub mod group;
 pub mod header;
 pub mod program_header;
 pub mod relocation;
@@ -17,6 +20,43 @@ pub struct ELF {
     pub section_headers: Vec<section::Header>,
     /// A list of symbols defined in the ELF file.
     pub symbols: Vec<symbol::Symbol>,
-    /// A list of relocation entries with addends.
-    pub relocations: Vec<relocation::RelocationAddend>,
+    /// Relocation entries with addends, grouped by the section they apply to.
+    pub relocations: Vec<relocation::RelocationSection>,
+    /// Section groups (`SHT_GROUP`) defined in the ELF file.
+    pub groups: Vec<group::Group>,
+    /// Symbols of the dynamic symbol table (`.dynsym`) of a shared object.
+    pub dynamic_symbols: Vec<symbol::Symbol>,
+    /// Entries of the dynamic section (`.dynamic`) of a shared object or executable.
+    pub dynamic: Vec<dynamic::Entry>,
+}
+
+impl ELF {
+    /// Returns the string at `offset` in the string table of the dynamic section.
+    pub fn dynamic_string(&self, offset: u64) -> Option<String> {
+        let dynamic = self
+            .section_headers
+            .iter()
+            .find(|s| s.r#type == section::SectionType::Dynamic)?;
+        let strtab = self.section_headers.get(dynamic.link as usize)?;
+        let bytes = strtab.section_raw_data.get(offset as usize..)?;
+        let end = bytes.iter().position(|&b| b == 0)?;
+        Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+
+    /// Returns the name of a shared object (`DT_SONAME`).
+    pub fn soname(&self) -> Option<String> {
+        self.dynamic
+            .iter()
+            .find(|entry| entry.tag == dynamic::Tag::SoName)
+            .and_then(|entry| self.dynamic_string(entry.value))
+    }
+
+    /// Returns the names of the shared objects this file depends on (`DT_NEEDED`).
+    pub fn needed(&self) -> Vec<String> {
+        self.dynamic
+            .iter()
+            .filter(|entry| entry.tag == dynamic::Tag::Needed)
+            .filter_map(|entry| self.dynamic_string(entry.value))
+            .collect()
+    }
 }
@@ -0,0 +1,23 @@
+/// The magic string at the beginning of every `ar` archive.
+pub const MAGIC: &[u8; 8] = b"!<arch>\n";
+
+/// A static library (`ar` archive) containing relocatable object files.
+///
+/// Only the regular members are kept. The long name table is consumed while
+/// parsing, and the symbol table is turned into `symbols`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Archive {
+    pub members: Vec<Member>,
+    /// Global symbols defined by the members, with the index of the defining
+    /// member, if the archive has a GNU symbol table.
+    pub symbols: Option<Vec<(String, usize)>>,
+}
+
+/// A single file stored in an archive.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Member {
+    /// File name of the member.
+    pub name: String,
+    /// Raw contents of the member.
+    pub data: Vec<u8>,
+}
@@ -0,0 +1,73 @@
+use super::header::Encoding;
+
+/// Tags of the entries of the dynamic section (`.dynamic`).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[repr(u64)]
+pub enum Tag {
+    Null = 0,                // Marks end of dynamic section
+    Needed = 1,              // Name of needed library
+    PltRelSz = 2,            // Size in bytes of PLT relocs
+    PltGot = 3,              // Processor defined value
+    Hash = 4,                // Address of symbol hash table
+    StrTab = 5,              // Address of string table
+    SymTab = 6,              // Address of symbol table
+    Rela = 7,                // Address of Rela relocs
+    RelaSz = 8,              // Total size of Rela relocs
+    RelaEnt = 9,             // Size of one Rela reloc
+    StrSz = 10,              // Size of string table
+    SymEnt = 11,             // Size of one symbol table entry
+    Init = 12,               // Address of init function
+    Fini = 13,               // Address of termination function
+    SoName = 14,             // Name of shared object
+    RPath = 15,              // Library search path (deprecated)
+    Symbolic = 16,           // Start symbol search here
+    Rel = 17,                // Address of Rel relocs
+    RelSz = 18,              // Total size of Rel relocs
+    RelEnt = 19,             // Size of one Rel reloc
+    PltRel = 20,             // Type of reloc in PLT
+    Debug = 21,              // For debugging; unspecified
+    TextRel = 22,            // Reloc might modify .text
+    JmpRel = 23,             // Address of PLT relocs
+    BindNow = 24,            // Process relocations of object
+    InitArray = 25,          // Array with addresses of init fct
+    FiniArray = 26,          // Array with addresses of fini fct
+    InitArraySz = 27,        // Size in bytes of DT_INIT_ARRAY
+    FiniArraySz = 28,        // Size in bytes of DT_FINI_ARRAY
+    RunPath = 29,            // Library search path
+    Flags = 30,              // Flags for the object being loaded
+    PreInitArray = 32,       // Array with addresses of preinit fct
+    PreInitArraySz = 33,     // Size in bytes of DT_PREINIT_ARRAY
+    GnuHash = 0x6ffffef5,    // GNU-style hash table
+    VerSym = 0x6ffffff0,     // Address of the version symbol table
+    RelaCount = 0x6ffffff9,  // Number of relative relocations
+    Flags1 = 0x6ffffffb,     // State flags
+    VerDef = 0x6ffffffc,     // Address of version definition table
+    VerDefNum = 0x6ffffffd,  // Number of version definitions
+    VerNeed = 0x6ffffffe,    // Address of table with needed versions
+    VerNeedNum = 0x6fffffff, // Number of needed versions
+}
+
+/// `DF_1_NOW`: set RTLD_NOW for this object.
+pub const DF_1_NOW: u64 = 0x1;
+/// `DF_1_PIE`: the object is a position-independent executable.
+pub const DF_1_PIE: u64 = 0x08000000;
+
+/// A dynamic section entry (`Elf64_Dyn`).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Entry {
+    pub tag: Tag,
+    /// The address or value associated with the tag.
+    pub value: u64,
+}
+
+impl Entry {
+    /// Size of an `Elf64_Dyn` entry.
+    pub const SIZE: u64 = 16;
+
+    pub fn to_bytes(&self, encoding: Encoding) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        bytes[..8].copy_from_slice(&encoding.u64_bytes(self.tag as u64));
+        bytes[8..].copy_from_slice(&encoding.u64_bytes(self.value));
+        bytes
+    }
+}
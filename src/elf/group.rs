@@ -0,0 +1,25 @@
+/// `GRP_COMDAT`: only one copy of the group is kept in the link.
+pub const GRP_COMDAT: u32 = 0x1;
+
+/// A section group (`SHT_GROUP`).
+///
+/// Groups bind sections that must be kept or discarded together, such as the code,
+/// data and relocations of a C++ inline function or template instantiation.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Group {
+    /// Index of the `SHT_GROUP` section.
+    pub section_index: u16,
+    /// Name of the signature symbol that identifies the group.
+    pub signature: String,
+    /// Group flags (`GRP_COMDAT`).
+    pub flags: u32,
+    /// Indices of the member sections.
+    pub members: Vec<u16>,
+}
+
+impl Group {
+    /// Returns true if this is a COMDAT group, of which only one copy is kept.
+    pub fn is_comdat(&self) -> bool {
+        self.flags & GRP_COMDAT != 0
+    }
+}
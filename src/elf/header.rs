@@ -8,6 +8,48 @@ pub enum Class {
     Num = 3,
 }
 
+impl Class {
+    /// Size of an address, offset or size field: 4 bytes in ELF32, 8 bytes in ELF64.
+    pub fn word_size(self) -> u64 {
+        match self {
+            Class::Bit32 => 4,
+            _ => 8,
+        }
+    }
+
+    /// Size of the ELF header (`e_ehsize`).
+    pub fn header_size(self) -> u64 {
+        match self {
+            Class::Bit32 => 52,
+            _ => 64,
+        }
+    }
+
+    /// Size of a program header (`e_phentsize`).
+    pub fn program_header_size(self) -> u64 {
+        match self {
+            Class::Bit32 => 32,
+            _ => 56,
+        }
+    }
+
+    /// Size of a section header (`e_shentsize`).
+    pub fn section_header_size(self) -> u64 {
+        match self {
+            Class::Bit32 => 40,
+            _ => 64,
+        }
+    }
+
+    /// Size of a symbol table entry (`Elf32_Sym` or `Elf64_Sym`).
+    pub fn symbol_size(self) -> u64 {
+        match self {
+            Class::Bit32 => 16,
+            _ => 24,
+        }
+    }
+}
+
 #[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u8)]
 pub enum Data {
@@ -56,6 +98,91 @@ pub struct Ident {
     pub abi_version: u8,
 }
 
+impl Ident {
+    pub fn encoding(&self) -> Encoding {
+        Encoding {
+            class: self.class,
+            data: self.data,
+        }
+    }
+}
+
+/// How the fields of an ELF file are stored: the class sets the width of addresses,
+/// offsets and sizes, and the data encoding the byte order of every field.
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Encoding {
+    pub class: Class,
+    pub data: Data,
+}
+
+impl Encoding {
+    pub fn is_big_endian(self) -> bool {
+        self.data == Data::Msb
+    }
+
+    pub fn u16_bytes(self, value: u16) -> [u8; 2] {
+        if self.is_big_endian() {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    }
+
+    pub fn u32_bytes(self, value: u32) -> [u8; 4] {
+        if self.is_big_endian() {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    }
+
+    pub fn u64_bytes(self, value: u64) -> [u8; 8] {
+        if self.is_big_endian() {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    }
+
+    /// Returns the bytes of an address, offset or size field.
+    pub fn word_bytes(self, value: u64) -> Vec<u8> {
+        match self.class {
+            Class::Bit32 => self.u32_bytes(value as u32).to_vec(),
+            _ => self.u64_bytes(value).to_vec(),
+        }
+    }
+
+    /// Reads a 2-byte field at the start of `bytes`.
+    pub fn read_u16(self, bytes: &[u8]) -> u16 {
+        let bytes = bytes[..2].try_into().unwrap();
+        if self.is_big_endian() {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    /// Reads a 4-byte field at the start of `bytes`.
+    pub fn read_u32(self, bytes: &[u8]) -> u32 {
+        let bytes = bytes[..4].try_into().unwrap();
+        if self.is_big_endian() {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Reads an 8-byte field at the start of `bytes`.
+    pub fn read_u64(self, bytes: &[u8]) -> u64 {
+        let bytes = bytes[..8].try_into().unwrap();
+        if self.is_big_endian() {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        }
+    }
+}
+
 /// Represents the type of an ELF file.
 ///
 /// This enum defines various types of ELF files, such as relocatable files,
@@ -99,6 +226,19 @@ pub enum Machine {
     Num = 253,
 }
 
+impl std::fmt::Display for Machine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::None => "none",
+            Self::X86_64 => "x86-64",
+            Self::AArch64 => "AArch64",
+            Self::RiscV => "RISC-V",
+            Self::Num => "unknown",
+        };
+        f.write_str(name)
+    }
+}
+
 #[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
 #[repr(u32)]
 pub enum Version {
@@ -146,27 +286,31 @@ pub struct Header {
 
 impl Header {
     pub fn to_vec(&self) -> Vec<u8> {
+        let encoding = self.ident.encoding();
         [
             &[0x7f, b'E', b'L', b'F'],
-            (self.ident.class as u8).to_le_bytes().as_slice(),
-            (self.ident.data as u8).to_le_bytes().as_slice(),
-            (self.ident.version as u8).to_le_bytes().as_slice(),
-            (self.ident.os_abi as u8).to_le_bytes().as_slice(),
-            (self.ident.abi_version).to_le_bytes().as_slice(),
+            [
+                self.ident.class as u8,
+                self.ident.data as u8,
+                self.ident.version as u8,
+                self.ident.os_abi as u8,
+                self.ident.abi_version,
+            ]
+            .as_slice(),
             [0; 7].as_slice(), // Padding
-            (self.r#type as u16).to_le_bytes().as_slice(),
-            (self.machine as u16).to_le_bytes().as_slice(),
-            (self.version as u32).to_le_bytes().as_slice(),
-            self.entry.to_le_bytes().as_slice(),
-            self.phoff.to_le_bytes().as_slice(),
-            self.shoff.to_le_bytes().as_slice(),
-            self.flags.to_le_bytes().as_slice(),
-            self.ehsize.to_le_bytes().as_slice(),
-            self.phentsize.to_le_bytes().as_slice(),
-            self.phnum.to_le_bytes().as_slice(),
-            self.shentsize.to_le_bytes().as_slice(),
-            self.shnum.to_le_bytes().as_slice(),
-            self.shstrndx.to_le_bytes().as_slice(),
+            encoding.u16_bytes(self.r#type as u16).as_slice(),
+            encoding.u16_bytes(self.machine as u16).as_slice(),
+            encoding.u32_bytes(self.version as u32).as_slice(),
+            encoding.word_bytes(self.entry).as_slice(),
+            encoding.word_bytes(self.phoff).as_slice(),
+            encoding.word_bytes(self.shoff).as_slice(),
+            encoding.u32_bytes(self.flags).as_slice(),
+            encoding.u16_bytes(self.ehsize).as_slice(),
+            encoding.u16_bytes(self.phentsize).as_slice(),
+            encoding.u16_bytes(self.phnum).as_slice(),
+            encoding.u16_bytes(self.shentsize).as_slice(),
+            encoding.u16_bytes(self.shnum).as_slice(),
+            encoding.u16_bytes(self.shstrndx).as_slice(),
         ]
         .concat()
     }
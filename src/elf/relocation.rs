@@ -1,9 +1,280 @@
-#[derive(Debug, PartialEq, Eq)]
+/// The relocation types of each machine reuse the same numbers, so the variants of
+/// x86-64 and RISC-V are offset by a multiple of this value.
+/// [`RelocationType::value`] gives the number stored in `r_info`.
+const MACHINE_STRIDE: isize = 0x10000;
+const X86_64_BASE: isize = MACHINE_STRIDE;
+const RISCV_BASE: isize = 2 * MACHINE_STRIDE;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum RelocationType {
+    Aarch64None = 0,
+    Aarch64Abs64 = 257,
+    Aarch64Abs32 = 258,
+    Aarch64Abs16 = 259,
+    Aarch64Prel64 = 260,
+    Aarch64Prel32 = 261,
+    Aarch64Prel16 = 262,
+    Aarch64MovwUabsG0 = 263,
+    Aarch64MovwUabsG0Nc = 264,
+    Aarch64MovwUabsG1 = 265,
+    Aarch64MovwUabsG1Nc = 266,
+    Aarch64MovwUabsG2 = 267,
+    Aarch64MovwUabsG2Nc = 268,
+    Aarch64MovwUabsG3 = 269,
+    Aarch64MovwSabsG0 = 270,
+    Aarch64MovwSabsG1 = 271,
+    Aarch64MovwSabsG2 = 272,
+    Aarch64LdPrelLo19 = 273,
     Aarch64AdrPrelLo21 = 274,
+    Aarch64AdrPrelPgHi21 = 275,
+    Aarch64AdrPrelPgHi21Nc = 276,
+    Aarch64AddAbsLo12Nc = 277,
+    Aarch64Ldst8AbsLo12Nc = 278,
+    Aarch64Tstbr14 = 279,
+    Aarch64Condbr19 = 280,
+    Aarch64Jump26 = 282,
+    Aarch64Call26 = 283,
+    Aarch64Ldst16AbsLo12Nc = 284,
+    Aarch64Ldst32AbsLo12Nc = 285,
+    Aarch64Ldst64AbsLo12Nc = 286,
+    Aarch64MovwPrelG0 = 287,
+    Aarch64MovwPrelG0Nc = 288,
+    Aarch64MovwPrelG1 = 289,
+    Aarch64MovwPrelG1Nc = 290,
+    Aarch64MovwPrelG2 = 291,
+    Aarch64MovwPrelG2Nc = 292,
+    Aarch64MovwPrelG3 = 293,
+    Aarch64Ldst128AbsLo12Nc = 299,
+    Aarch64AdrGotPage = 311,
+    Aarch64Ld64GotLo12Nc = 312,
+    Aarch64Ld64GotPageLo15 = 313,
+    Aarch64TlsIeAdrGotTprelPage21 = 541,
+    Aarch64TlsIeLd64GotTprelLo12Nc = 542,
+    Aarch64TlsLeMovwTprelG2 = 544,
+    Aarch64TlsLeMovwTprelG1 = 545,
+    Aarch64TlsLeMovwTprelG1Nc = 546,
+    Aarch64TlsLeMovwTprelG0 = 547,
+    Aarch64TlsLeMovwTprelG0Nc = 548,
+    Aarch64TlsLeAddTprelHi12 = 549,
+    Aarch64TlsLeAddTprelLo12 = 550,
+    Aarch64TlsLeAddTprelLo12Nc = 551,
+    Aarch64TlsLeLdst8TprelLo12 = 552,
+    Aarch64TlsLeLdst8TprelLo12Nc = 553,
+    Aarch64TlsLeLdst16TprelLo12 = 554,
+    Aarch64TlsLeLdst16TprelLo12Nc = 555,
+    Aarch64TlsLeLdst32TprelLo12 = 556,
+    Aarch64TlsLeLdst32TprelLo12Nc = 557,
+    Aarch64TlsLeLdst64TprelLo12 = 558,
+    Aarch64TlsLeLdst64TprelLo12Nc = 559,
+    Aarch64TlsDescAdrPage21 = 562,
+    Aarch64TlsDescLd64Lo12 = 563,
+    Aarch64TlsDescAddLo12 = 564,
+    Aarch64TlsDescCall = 569,
+    Aarch64TlsLeLdst128TprelLo12 = 570,
+    Aarch64TlsLeLdst128TprelLo12Nc = 571,
+    Aarch64Copy = 1024,
+    Aarch64GlobDat = 1025,
+    Aarch64JumpSlot = 1026,
+    Aarch64Relative = 1027,
+    Aarch64TlsDtpMod64 = 1028,
+    Aarch64TlsDtpRel64 = 1029,
+    Aarch64TlsTpRel64 = 1030,
+    Aarch64TlsDesc = 1031,
+    Aarch64IRelative = 1032,
+    X86_64None = X86_64_BASE,
+    X86_64_64 = X86_64_BASE + 1,
+    X86_64Pc32 = X86_64_BASE + 2,
+    X86_64Plt32 = X86_64_BASE + 4,
+    X86_64Copy = X86_64_BASE + 5,
+    X86_64GlobDat = X86_64_BASE + 6,
+    X86_64JumpSlot = X86_64_BASE + 7,
+    X86_64Relative = X86_64_BASE + 8,
+    X86_64GotPcRel = X86_64_BASE + 9,
+    X86_64_32 = X86_64_BASE + 10,
+    X86_64_32S = X86_64_BASE + 11,
+    X86_64DtpMod64 = X86_64_BASE + 16,
+    X86_64DtpOff64 = X86_64_BASE + 17,
+    X86_64TpOff64 = X86_64_BASE + 18,
+    X86_64IRelative = X86_64_BASE + 37,
+    X86_64GotPcRelX = X86_64_BASE + 41,
+    X86_64RexGotPcRelX = X86_64_BASE + 42,
+    RiscvNone = RISCV_BASE,
+    Riscv32 = RISCV_BASE + 1,
+    Riscv64 = RISCV_BASE + 2,
+    RiscvRelative = RISCV_BASE + 3,
+    RiscvCopy = RISCV_BASE + 4,
+    RiscvJumpSlot = RISCV_BASE + 5,
+    RiscvTlsTpRel64 = RISCV_BASE + 11,
+    RiscvBranch = RISCV_BASE + 16,
+    RiscvJal = RISCV_BASE + 17,
+    RiscvCall = RISCV_BASE + 18,
+    RiscvCallPlt = RISCV_BASE + 19,
+    RiscvPcrelHi20 = RISCV_BASE + 23,
+    RiscvPcrelLo12I = RISCV_BASE + 24,
+    RiscvPcrelLo12S = RISCV_BASE + 25,
+    RiscvHi20 = RISCV_BASE + 26,
+    RiscvLo12I = RISCV_BASE + 27,
+    RiscvLo12S = RISCV_BASE + 28,
+    RiscvAdd8 = RISCV_BASE + 33,
+    RiscvAdd16 = RISCV_BASE + 34,
+    RiscvAdd32 = RISCV_BASE + 35,
+    RiscvAdd64 = RISCV_BASE + 36,
+    RiscvSub8 = RISCV_BASE + 37,
+    RiscvSub16 = RISCV_BASE + 38,
+    RiscvSub32 = RISCV_BASE + 39,
+    RiscvSub64 = RISCV_BASE + 40,
+    RiscvAlign = RISCV_BASE + 43,
+    RiscvRelax = RISCV_BASE + 51,
+    RiscvSub6 = RISCV_BASE + 52,
+    RiscvSet6 = RISCV_BASE + 53,
+    RiscvSet8 = RISCV_BASE + 54,
+    RiscvSet16 = RISCV_BASE + 55,
+    RiscvSet32 = RISCV_BASE + 56,
+    Riscv32Pcrel = RISCV_BASE + 57,
+    RiscvIRelative = RISCV_BASE + 58,
     // TODO: Add more relocation types
 }
 
+impl std::fmt::Display for RelocationType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Aarch64None => "R_AARCH64_NONE",
+            Self::Aarch64Abs64 => "R_AARCH64_ABS64",
+            Self::Aarch64Abs32 => "R_AARCH64_ABS32",
+            Self::Aarch64Abs16 => "R_AARCH64_ABS16",
+            Self::Aarch64Prel64 => "R_AARCH64_PREL64",
+            Self::Aarch64Prel32 => "R_AARCH64_PREL32",
+            Self::Aarch64Prel16 => "R_AARCH64_PREL16",
+            Self::Aarch64MovwUabsG0 => "R_AARCH64_MOVW_UABS_G0",
+            Self::Aarch64MovwUabsG0Nc => "R_AARCH64_MOVW_UABS_G0_NC",
+            Self::Aarch64MovwUabsG1 => "R_AARCH64_MOVW_UABS_G1",
+            Self::Aarch64MovwUabsG1Nc => "R_AARCH64_MOVW_UABS_G1_NC",
+            Self::Aarch64MovwUabsG2 => "R_AARCH64_MOVW_UABS_G2",
+            Self::Aarch64MovwUabsG2Nc => "R_AARCH64_MOVW_UABS_G2_NC",
+            Self::Aarch64MovwUabsG3 => "R_AARCH64_MOVW_UABS_G3",
+            Self::Aarch64MovwSabsG0 => "R_AARCH64_MOVW_SABS_G0",
+            Self::Aarch64MovwSabsG1 => "R_AARCH64_MOVW_SABS_G1",
+            Self::Aarch64MovwSabsG2 => "R_AARCH64_MOVW_SABS_G2",
+            Self::Aarch64LdPrelLo19 => "R_AARCH64_LD_PREL_LO19",
+            Self::Aarch64AdrPrelLo21 => "R_AARCH64_ADR_PREL_LO21",
+            Self::Aarch64AdrPrelPgHi21 => "R_AARCH64_ADR_PREL_PG_HI21",
+            Self::Aarch64AdrPrelPgHi21Nc => "R_AARCH64_ADR_PREL_PG_HI21_NC",
+            Self::Aarch64AddAbsLo12Nc => "R_AARCH64_ADD_ABS_LO12_NC",
+            Self::Aarch64Ldst8AbsLo12Nc => "R_AARCH64_LDST8_ABS_LO12_NC",
+            Self::Aarch64Tstbr14 => "R_AARCH64_TSTBR14",
+            Self::Aarch64Condbr19 => "R_AARCH64_CONDBR19",
+            Self::Aarch64Jump26 => "R_AARCH64_JUMP26",
+            Self::Aarch64Call26 => "R_AARCH64_CALL26",
+            Self::Aarch64Ldst16AbsLo12Nc => "R_AARCH64_LDST16_ABS_LO12_NC",
+            Self::Aarch64Ldst32AbsLo12Nc => "R_AARCH64_LDST32_ABS_LO12_NC",
+            Self::Aarch64Ldst64AbsLo12Nc => "R_AARCH64_LDST64_ABS_LO12_NC",
+            Self::Aarch64MovwPrelG0 => "R_AARCH64_MOVW_PREL_G0",
+            Self::Aarch64MovwPrelG0Nc => "R_AARCH64_MOVW_PREL_G0_NC",
+            Self::Aarch64MovwPrelG1 => "R_AARCH64_MOVW_PREL_G1",
+            Self::Aarch64MovwPrelG1Nc => "R_AARCH64_MOVW_PREL_G1_NC",
+            Self::Aarch64MovwPrelG2 => "R_AARCH64_MOVW_PREL_G2",
+            Self::Aarch64MovwPrelG2Nc => "R_AARCH64_MOVW_PREL_G2_NC",
+            Self::Aarch64MovwPrelG3 => "R_AARCH64_MOVW_PREL_G3",
+            Self::Aarch64Ldst128AbsLo12Nc => "R_AARCH64_LDST128_ABS_LO12_NC",
+            Self::Aarch64AdrGotPage => "R_AARCH64_ADR_GOT_PAGE",
+            Self::Aarch64Ld64GotLo12Nc => "R_AARCH64_LD64_GOT_LO12_NC",
+            Self::Aarch64Ld64GotPageLo15 => "R_AARCH64_LD64_GOTPAGE_LO15",
+            Self::Aarch64TlsIeAdrGotTprelPage21 => "R_AARCH64_TLSIE_ADR_GOTTPREL_PAGE21",
+            Self::Aarch64TlsIeLd64GotTprelLo12Nc => "R_AARCH64_TLSIE_LD64_GOTTPREL_LO12_NC",
+            Self::Aarch64TlsLeMovwTprelG2 => "R_AARCH64_TLSLE_MOVW_TPREL_G2",
+            Self::Aarch64TlsLeMovwTprelG1 => "R_AARCH64_TLSLE_MOVW_TPREL_G1",
+            Self::Aarch64TlsLeMovwTprelG1Nc => "R_AARCH64_TLSLE_MOVW_TPREL_G1_NC",
+            Self::Aarch64TlsLeMovwTprelG0 => "R_AARCH64_TLSLE_MOVW_TPREL_G0",
+            Self::Aarch64TlsLeMovwTprelG0Nc => "R_AARCH64_TLSLE_MOVW_TPREL_G0_NC",
+            Self::Aarch64TlsLeAddTprelHi12 => "R_AARCH64_TLSLE_ADD_TPREL_HI12",
+            Self::Aarch64TlsLeAddTprelLo12 => "R_AARCH64_TLSLE_ADD_TPREL_LO12",
+            Self::Aarch64TlsLeAddTprelLo12Nc => "R_AARCH64_TLSLE_ADD_TPREL_LO12_NC",
+            Self::Aarch64TlsLeLdst8TprelLo12 => "R_AARCH64_TLSLE_LDST8_TPREL_LO12",
+            Self::Aarch64TlsLeLdst8TprelLo12Nc => "R_AARCH64_TLSLE_LDST8_TPREL_LO12_NC",
+            Self::Aarch64TlsLeLdst16TprelLo12 => "R_AARCH64_TLSLE_LDST16_TPREL_LO12",
+            Self::Aarch64TlsLeLdst16TprelLo12Nc => "R_AARCH64_TLSLE_LDST16_TPREL_LO12_NC",
+            Self::Aarch64TlsLeLdst32TprelLo12 => "R_AARCH64_TLSLE_LDST32_TPREL_LO12",
+            Self::Aarch64TlsLeLdst32TprelLo12Nc => "R_AARCH64_TLSLE_LDST32_TPREL_LO12_NC",
+            Self::Aarch64TlsLeLdst64TprelLo12 => "R_AARCH64_TLSLE_LDST64_TPREL_LO12",
+            Self::Aarch64TlsLeLdst64TprelLo12Nc => "R_AARCH64_TLSLE_LDST64_TPREL_LO12_NC",
+            Self::Aarch64TlsDescAdrPage21 => "R_AARCH64_TLSDESC_ADR_PAGE21",
+            Self::Aarch64TlsDescLd64Lo12 => "R_AARCH64_TLSDESC_LD64_LO12",
+            Self::Aarch64TlsDescAddLo12 => "R_AARCH64_TLSDESC_ADD_LO12",
+            Self::Aarch64TlsDescCall => "R_AARCH64_TLSDESC_CALL",
+            Self::Aarch64TlsLeLdst128TprelLo12 => "R_AARCH64_TLSLE_LDST128_TPREL_LO12",
+            Self::Aarch64TlsLeLdst128TprelLo12Nc => "R_AARCH64_TLSLE_LDST128_TPREL_LO12_NC",
+            Self::Aarch64Copy => "R_AARCH64_COPY",
+            Self::Aarch64GlobDat => "R_AARCH64_GLOB_DAT",
+            Self::Aarch64JumpSlot => "R_AARCH64_JUMP_SLOT",
+            Self::Aarch64Relative => "R_AARCH64_RELATIVE",
+            Self::Aarch64TlsDtpMod64 => "R_AARCH64_TLS_DTPMOD64",
+            Self::Aarch64TlsDtpRel64 => "R_AARCH64_TLS_DTPREL64",
+            Self::Aarch64TlsTpRel64 => "R_AARCH64_TLS_TPREL64",
+            Self::Aarch64TlsDesc => "R_AARCH64_TLSDESC",
+            Self::Aarch64IRelative => "R_AARCH64_IRELATIVE",
+            Self::X86_64None => "R_X86_64_NONE",
+            Self::X86_64_64 => "R_X86_64_64",
+            Self::X86_64Pc32 => "R_X86_64_PC32",
+            Self::X86_64Plt32 => "R_X86_64_PLT32",
+            Self::X86_64Copy => "R_X86_64_COPY",
+            Self::X86_64GlobDat => "R_X86_64_GLOB_DAT",
+            Self::X86_64JumpSlot => "R_X86_64_JUMP_SLOT",
+            Self::X86_64Relative => "R_X86_64_RELATIVE",
+            Self::X86_64GotPcRel => "R_X86_64_GOTPCREL",
+            Self::X86_64_32 => "R_X86_64_32",
+            Self::X86_64_32S => "R_X86_64_32S",
+            Self::X86_64DtpMod64 => "R_X86_64_DTPMOD64",
+            Self::X86_64DtpOff64 => "R_X86_64_DTPOFF64",
+            Self::X86_64TpOff64 => "R_X86_64_TPOFF64",
+            Self::X86_64IRelative => "R_X86_64_IRELATIVE",
+            Self::X86_64GotPcRelX => "R_X86_64_GOTPCRELX",
+            Self::X86_64RexGotPcRelX => "R_X86_64_REX_GOTPCRELX",
+            Self::RiscvNone => "R_RISCV_NONE",
+            Self::Riscv32 => "R_RISCV_32",
+            Self::Riscv64 => "R_RISCV_64",
+            Self::RiscvRelative => "R_RISCV_RELATIVE",
+            Self::RiscvCopy => "R_RISCV_COPY",
+            Self::RiscvJumpSlot => "R_RISCV_JUMP_SLOT",
+            Self::RiscvTlsTpRel64 => "R_RISCV_TLS_TPREL64",
+            Self::RiscvBranch => "R_RISCV_BRANCH",
+            Self::RiscvJal => "R_RISCV_JAL",
+            Self::RiscvCall => "R_RISCV_CALL",
+            Self::RiscvCallPlt => "R_RISCV_CALL_PLT",
+            Self::RiscvPcrelHi20 => "R_RISCV_PCREL_HI20",
+            Self::RiscvPcrelLo12I => "R_RISCV_PCREL_LO12_I",
+            Self::RiscvPcrelLo12S => "R_RISCV_PCREL_LO12_S",
+            Self::RiscvHi20 => "R_RISCV_HI20",
+            Self::RiscvLo12I => "R_RISCV_LO12_I",
+            Self::RiscvLo12S => "R_RISCV_LO12_S",
+            Self::RiscvAdd8 => "R_RISCV_ADD8",
+            Self::RiscvAdd16 => "R_RISCV_ADD16",
+            Self::RiscvAdd32 => "R_RISCV_ADD32",
+            Self::RiscvAdd64 => "R_RISCV_ADD64",
+            Self::RiscvSub8 => "R_RISCV_SUB8",
+            Self::RiscvSub16 => "R_RISCV_SUB16",
+            Self::RiscvSub32 => "R_RISCV_SUB32",
+            Self::RiscvSub64 => "R_RISCV_SUB64",
+            Self::RiscvAlign => "R_RISCV_ALIGN",
+            Self::RiscvRelax => "R_RISCV_RELAX",
+            Self::RiscvSub6 => "R_RISCV_SUB6",
+            Self::RiscvSet6 => "R_RISCV_SET6",
+            Self::RiscvSet8 => "R_RISCV_SET8",
+            Self::RiscvSet16 => "R_RISCV_SET16",
+            Self::RiscvSet32 => "R_RISCV_SET32",
+            Self::Riscv32Pcrel => "R_RISCV_32_PCREL",
+            Self::RiscvIRelative => "R_RISCV_IRELATIVE",
+        };
+        f.write_str(name)
+    }
+}
+
+impl RelocationType {
+    /// Returns the number of the relocation type, as stored in `r_info`.
+    pub fn value(self) -> u32 {
+        (self as isize % MACHINE_STRIDE) as u32
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub struct Info {
     pub r#type: RelocationType,
@@ -25,3 +296,12 @@ pub struct RelocationAddend {
     /// The constant addend used to compute the value to be stored into the relocatable field.
     pub addend: i64,
 }
+
+/// The relocation entries of a single relocation section (e.g. `.rela.text`).
+#[derive(Debug, PartialEq, Eq)]
+pub struct RelocationSection {
+    /// Index of the section the relocations apply to (`sh_info` of the relocation section).
+    pub target_section: u16,
+    /// The relocation entries.
+    pub entries: Vec<RelocationAddend>,
+}
@@ -39,6 +39,7 @@ pub enum SectionFlag {
     Group = 1 << 9,       // Section is member of a group
     Tls = 1 << 10,        // Section hold thread-local data
     Compressed = 1 << 11, // Section with compressed data
+    GnuRetain = 1 << 21,  // Not to be GCed by the linker
 }
 
 /// Represents the header of an ELF section.
@@ -39,7 +39,7 @@ impl From<Info> for u8 {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Visibility {
     Default = 0,   // Default visibility rules
     Internal = 1,  // Processor-specific hidden class
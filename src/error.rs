@@ -35,6 +35,10 @@ pub enum LinkerError {
     #[error("Unresolved symbols found:\n{}", format_unresolved_symbols(.symbols))]
     UnresolvedSymbols { symbols: Vec<UnresolvedSymbol> },
 
+    /// Symbol given with `--require-defined` is not defined by any input
+    #[error("Required symbol '{symbol_name}' is not defined")]
+    RequiredSymbolNotDefined { symbol_name: String },
+
     /// Entry point symbol not found
     #[error("Entry point symbol '{entry_symbol}' not found")]
     MissingEntryPoint { entry_symbol: String },
@@ -75,6 +79,18 @@ pub enum LinkerError {
         context: Option<String>,
     },
 
+    /// Invalid command line argument
+    #[error("Invalid argument '{argument}': {message}")]
+    InvalidArgument { argument: String, message: String },
+
+    /// Invalid expression in a symbol assignment
+    #[error("Invalid expression '{expression}': {message}")]
+    InvalidExpression { expression: String, message: String },
+
+    /// Invalid version script given with `--version-script`
+    #[error("Invalid version script: {message}")]
+    InvalidVersionScript { message: String },
+
     /// Generic error for other cases
     #[error("Linker error{}: {message}", context.as_ref().map(|c| format!(" ({})", c)).unwrap_or_default())]
     Generic {
@@ -160,6 +176,29 @@ impl LinkerError {
         }
     }
 
+    /// Create an invalid argument error
+    pub fn invalid_argument<A: Into<String>, S: Into<String>>(argument: A, message: S) -> Self {
+        LinkerError::InvalidArgument {
+            argument: argument.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Create an invalid expression error
+    pub fn invalid_expression<E: Into<String>, S: Into<String>>(expression: E, message: S) -> Self {
+        LinkerError::InvalidExpression {
+            expression: expression.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Create an invalid version script error
+    pub fn invalid_version_script<S: Into<String>>(message: S) -> Self {
+        LinkerError::InvalidVersionScript {
+            message: message.into(),
+        }
+    }
+
     /// Create an unresolved symbols error
     pub fn unresolved_symbols(symbols: Vec<UnresolvedSymbol>) -> Self {
         LinkerError::UnresolvedSymbols { symbols }
@@ -1,5 +1,6 @@
 mod macros;
 
+pub mod cli;
 pub mod elf;
 pub mod error;
 pub mod linker;
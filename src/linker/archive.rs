@@ -0,0 +1,194 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::elf::ELF;
+use crate::elf::archive::Archive;
+use crate::elf::symbol::{Binding, SymbolIndex};
+use crate::error::Result;
+use crate::parser;
+
+use super::{Linker, parse_error};
+
+/// An archive member that has not been pulled into the link yet. It is only
+/// parsed once it is.
+#[derive(Debug)]
+pub(super) struct ArchiveMember {
+    /// Name of the member, as `archive(member)`.
+    name: String,
+    data: Vec<u8>,
+    /// Global symbols defined by the member.
+    symbols: Vec<String>,
+    /// Context of the errors found while parsing the member.
+    context: String,
+}
+
+/// Names of the global symbols defined and referenced by the objects in the link.
+#[derive(Debug, Default)]
+struct SymbolNames {
+    defined: HashSet<String>,
+    /// Symbols that are referenced but not defined.
+    undefined: HashSet<String>,
+}
+
+impl SymbolNames {
+    fn define(&mut self, name: &str) {
+        self.undefined.remove(name);
+        self.defined.insert(name.to_string());
+    }
+
+    /// Records a reference and returns true if it makes the symbol undefined.
+    fn reference(&mut self, name: String) -> bool {
+        !self.defined.contains(&name) && self.undefined.insert(name)
+    }
+}
+
+impl Linker {
+    /// Keeps the members of an archive aside until
+    /// [`Linker::extract_archive_members`] finds that they are needed.
+    ///
+    /// The symbol table of the archive tells which symbols each member defines.
+    /// Archives without one have their members parsed to find out.
+    pub(super) fn add_archive(
+        &mut self,
+        name: &str,
+        archive: Archive,
+        context: &str,
+    ) -> Result<()> {
+        let mut members: Vec<ArchiveMember> = archive
+            .members
+            .into_iter()
+            .map(|member| ArchiveMember {
+                name: format!("{}({})", name, member.name),
+                data: member.data,
+                symbols: Vec::new(),
+                context: format!("{} ({})", context, member.name),
+            })
+            .collect();
+
+        match archive.symbols {
+            Some(symbols) => {
+                for (symbol, idx) in symbols {
+                    members[idx].symbols.push(symbol);
+                }
+            }
+            None => {
+                for member in &mut members {
+                    member.symbols = defined_symbols(&member.parse()?);
+                }
+            }
+        }
+
+        self.archive_members.extend(members.into_iter().map(Some));
+        Ok(())
+    }
+
+    /// Pulls archive members into the link as long as they define a symbol that is
+    /// still undefined.
+    ///
+    /// The undefined set is seeded with the symbols given by `-u` and
+    /// `--require-defined`, so members that are never referenced directly can be
+    /// forced into the link. All archives are searched repeatedly until no more
+    /// members are needed, so the order of archives on the command line does not matter.
+    pub fn extract_archive_members(&mut self) -> Result<()> {
+        let mut providers: HashMap<String, Vec<usize>> = HashMap::new();
+        for (idx, member) in self.archive_members.iter().enumerate() {
+            for symbol in member.iter().flat_map(|member| &member.symbols) {
+                providers.entry(symbol.clone()).or_default().push(idx);
+            }
+        }
+        let providers_of = |name: &String| providers.get(name).into_iter().flatten().copied();
+
+        let mut names = self.symbol_names();
+        // members that define an undefined symbol, pulled in archive order
+        let mut candidates: BTreeSet<usize> =
+            names.undefined.iter().flat_map(providers_of).collect();
+
+        while let Some(idx) = candidates.pop_first() {
+            let Some(member) = self.archive_members[idx].take_if(|member| {
+                member
+                    .symbols
+                    .iter()
+                    .any(|symbol| names.undefined.contains(symbol))
+            }) else {
+                continue;
+            };
+            let elf = member.parse()?;
+            self.check_machine(&member.name, &elf.header)?;
+            for name in self.record_symbols(&elf, &mut names) {
+                candidates.extend(providers_of(&name));
+            }
+            self.add_object(member.name, elf);
+        }
+
+        Ok(())
+    }
+
+    /// Collects the names of symbols that are defined and referenced by the objects
+    /// loaded so far, including the symbols requested on the command line.
+    fn symbol_names(&self) -> SymbolNames {
+        let mut names = SymbolNames::default();
+
+        for assignment in &self.options.defsym {
+            names.define(&assignment.name);
+        }
+        for symbol in self
+            .shared_objects
+            .iter()
+            .flat_map(|shared| &shared.symbols)
+        {
+            names.define(&symbol.name);
+        }
+        for obj in &self.objects {
+            self.record_symbols(obj, &mut names);
+        }
+        for name in self
+            .options
+            .undefined
+            .iter()
+            .chain(self.options.require_defined.iter())
+        {
+            names.reference(name.clone());
+        }
+
+        names
+    }
+
+    /// Adds the global symbols of an object to `names`, and returns the symbols
+    /// that it leaves undefined and that were not referenced before.
+    fn record_symbols(&self, elf: &ELF, names: &mut SymbolNames) -> Vec<String> {
+        for name in defined_symbols(elf) {
+            names.define(&name);
+        }
+
+        elf.symbols
+            .iter()
+            .skip(1)
+            // weak references do not pull members out of archives
+            .filter(|symbol| {
+                !matches!(symbol.info.binding, Binding::Local | Binding::Weak)
+                    && SymbolIndex::Undefined == symbol.shndx
+            })
+            .map(|symbol| self.reference_name(symbol).into_owned())
+            .filter(|name| names.reference(name.clone()))
+            .collect()
+    }
+}
+
+impl ArchiveMember {
+    fn parse(&self) -> Result<ELF> {
+        parser::parse_elf(&self.data)
+            .map(|(_, elf)| elf)
+            .map_err(|e| parse_error(e, self.context.clone()))
+    }
+}
+
+/// Returns the names of the global symbols that an object defines.
+fn defined_symbols(elf: &ELF) -> Vec<String> {
+    elf.symbols
+        .iter()
+        .skip(1)
+        .filter(|symbol| {
+            symbol.info.binding != Binding::Local && SymbolIndex::Undefined != symbol.shndx
+        })
+        .map(|symbol| symbol.name.clone())
+        .collect()
+}
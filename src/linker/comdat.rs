@@ -0,0 +1,55 @@
+use crate::elf::ELF;
+
+use super::Linker;
+use super::gc::InputSectionId;
+
+impl Linker {
+    /// Adds a parsed object to the link and deduplicates its COMDAT groups.
+    ///
+    /// The first group with a given signature is kept. The members of later groups
+    /// with the same signature are discarded: symbols they define are treated as
+    /// references to the kept copy, and local references to them are redirected to
+    /// the member of the kept group with the same name, as GNU ld does.
+    pub(super) fn add_object(&mut self, name: String, elf: ELF) {
+        let obj_idx = self.objects.len();
+
+        for group in elf.groups.iter().filter(|g| g.is_comdat()) {
+            let Some(&kept_obj_idx) = self.comdat_groups.get(&group.signature) else {
+                self.comdat_groups.insert(group.signature.clone(), obj_idx);
+                continue;
+            };
+
+            let kept_obj = &self.objects[kept_obj_idx];
+            let kept_members = kept_obj
+                .groups
+                .iter()
+                .find(|g| g.is_comdat() && g.signature == group.signature)
+                .map(|g| g.members.as_slice())
+                .unwrap_or_default();
+
+            for &member in &group.members {
+                let name = &elf.section_headers[member as usize].name;
+                let replacement = kept_members
+                    .iter()
+                    .find(|&&kept| kept_obj.section_headers[kept as usize].name == *name)
+                    .map(|&kept| (kept_obj_idx, kept));
+                self.discarded_sections
+                    .insert((obj_idx, member), replacement);
+            }
+        }
+
+        self.objects.push(elf);
+        self.object_names.push(name);
+    }
+
+    /// Returns true if the input section belongs to a discarded COMDAT group.
+    pub(super) fn is_discarded(&self, (obj_idx, section_idx): InputSectionId) -> bool {
+        self.discarded_sections
+            .contains_key(&(obj_idx, section_idx))
+    }
+
+    /// Returns the section of the kept COMDAT group that replaces a discarded section.
+    pub(super) fn comdat_replacement(&self, id: InputSectionId) -> Option<InputSectionId> {
+        self.discarded_sections.get(&id).copied().flatten()
+    }
+}
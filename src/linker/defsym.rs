@@ -0,0 +1,471 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::elf::symbol::{self, SymbolIndex};
+use crate::error::{LinkerError, Result};
+
+use super::Linker;
+use super::output::ResolvedSymbol;
+
+/// Object index used for symbols that are defined on the command line.
+pub const COMMAND_LINE_OBJECT: usize = usize::MAX;
+
+/// A symbol assignment given with `--defsym=name=expression`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolAssignment {
+    pub name: String,
+    pub expr: Expr,
+}
+
+impl std::str::FromStr for SymbolAssignment {
+    type Err = LinkerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((name, expr)) = s.split_once('=') else {
+            return Err(LinkerError::invalid_expression(
+                s,
+                "expected 'symbol=expression'",
+            ));
+        };
+        let name = name.trim();
+        if !is_symbol_start(name.chars().next().unwrap_or('0')) || !name.chars().all(is_symbol_char)
+        {
+            return Err(LinkerError::invalid_expression(s, "invalid symbol name"));
+        }
+
+        Ok(SymbolAssignment {
+            name: name.to_string(),
+            expr: expr.parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// An integer expression that may reference symbols.
+///
+/// ```text
+/// expr    := term (('+' | '-') term)*
+/// term    := unary (('*' | '/' | '%') unary)*
+/// unary   := '-' unary | primary
+/// primary := number | symbol | '(' expr ')'
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Number(u64),
+    Symbol(String),
+    Neg(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+/// The result of evaluating an expression.
+///
+/// `base` holds the symbol the value is relative to, if the expression is of the
+/// form `symbol ± constant`. Otherwise the value is absolute.
+#[derive(Debug, Clone, Copy)]
+pub struct Value<'a> {
+    pub value: u64,
+    pub base: Option<&'a ResolvedSymbol>,
+}
+
+impl std::str::FromStr for Expr {
+    type Err = LinkerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parser = ExprParser {
+            input: s,
+            chars: s.char_indices().peekable(),
+        };
+        let expr = parser.expr()?;
+        parser.skip_whitespace();
+        if let Some((_, c)) = parser.chars.peek() {
+            return Err(LinkerError::invalid_expression(
+                s,
+                format!("unexpected character '{}'", c),
+            ));
+        }
+        Ok(expr)
+    }
+}
+
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{:#x}", n),
+            Expr::Symbol(name) => write!(f, "{}", name),
+            Expr::Neg(expr) => write!(f, "-{}", expr),
+            Expr::Binary(op, lhs, rhs) => {
+                let op = match op {
+                    BinaryOp::Add => '+',
+                    BinaryOp::Sub => '-',
+                    BinaryOp::Mul => '*',
+                    BinaryOp::Div => '/',
+                    BinaryOp::Rem => '%',
+                };
+                write!(f, "({} {} {})", lhs, op, rhs)
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// Returns the names of the symbols referenced by the expression.
+    pub fn symbols(&self) -> Vec<&str> {
+        match self {
+            Expr::Number(_) => vec![],
+            Expr::Symbol(name) => vec![name.as_str()],
+            Expr::Neg(expr) => expr.symbols(),
+            Expr::Binary(_, lhs, rhs) => [lhs.symbols(), rhs.symbols()].concat(),
+        }
+    }
+
+    /// Evaluates the expression, looking up symbol values in `symbols`.
+    pub fn evaluate<'a>(&self, symbols: &'a HashMap<String, ResolvedSymbol>) -> Result<Value<'a>> {
+        let absolute = |value| Value { value, base: None };
+        match self {
+            Expr::Number(n) => Ok(absolute(*n)),
+            Expr::Symbol(name) => match symbols.get(name) {
+                Some(symbol) if symbol.is_defined => Ok(Value {
+                    value: symbol.value,
+                    base: (SymbolIndex::Abs != symbol.shndx).then_some(symbol),
+                }),
+                _ => Err(LinkerError::invalid_expression(
+                    name,
+                    "undefined symbol referenced in expression",
+                )),
+            },
+            Expr::Neg(expr) => Ok(absolute(expr.evaluate(symbols)?.value.wrapping_neg())),
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = lhs.evaluate(symbols)?;
+                let rhs = rhs.evaluate(symbols)?;
+                let value = match op {
+                    BinaryOp::Add => lhs.value.wrapping_add(rhs.value),
+                    BinaryOp::Sub => lhs.value.wrapping_sub(rhs.value),
+                    BinaryOp::Mul => lhs.value.wrapping_mul(rhs.value),
+                    BinaryOp::Div | BinaryOp::Rem if rhs.value == 0 => {
+                        return Err(LinkerError::invalid_expression(
+                            self.to_string(),
+                            "division by zero",
+                        ));
+                    }
+                    BinaryOp::Div => lhs.value / rhs.value,
+                    BinaryOp::Rem => lhs.value % rhs.value,
+                };
+                // `symbol + constant`, `constant + symbol` and `symbol - constant` stay
+                // relative to the symbol's section; everything else is absolute.
+                let base = match (op, lhs.base, rhs.base) {
+                    (BinaryOp::Add, base, None) | (BinaryOp::Add, None, base) => base,
+                    (BinaryOp::Sub, base, None) => base,
+                    _ => None,
+                };
+                Ok(Value { value, base })
+            }
+        }
+    }
+}
+
+struct ExprParser<'a> {
+    input: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl ExprParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if(|(_, c)| *c == expected).is_some()
+    }
+
+    fn error(&self, message: &str) -> LinkerError {
+        LinkerError::invalid_expression(self.input, message)
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                BinaryOp::Add
+            } else if self.eat('-') {
+                BinaryOp::Sub
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                BinaryOp::Mul
+            } else if self.eat('/') {
+                BinaryOp::Div
+            } else if self.eat('%') {
+                BinaryOp::Rem
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat('-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        if self.eat('(') {
+            let expr = self.expr()?;
+            if !self.eat(')') {
+                return Err(self.error("expected ')'"));
+            }
+            return Ok(expr);
+        }
+
+        self.skip_whitespace();
+        let Some(&(start, c)) = self.chars.peek() else {
+            return Err(self.error("unexpected end of expression"));
+        };
+
+        if c.is_ascii_digit() {
+            let end = self.take_while(|c| c.is_ascii_alphanumeric());
+            let literal = &self.input[start..end];
+            let parsed = match literal.strip_prefix("0x").or(literal.strip_prefix("0X")) {
+                Some(hex) => u64::from_str_radix(hex, 16),
+                None => literal.parse(),
+            };
+            return parsed
+                .map(Expr::Number)
+                .map_err(|_| self.error("invalid number"));
+        }
+
+        if is_symbol_start(c) {
+            let end = self.take_while(is_symbol_char);
+            return Ok(Expr::Symbol(self.input[start..end].to_string()));
+        }
+
+        Err(self.error(&format!("unexpected character '{}'", c)))
+    }
+
+    /// Consumes characters while `pred` holds and returns the end offset.
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> usize {
+        while self.chars.next_if(|(_, c)| pred(*c)).is_some() {}
+        self.chars
+            .peek()
+            .map(|(i, _)| *i)
+            .unwrap_or(self.input.len())
+    }
+}
+
+fn is_symbol_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || matches!(c, '_' | '.' | '$')
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$')
+}
+
+impl Linker {
+    /// Defines the symbols given with `--defsym`.
+    ///
+    /// The symbols are created as absolute placeholders so that they satisfy
+    /// undefined references, and override definitions from input objects.
+    /// Their final values are assigned by [`Linker::assign_defsym_values`].
+    ///
+    /// A symbol whose assignment refers to the symbol itself, as in `foo=foo+1`, keeps
+    /// its definition from the input objects until it is assigned, as that is what the
+    /// expression refers to.
+    pub(super) fn define_command_line_symbols(
+        &self,
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) {
+        for assignment in &self.options.defsym {
+            let is_input_definition = resolved_symbols
+                .get(&assignment.name)
+                .is_some_and(|s| s.is_defined && s.object_index != COMMAND_LINE_OBJECT);
+            if is_input_definition && self.refers_to_itself(&assignment.name) {
+                continue;
+            }
+            resolved_symbols.insert(
+                assignment.name.clone(),
+                ResolvedSymbol {
+                    name: assignment.name.clone(),
+                    value: 0,
+                    size: 0,
+                    info: symbol::Info {
+                        r#type: symbol::Type::NoType,
+                        binding: symbol::Binding::Global,
+                    },
+                    visibility: symbol::Visibility::Default,
+                    shndx: SymbolIndex::Abs as u16,
+                    object_index: COMMAND_LINE_OBJECT,
+                    is_defined: true,
+                },
+            );
+        }
+    }
+
+    /// Evaluates `--defsym` expressions once the addresses of all other symbols are known.
+    ///
+    /// An expression may refer to symbols defined by other assignments, wherever they
+    /// are on the command line, so an assignment is evaluated once the assignments it
+    /// refers to have been. Assignments that refer to each other are rejected.
+    ///
+    /// A reference to the symbol being assigned is to the value of an earlier
+    /// assignment of that symbol, or else to its definition in the input objects.
+    pub(super) fn assign_defsym_values(
+        &self,
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) -> Result<()> {
+        let mut pending: Vec<(usize, &SymbolAssignment)> =
+            self.options.defsym.iter().enumerate().collect();
+        let mut assigned = HashSet::new();
+        while !pending.is_empty() {
+            // the first assignment that refers to no pending symbol, other than to
+            // later assignments of its own symbol
+            let ready = pending.iter().position(|&(idx, assignment)| {
+                let symbols = assignment.expr.symbols();
+                !pending.iter().any(|&(other_idx, other)| {
+                    symbols.contains(&other.name.as_str())
+                        && (other.name != assignment.name || other_idx < idx)
+                })
+            });
+            let Some(ready) = ready else {
+                let assignment = pending[0].1;
+                return Err(LinkerError::invalid_expression(
+                    format!("{}={}", assignment.name, assignment.expr),
+                    "circular reference between --defsym symbols",
+                ));
+            };
+            let (_, assignment) = pending.remove(ready);
+
+            // without an earlier assignment or an input definition, the symbol is
+            // still the placeholder
+            let name = assignment.name.as_str();
+            if assignment.expr.symbols().contains(&name)
+                && !assigned.contains(name)
+                && resolved_symbols
+                    .get(name)
+                    .is_none_or(|s| s.object_index == COMMAND_LINE_OBJECT)
+            {
+                return Err(LinkerError::invalid_expression(
+                    name,
+                    "undefined symbol referenced in expression",
+                ));
+            }
+
+            let (value, shndx, object_index) = {
+                let evaluated = assignment.expr.evaluate(resolved_symbols)?;
+                match evaluated.base {
+                    Some(base) => (evaluated.value, base.shndx, base.object_index),
+                    None => (
+                        evaluated.value,
+                        SymbolIndex::Abs as u16,
+                        COMMAND_LINE_OBJECT,
+                    ),
+                }
+            };
+
+            if let Some(symbol) = resolved_symbols.get_mut(&assignment.name) {
+                symbol.value = value;
+                symbol.shndx = shndx;
+                symbol.object_index = object_index;
+            }
+            assigned.insert(name);
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if an assignment of `name` refers to the symbol itself.
+    fn refers_to_itself(&self, name: &str) -> bool {
+        self.options
+            .defsym
+            .iter()
+            .any(|assignment| assignment.name == name && assignment.expr.symbols().contains(&name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn symbol(name: &str, value: u64, shndx: u16) -> (String, ResolvedSymbol) {
+        (
+            name.to_string(),
+            ResolvedSymbol {
+                name: name.to_string(),
+                value,
+                size: 0,
+                info: symbol::Info {
+                    r#type: symbol::Type::Func,
+                    binding: symbol::Binding::Global,
+                },
+                visibility: symbol::Visibility::Default,
+                shndx,
+                object_index: 0,
+                is_defined: true,
+            },
+        )
+    }
+
+    #[test]
+    fn should_parse_symbol_assignment() {
+        let assignment: SymbolAssignment = "alias=_start + 8".parse().unwrap();
+        assert_eq!(
+            assignment,
+            SymbolAssignment {
+                name: "alias".to_string(),
+                expr: Expr::Binary(
+                    BinaryOp::Add,
+                    Box::new(Expr::Symbol("_start".to_string())),
+                    Box::new(Expr::Number(8)),
+                ),
+            }
+        );
+    }
+
+    #[test]
+    fn should_error_invalid_expression() {
+        let err = "version=0x12+".parse::<SymbolAssignment>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid expression '0x12+': unexpected end of expression"
+        );
+    }
+
+    #[test]
+    fn should_evaluate_expression() {
+        let symbols = HashMap::from([symbol("_start", 0x400100, 1), symbol("abs", 0x10, 0xfff1)]);
+
+        let eval = |s: &str| s.parse::<Expr>().unwrap().evaluate(&symbols).unwrap();
+
+        let value = eval("(0x10 + 6) * 2 - 4 / 2 % 3");
+        assert_eq!((value.value, value.base.is_none()), (42, true));
+
+        let value = eval("_start + 8");
+        assert_eq!(value.value, 0x400108);
+        assert_eq!(value.base.map(|s| s.name.as_str()), Some("_start"));
+
+        let value = eval("_start - abs");
+        assert_eq!(value.value, 0x4000f0);
+        assert_eq!(value.base.map(|s| s.name.as_str()), Some("_start"));
+
+        let value = eval("_start - _start");
+        assert_eq!((value.value, value.base.is_none()), (0, true));
+    }
+}
@@ -0,0 +1,979 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::elf::dynamic::{self, Tag};
+use crate::elf::header::Encoding;
+use crate::elf::relocation::{RelocationAddend, RelocationType};
+use crate::elf::section::{SectionFlag, SectionType};
+use crate::elf::symbol::{self, Binding, SymbolIndex};
+use crate::error::{LinkerError, Result};
+
+use super::Linker;
+use super::defsym::COMMAND_LINE_OBJECT;
+use super::gc::InputSectionId;
+use super::ifunc::{RELA_IPLT_END, RELA_IPLT_START};
+use super::output::{DynamicRelocation, InputSection, ResolvedSymbol, Section};
+use super::section::{InputSectionMap, symbol_value, write_symbol_entry};
+use super::shared::SHARED_OBJECT;
+use super::tls::tls_segment;
+
+/// Object index used for the sections that the linker creates itself.
+pub(super) const SYNTHETIC_OBJECT: usize = usize::MAX - 1;
+
+/// Section index of `.dynamic` within [`SYNTHETIC_OBJECT`].
+pub(super) const DYNAMIC_SECTION: u16 = 1;
+
+/// Size of an `Elf64_Rela` entry
+pub(super) const RELA_ENTRY_SIZE: u64 = 24;
+
+/// Size of a GOT slot
+pub(super) const GOT_ENTRY_SIZE: u64 = 8;
+
+/// `.got.plt` starts with three slots reserved for the dynamic loader
+const GOT_PLT_RESERVED: u64 = 3;
+
+/// The dynamic loader used when none is given with `--dynamic-linker`.
+pub const DEFAULT_DYNAMIC_LINKER: &str = "/lib/ld-linux-aarch64.so.1";
+
+/// Names and section indices (within [`SYNTHETIC_OBJECT`]) of the synthetic sections.
+const INTERP: (&str, u16) = (".interp", 2);
+const HASH: (&str, u16) = (".hash", 3);
+const DYNSYM: (&str, u16) = (".dynsym", 4);
+const DYNSTR: (&str, u16) = (".dynstr", 5);
+const RELA_DYN: (&str, u16) = (".rela.dyn", 6);
+const RELA_PLT: (&str, u16) = (".rela.plt", 7);
+const PLT: (&str, u16) = (".plt", 8);
+pub(super) const GOT: (&str, u16) = (".got", 9);
+const GOT_PLT: (&str, u16) = (".got.plt", 10);
+pub(super) const RELA_IPLT: (&str, u16) = (".rela.iplt", 11);
+pub(super) const IPLT: (&str, u16) = (".iplt", 12);
+pub(super) const IGOT_PLT: (&str, u16) = (".igot.plt", 13);
+
+/// Symbol at the start of `.got`, which `R_AARCH64_LD64_GOTPAGE_LO15` is relative to.
+const GOT_SYMBOL: &str = "_GLOBAL_OFFSET_TABLE_";
+
+/// The target of a GOT slot.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(super) enum GotEntry {
+    /// A global symbol, by name.
+    Global(String),
+    /// A local symbol, as `(object index, symbol index)`.
+    Local(usize, u32),
+}
+
+/// The symbols, PLT entries and GOT slots needed by the relocations of the placed
+/// input sections.
+#[derive(Debug, Default)]
+pub(super) struct DynamicSymbols {
+    /// Symbols of `.dynsym`, after the null symbol.
+    pub symbols: Vec<String>,
+    /// Symbols with an entry in `.plt` and a slot in `.got.plt`.
+    pub plt: Vec<String>,
+    /// IFUNC symbols, with an entry in `.iplt` and a slot in `.igot.plt`.
+    pub iplt: Vec<String>,
+    /// Targets of the slots of `.got`.
+    pub got: Vec<GotEntry>,
+    /// Number of relocations in `.rela.dyn`.
+    pub relocation_count: usize,
+    /// True if `_GLOBAL_OFFSET_TABLE_` is referenced, which needs `.got` even
+    /// without slots.
+    pub got_symbol: bool,
+    /// True if `__rela_iplt_start` or `__rela_iplt_end` is referenced, which needs
+    /// `.rela.iplt` even without IFUNC symbols.
+    pub iplt_symbols: bool,
+    symbol_indices: HashMap<String, usize>,
+    plt_indices: HashMap<String, usize>,
+    iplt_indices: HashMap<String, usize>,
+    got_indices: HashMap<GotEntry, usize>,
+}
+
+impl DynamicSymbols {
+    /// Returns the index of a symbol in `.dynsym`.
+    pub fn symbol_index(&self, name: &str) -> Option<u32> {
+        // includes null symbol
+        self.symbol_indices.get(name).map(|&idx| idx as u32 + 1)
+    }
+
+    /// Returns the index of the GOT slot of a symbol.
+    pub fn got_index(&self, entry: &GotEntry) -> Option<usize> {
+        self.got_indices.get(entry).copied()
+    }
+
+    fn add_plt(&mut self, name: &str) {
+        if !self.plt_indices.contains_key(name) {
+            self.plt_indices.insert(name.to_string(), self.plt.len());
+            self.plt.push(name.to_string());
+        }
+    }
+
+    fn add_iplt(&mut self, name: &str) {
+        if !self.iplt_indices.contains_key(name) {
+            self.iplt_indices.insert(name.to_string(), self.iplt.len());
+            self.iplt.push(name.to_string());
+        }
+    }
+
+    /// Adds a GOT slot and returns true if it is new.
+    fn add_got(&mut self, entry: GotEntry) -> bool {
+        if self.got_indices.contains_key(&entry) {
+            return false;
+        }
+        self.got_indices.insert(entry.clone(), self.got.len());
+        self.got.push(entry);
+        true
+    }
+}
+
+impl DynamicRelocation {
+    pub(super) fn to_bytes(self, encoding: Encoding) -> [u8; RELA_ENTRY_SIZE as usize] {
+        let info = ((self.symbol as u64) << 32) | self.r#type.value() as u64;
+        let mut bytes = [0; RELA_ENTRY_SIZE as usize];
+        bytes[..8].copy_from_slice(&encoding.u64_bytes(self.offset));
+        bytes[8..16].copy_from_slice(&encoding.u64_bytes(info));
+        bytes[16..].copy_from_slice(&encoding.u64_bytes(self.addend as u64));
+        bytes
+    }
+}
+
+impl Linker {
+    /// Defines `_DYNAMIC`, the address of `.dynamic`, for dynamically linked and
+    /// position-independent output, `_GLOBAL_OFFSET_TABLE_`, the address of `.got`,
+    /// and `__rela_iplt_start` and `__rela_iplt_end`, the bounds of `.rela.iplt`, if
+    /// they are referenced.
+    pub(super) fn define_synthetic_symbols(
+        &self,
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) {
+        let got_referenced = resolved_symbols.contains_key(GOT_SYMBOL);
+        let iplt_referenced = [RELA_IPLT_START, RELA_IPLT_END]
+            .iter()
+            .any(|name| resolved_symbols.contains_key(*name));
+        let mut define = |name: &str, shndx: u16| {
+            if resolved_symbols.get(name).is_some_and(|s| s.is_defined) {
+                return;
+            }
+            resolved_symbols.insert(
+                name.to_string(),
+                ResolvedSymbol {
+                    name: name.to_string(),
+                    value: 0,
+                    size: 0,
+                    info: symbol::Info {
+                        r#type: symbol::Type::Object,
+                        binding: Binding::Global,
+                    },
+                    visibility: symbol::Visibility::Hidden,
+                    shndx,
+                    object_index: SYNTHETIC_OBJECT,
+                    is_defined: true,
+                },
+            );
+        };
+
+        if self.is_dynamic() {
+            define("_DYNAMIC", DYNAMIC_SECTION);
+        }
+        if got_referenced && !self.options.relocatable {
+            define(GOT_SYMBOL, GOT.1);
+        }
+        // the end is moved past the relocations once they are counted
+        if iplt_referenced && !self.options.relocatable {
+            define(RELA_IPLT_START, RELA_IPLT.1);
+            define(RELA_IPLT_END, RELA_IPLT.1);
+        }
+    }
+
+    /// Collects the dynamic symbols, PLT entries, GOT slots and dynamic relocations
+    /// needed by the relocations of the placed input sections.
+    pub(super) fn scan_dynamic_symbols(
+        &self,
+        output_sections: &[Section<'static>],
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> DynamicSymbols {
+        let mut dynamic_symbols = DynamicSymbols {
+            got_symbol: resolved_symbols
+                .get(GOT_SYMBOL)
+                .is_some_and(|s| s.object_index == SYNTHETIC_OBJECT),
+            iplt_symbols: resolved_symbols
+                .get(RELA_IPLT_START)
+                .or_else(|| resolved_symbols.get(RELA_IPLT_END))
+                .is_some_and(|s| s.object_index == SYNTHETIC_OBJECT),
+            ..Default::default()
+        };
+
+        // symbols imported from shared objects, and those a shared object exports
+        let mut names: Vec<&str> = resolved_symbols
+            .values()
+            .filter(|s| s.object_index == SHARED_OBJECT || self.is_exported(s))
+            .map(|s| s.name.as_str())
+            .collect();
+        names.sort();
+        for (idx, name) in names.into_iter().enumerate() {
+            dynamic_symbols.symbol_indices.insert(name.to_string(), idx);
+            dynamic_symbols.symbols.push(name.to_string());
+        }
+
+        let relocations = output_sections
+            .iter()
+            .flat_map(|section| section.inputs.iter())
+            .filter(|input| !input.folded)
+            .flat_map(|input| {
+                self.objects
+                    .get(input.object_index)
+                    .into_iter()
+                    .flat_map(|obj| obj.relocations.iter())
+                    .filter(|r| r.target_section == input.section_index)
+                    .flat_map(|r| r.entries.iter())
+                    .filter(|reloc| input.placed_offset(reloc.offset).is_some())
+                    .map(|reloc| (input.object_index, input.section_index, reloc))
+            });
+
+        for (obj_idx, section_idx, reloc) in relocations {
+            let Some(symbol) = self.objects[obj_idx]
+                .symbols
+                .get(reloc.info.symbol_index as usize)
+            else {
+                continue;
+            };
+            let shared = self.shared_symbol(symbol, resolved_symbols);
+            let r#type = reloc.info.r#type;
+
+            // every reference to an IFUNC symbol goes through its `.iplt` entry
+            if let Some(ifunc) = self.ifunc_symbol(symbol, resolved_symbols) {
+                dynamic_symbols.add_iplt(&ifunc.name);
+            }
+
+            if self.target().is_got_relocation(r#type) {
+                if self.can_relax_got_load((obj_idx, section_idx), reloc, shared.is_some()) {
+                    continue;
+                }
+                let entry = self.got_entry(obj_idx, reloc);
+                if dynamic_symbols.add_got(entry)
+                    && (shared.is_some()
+                        || (self.options.is_position_independent()
+                            && self.is_position_dependent(obj_idx, reloc, resolved_symbols)))
+                {
+                    dynamic_symbols.relocation_count += 1;
+                }
+                continue;
+            }
+            if self.uses_tls_got_slot(r#type, shared.is_some()) {
+                if dynamic_symbols.add_got(self.got_entry(obj_idx, reloc))
+                    && (shared.is_some() || self.options.shared)
+                {
+                    dynamic_symbols.relocation_count += 1;
+                }
+                continue;
+            }
+
+            match shared {
+                Some(shared) => match r#type {
+                    _ if r#type == self.target().dynamic_relocations().absolute => {
+                        dynamic_symbols.relocation_count += 1
+                    }
+                    // calls and address references to functions go through the PLT
+                    _ if self.target().is_branch(r#type) => dynamic_symbols.add_plt(&shared.name),
+                    _ if shared.info.r#type == symbol::Type::Func && !self.options.shared => {
+                        dynamic_symbols.add_plt(&shared.name)
+                    }
+                    _ => {}
+                },
+                None if self.needs_dynamic_relocation(obj_idx, reloc, resolved_symbols) => {
+                    dynamic_symbols.relocation_count += 1
+                }
+                None => {}
+            }
+        }
+
+        dynamic_symbols
+    }
+
+    /// Returns the GOT slot that a GOT relocation refers to.
+    pub(super) fn got_entry(&self, obj_idx: usize, reloc: &RelocationAddend) -> GotEntry {
+        match self.objects[obj_idx]
+            .symbols
+            .get(reloc.info.symbol_index as usize)
+        {
+            Some(symbol) if symbol.info.binding != Binding::Local => {
+                GotEntry::Global(self.reference_name(symbol).into_owned())
+            }
+            _ => GotEntry::Local(obj_idx, reloc.info.symbol_index),
+        }
+    }
+
+    /// Creates the sections used by the dynamic loader: `.interp`, `.dynsym`,
+    /// `.dynstr`, `.hash`, `.rela.dyn`, `.rela.plt`, `.plt`, `.got.plt` and
+    /// `.dynamic`, as well as `.got`, which static output may need too, and
+    /// `.iplt`, `.igot.plt` and `.rela.iplt` for IFUNC symbols.
+    ///
+    /// Their sizes are final, but their contents are only filled in by
+    /// [`Linker::fill_dynamic_sections`] once the layout is known.
+    pub(super) fn create_dynamic_sections(
+        &self,
+        dynamic_symbols: &DynamicSymbols,
+    ) -> Vec<Section<'static>> {
+        let mut sections = Vec::new();
+        if !dynamic_symbols.got.is_empty() || dynamic_symbols.got_symbol {
+            sections.push(synthetic_section(
+                GOT,
+                SectionType::ProgBits,
+                vec![SectionFlag::Write, SectionFlag::Alloc],
+                dynamic_symbols.got.len() as u64 * self.class().word_size(),
+                self.class().word_size(),
+                0,
+            ));
+        }
+        if !dynamic_symbols.iplt.is_empty() || dynamic_symbols.iplt_symbols {
+            let count = dynamic_symbols.iplt.len() as u64;
+            sections.push(synthetic_section(
+                RELA_IPLT,
+                SectionType::Rela,
+                vec![SectionFlag::Alloc],
+                count * RELA_ENTRY_SIZE,
+                8,
+                RELA_ENTRY_SIZE,
+            ));
+        }
+        if !dynamic_symbols.iplt.is_empty() {
+            let count = dynamic_symbols.iplt.len() as u64;
+            sections.push(synthetic_section(
+                IPLT,
+                SectionType::ProgBits,
+                vec![SectionFlag::Alloc, SectionFlag::ExecInstr],
+                count * self.plt_sizes().1,
+                16,
+                0,
+            ));
+            sections.push(synthetic_section(
+                IGOT_PLT,
+                SectionType::ProgBits,
+                vec![SectionFlag::Write, SectionFlag::Alloc],
+                count * GOT_ENTRY_SIZE,
+                8,
+                0,
+            ));
+        }
+        if !self.is_dynamic() {
+            return sections;
+        }
+
+        if let Some(interp) = self.dynamic_linker() {
+            let mut section = synthetic_section(
+                INTERP,
+                SectionType::ProgBits,
+                vec![SectionFlag::Alloc],
+                interp.len() as u64 + 1,
+                1,
+                0,
+            );
+            section.data = Cow::Owned([interp.as_bytes(), b"\0"].concat());
+            sections.push(section);
+        }
+
+        let symbol_count = dynamic_symbols.symbols.len() as u64 + 1;
+        sections.push(synthetic_section(
+            HASH,
+            SectionType::Hash,
+            vec![SectionFlag::Alloc],
+            // nbucket, nchain, one bucket per symbol and the chains
+            (2 + 2 * symbol_count) * 4,
+            8,
+            4,
+        ));
+        sections.push(synthetic_section(
+            DYNSYM,
+            SectionType::DynSym,
+            vec![SectionFlag::Alloc],
+            symbol_count * self.class().symbol_size(),
+            8,
+            self.class().symbol_size(),
+        ));
+        sections.push(synthetic_section(
+            DYNSTR,
+            SectionType::StrTab,
+            vec![SectionFlag::Alloc],
+            self.dynamic_strings(dynamic_symbols).0.len() as u64,
+            1,
+            0,
+        ));
+
+        if dynamic_symbols.relocation_count > 0 {
+            sections.push(synthetic_section(
+                RELA_DYN,
+                SectionType::Rela,
+                vec![SectionFlag::Alloc],
+                dynamic_symbols.relocation_count as u64 * RELA_ENTRY_SIZE,
+                8,
+                RELA_ENTRY_SIZE,
+            ));
+        }
+
+        if !dynamic_symbols.plt.is_empty() {
+            let count = dynamic_symbols.plt.len() as u64;
+            sections.push(synthetic_section(
+                RELA_PLT,
+                SectionType::Rela,
+                vec![SectionFlag::Alloc, SectionFlag::InfoLink],
+                count * RELA_ENTRY_SIZE,
+                8,
+                RELA_ENTRY_SIZE,
+            ));
+            sections.push(synthetic_section(
+                PLT,
+                SectionType::ProgBits,
+                vec![SectionFlag::Alloc, SectionFlag::ExecInstr],
+                self.plt_entry_address(0, count as usize),
+                16,
+                0,
+            ));
+            sections.push(synthetic_section(
+                GOT_PLT,
+                SectionType::ProgBits,
+                vec![SectionFlag::Write, SectionFlag::Alloc],
+                (GOT_PLT_RESERVED + count) * GOT_ENTRY_SIZE,
+                8,
+                0,
+            ));
+        }
+
+        let entry_count = self.dynamic_entries(&sections, dynamic_symbols, 0).len() as u64;
+        sections.push(synthetic_section(
+            (".dynamic", DYNAMIC_SECTION),
+            SectionType::Dynamic,
+            vec![SectionFlag::Write, SectionFlag::Alloc],
+            entry_count * dynamic::Entry::SIZE,
+            8,
+            dynamic::Entry::SIZE,
+        ));
+
+        sections
+    }
+
+    /// Sets `sh_link` and `sh_info` of the dynamic sections once the output sections
+    /// are in their final order.
+    pub(super) fn link_dynamic_sections(&self, output_sections: &mut [Section<'static>]) {
+        // includes null section
+        let index = |sections: &[Section<'static>], name: &str| {
+            sections
+                .iter()
+                .position(|s| s.name == name)
+                .map_or(0, |idx| idx as u32 + 1)
+        };
+        let dynsym = index(output_sections, DYNSYM.0);
+        let dynstr = index(output_sections, DYNSTR.0);
+        let got_plt = index(output_sections, GOT_PLT.0);
+
+        for section in output_sections.iter_mut() {
+            match section.name.as_ref() {
+                ".dynsym" => {
+                    section.link = dynstr;
+                    // only the null symbol is local
+                    section.info = 1;
+                }
+                ".dynamic" => section.link = dynstr,
+                ".hash" | ".rela.dyn" => section.link = dynsym,
+                ".rela.plt" => {
+                    section.link = dynsym;
+                    section.info = got_plt;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Gives the symbols with a PLT entry the address of that entry, which is what
+    /// calls from the output branch to.
+    pub(super) fn assign_plt_addresses(
+        &self,
+        output_sections: &[Section<'static>],
+        dynamic_symbols: &DynamicSymbols,
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) {
+        let Some(plt) = output_sections.iter().find(|s| s.name == PLT.0) else {
+            return;
+        };
+        for (idx, name) in dynamic_symbols.plt.iter().enumerate() {
+            if let Some(symbol) = resolved_symbols.get_mut(name) {
+                symbol.value = self.plt_entry_address(plt.addr, idx);
+            }
+        }
+    }
+
+    /// Writes the contents of the dynamic sections, including the dynamic relocations
+    /// collected while applying relocations.
+    pub(super) fn fill_dynamic_sections(
+        &self,
+        output_sections: &mut [Section<'static>],
+        dynamic_symbols: &DynamicSymbols,
+        mut relocations: Vec<DynamicRelocation>,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> Result<()> {
+        if !self.is_dynamic() {
+            return Ok(());
+        }
+
+        let address = |name: &str| {
+            output_sections
+                .iter()
+                .find(|s| s.name == name)
+                .map_or(0, |s| s.addr)
+        };
+        let plt = address(PLT.0);
+        let got_plt = address(GOT_PLT.0);
+
+        let types = self.target().dynamic_relocations();
+        let relative_count = sort_relocations(&mut relocations, types.relative);
+        if relocations.len() != dynamic_symbols.relocation_count {
+            return Err(LinkerError::Generic {
+                message: format!(
+                    "expected {} dynamic relocations, found {}",
+                    dynamic_symbols.relocation_count,
+                    relocations.len()
+                ),
+                context: Some("writing .rela.dyn".to_string()),
+            });
+        }
+
+        let encoding = self.encoding();
+        let (dynstr, string_offsets) = self.dynamic_strings(dynamic_symbols);
+
+        let input_sections = InputSectionMap::new(output_sections);
+        let tls = tls_segment(output_sections);
+        let mut dynsym = Vec::new();
+        write_symbol_entry(&mut dynsym, encoding, 0, 0, 0, 0, 0, 0);
+        for name in &dynamic_symbols.symbols {
+            let symbol = &resolved_symbols[name];
+            let (value, visibility, shndx) = if symbol.object_index == SHARED_OBJECT {
+                // executables give functions the address of their PLT entry, so that
+                // it is the same everywhere
+                let value = if self.options.shared { 0 } else { symbol.value };
+                (
+                    value,
+                    symbol::Visibility::Default,
+                    SymbolIndex::Undefined as u16,
+                )
+            } else {
+                (
+                    symbol_value(symbol, tls.as_ref()),
+                    symbol.visibility,
+                    input_sections.output_shndx(symbol.object_index, symbol.shndx),
+                )
+            };
+            write_symbol_entry(
+                &mut dynsym,
+                encoding,
+                string_offsets[name.as_str()],
+                value,
+                symbol.size,
+                symbol.info.into(),
+                visibility as u8,
+                shndx,
+            );
+        }
+
+        let hash = hash_table(&dynamic_symbols.symbols, encoding);
+
+        let rela_plt: Vec<u8> = dynamic_symbols
+            .plt
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, name)| {
+                DynamicRelocation {
+                    offset: got_plt_slot_address(got_plt, idx),
+                    r#type: types.jump_slot,
+                    symbol: dynamic_symbols.symbol_index(name).unwrap_or(0),
+                    addend: 0,
+                }
+                .to_bytes(encoding)
+            })
+            .collect();
+
+        // lazily bound slots initially point to the PLT header
+        let got_plt_data: Vec<u8> = (0..GOT_PLT_RESERVED as usize + dynamic_symbols.plt.len())
+            .flat_map(|idx| {
+                let value = if idx < GOT_PLT_RESERVED as usize {
+                    0
+                } else {
+                    plt
+                };
+                encoding.u64_bytes(value)
+            })
+            .collect();
+
+        let plt_data = self.plt_contents(plt, got_plt, dynamic_symbols.plt.len());
+
+        let dynamic: Vec<u8> = self
+            .dynamic_entries(output_sections, dynamic_symbols, relative_count)
+            .iter()
+            .flat_map(|entry| entry.to_bytes(encoding))
+            .collect();
+
+        let rela_dyn: Vec<u8> = relocations
+            .iter()
+            .flat_map(|r| r.to_bytes(encoding))
+            .collect();
+
+        for section in output_sections.iter_mut() {
+            let data = match section.name.as_ref() {
+                ".dynsym" => &dynsym,
+                ".dynstr" => &dynstr,
+                ".hash" => &hash,
+                ".rela.dyn" => &rela_dyn,
+                ".rela.plt" => &rela_plt,
+                ".plt" => &plt_data,
+                ".got.plt" => &got_plt_data,
+                ".dynamic" => &dynamic,
+                _ => continue,
+            };
+            section.data = Cow::Owned(data.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Returns true if a GOT load in `section` can use the address of the symbol
+    /// instead, turning `adrp` + `ldr` into `adrp` + `add` on AArch64, and loads,
+    /// calls and jumps through the slot into direct ones on x86-64.
+    ///
+    /// This is done with `--relax` in executables that are not position-independent,
+    /// where the address of a symbol defined in the output is known at link time.
+    pub(super) fn can_relax_got_load(
+        &self,
+        (obj_idx, section_idx): InputSectionId,
+        reloc: &RelocationAddend,
+        shared: bool,
+    ) -> bool {
+        if !self.options.relax || self.options.is_position_independent() || shared {
+            return false;
+        }
+        let Some(header) = self.objects[obj_idx]
+            .section_headers
+            .get(section_idx as usize)
+        else {
+            return false;
+        };
+        usize::try_from(reloc.offset).is_ok_and(|offset| {
+            self.target().can_relax_got_load(
+                reloc.info.r#type,
+                reloc.addend,
+                &header.section_raw_data,
+                offset,
+            )
+        })
+    }
+
+    /// Returns true if an absolute relocation must be applied again at load time
+    /// because the output is position-independent.
+    pub(super) fn needs_dynamic_relocation(
+        &self,
+        obj_idx: usize,
+        reloc: &RelocationAddend,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> bool {
+        self.options.is_position_independent()
+            && self.target().is_absolute_relocation(reloc.info.r#type)
+            && self.is_position_dependent(obj_idx, reloc, resolved_symbols)
+    }
+
+    /// Returns true if the address of the symbol a relocation refers to depends on
+    /// where the output is loaded, i.e. the symbol is defined in the output and is
+    /// not absolute.
+    pub(super) fn is_position_dependent(
+        &self,
+        obj_idx: usize,
+        reloc: &RelocationAddend,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> bool {
+        let Some(symbol) = self.objects[obj_idx]
+            .symbols
+            .get(reloc.info.symbol_index as usize)
+        else {
+            return false;
+        };
+
+        if symbol.info.binding == Binding::Local {
+            return SymbolIndex::Abs != symbol.shndx;
+        }
+        match resolved_symbols.get(self.reference_name(symbol).as_ref()) {
+            Some(resolved) if resolved.object_index == SHARED_OBJECT => false,
+            Some(resolved) => !self.is_absolute_symbol(resolved, resolved_symbols),
+            None => false,
+        }
+    }
+
+    fn is_absolute_symbol(
+        &self,
+        symbol: &ResolvedSymbol,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> bool {
+        if SymbolIndex::Abs != symbol.shndx {
+            return false;
+        }
+        // the bounds of the arrays of constructors are placeholders until layout
+        if symbol.object_index == SYNTHETIC_OBJECT {
+            return false;
+        }
+        if symbol.object_index != COMMAND_LINE_OBJECT {
+            return true;
+        }
+        // `--defsym` symbols are absolute placeholders until they are evaluated
+        // after layout, but the expression tells whether they are relative to a section
+        self.options
+            .defsym
+            .iter()
+            .rfind(|assignment| assignment.name == symbol.name)
+            .is_none_or(|assignment| {
+                assignment
+                    .expr
+                    .evaluate(resolved_symbols)
+                    .is_ok_and(|value| value.base.is_none())
+            })
+    }
+
+    /// Returns the path of the dynamic loader to record in `.interp`, if any.
+    fn dynamic_linker(&self) -> Option<&str> {
+        if self.options.no_dynamic_linker {
+            return None;
+        }
+        // shared objects are loaded by the loader of the executable
+        if self.options.shared {
+            return self.options.dynamic_linker.as_deref();
+        }
+        Some(
+            self.options
+                .dynamic_linker
+                .as_deref()
+                .unwrap_or(DEFAULT_DYNAMIC_LINKER),
+        )
+    }
+
+    /// Returns the contents of `.dynstr` and the offset of each string in it.
+    fn dynamic_strings<'a>(
+        &'a self,
+        dynamic_symbols: &'a DynamicSymbols,
+    ) -> (Vec<u8>, HashMap<&'a str, u32>) {
+        // includes null string
+        let mut strings = vec![0];
+        let mut offsets = HashMap::new();
+
+        let names = self
+            .shared_objects
+            .iter()
+            .map(|shared| shared.soname.as_str())
+            .chain(self.options.soname.as_deref())
+            .chain(dynamic_symbols.symbols.iter().map(String::as_str));
+        for name in names {
+            if offsets.contains_key(name) {
+                continue;
+            }
+            offsets.insert(name, strings.len() as u32);
+            strings.extend_from_slice(name.as_bytes());
+            strings.push(0);
+        }
+
+        (strings, offsets)
+    }
+
+    /// Returns the entries of `.dynamic`, using the addresses of `output_sections`.
+    /// `relative_count` is the number of relative relocations at the start of
+    /// `.rela.dyn`.
+    fn dynamic_entries(
+        &self,
+        output_sections: &[Section<'static>],
+        dynamic_symbols: &DynamicSymbols,
+        relative_count: usize,
+    ) -> Vec<dynamic::Entry> {
+        let section = |name: &str| output_sections.iter().find(|s| s.name == name);
+        let (_, string_offsets) = self.dynamic_strings(dynamic_symbols);
+
+        let mut entries = Vec::new();
+        let mut push = |tag, value| entries.push(dynamic::Entry { tag, value });
+
+        for shared in &self.shared_objects {
+            push(Tag::Needed, string_offsets[shared.soname.as_str()] as u64);
+        }
+        if let Some(soname) = &self.options.soname {
+            push(Tag::SoName, string_offsets[soname.as_str()] as u64);
+        }
+        if let Some(hash) = section(HASH.0) {
+            push(Tag::Hash, hash.addr);
+        }
+        if let Some(dynstr) = section(DYNSTR.0) {
+            push(Tag::StrTab, dynstr.addr);
+            push(Tag::StrSz, dynstr.size);
+        }
+        if let Some(dynsym) = section(DYNSYM.0) {
+            push(Tag::SymTab, dynsym.addr);
+            push(Tag::SymEnt, self.class().symbol_size());
+        }
+        // filled in by the dynamic loader for debuggers, only in executables
+        if !self.options.shared {
+            push(Tag::Debug, 0);
+        }
+        if let Some(rela_dyn) = section(RELA_DYN.0) {
+            push(Tag::Rela, rela_dyn.addr);
+            push(Tag::RelaSz, rela_dyn.size);
+            push(Tag::RelaEnt, RELA_ENTRY_SIZE);
+            push(Tag::RelaCount, relative_count as u64);
+        }
+        if let (Some(rela_plt), Some(got_plt)) = (section(RELA_PLT.0), section(GOT_PLT.0)) {
+            push(Tag::PltGot, got_plt.addr);
+            push(Tag::PltRelSz, rela_plt.size);
+            push(Tag::PltRel, Tag::Rela as u64);
+            push(Tag::JmpRel, rela_plt.addr);
+        }
+        if self.options.pie {
+            push(Tag::Flags1, dynamic::DF_1_PIE);
+        }
+        push(Tag::Null, 0);
+
+        entries
+    }
+
+    /// Returns the sizes of the PLT header and of a PLT entry of the target.
+    pub(super) fn plt_sizes(&self) -> (u64, u64) {
+        self.target().plt_sizes().unwrap_or_default()
+    }
+
+    /// Returns the address of the entry `idx` of `.plt`.
+    fn plt_entry_address(&self, plt: u64, idx: usize) -> u64 {
+        let (header_size, entry_size) = self.plt_sizes();
+        plt + header_size + idx as u64 * entry_size
+    }
+
+    /// Generates the PLT header and one entry per symbol.
+    fn plt_contents(&self, plt: u64, got_plt: u64, count: usize) -> Vec<u8> {
+        let target = self.target();
+        let mut data = target.plt_header(plt, got_plt);
+        for idx in 0..count {
+            let entry = self.plt_entry_address(plt, idx);
+            data.extend(target.plt_entry(entry, got_plt_slot_address(got_plt, idx)));
+        }
+        data
+    }
+}
+
+/// Puts the relative relocations first, so that DT_RELACOUNT covers them, and returns
+/// how many there are.
+fn sort_relocations(relocations: &mut [DynamicRelocation], relative: RelocationType) -> usize {
+    relocations.sort_by_key(|r| (r.r#type != relative, r.offset));
+    relocations
+        .iter()
+        .take_while(|r| r.r#type == relative)
+        .count()
+}
+
+fn got_plt_slot_address(got_plt: u64, idx: usize) -> u64 {
+    got_plt + (GOT_PLT_RESERVED + idx as u64) * GOT_ENTRY_SIZE
+}
+
+/// Builds a System V hash table (`.hash`) for the dynamic symbols, with one bucket
+/// per symbol.
+fn hash_table(symbols: &[String], encoding: Encoding) -> Vec<u8> {
+    // includes null symbol
+    let count = symbols.len() + 1;
+    let mut buckets = vec![0u32; count];
+    let mut chains = vec![0u32; count];
+
+    for (idx, name) in symbols.iter().enumerate() {
+        let symbol_index = idx + 1;
+        let bucket = elf_hash(name.as_bytes()) as usize % count;
+        // prepend to the chain of the bucket
+        chains[symbol_index] = buckets[bucket];
+        buckets[bucket] = symbol_index as u32;
+    }
+
+    [count as u32, count as u32]
+        .into_iter()
+        .chain(buckets)
+        .chain(chains)
+        .flat_map(|word| encoding.u32_bytes(word))
+        .collect()
+}
+
+/// The hash function of the System V ABI.
+fn elf_hash(name: &[u8]) -> u32 {
+    let mut h: u32 = 0;
+    for &c in name {
+        h = (h << 4).wrapping_add(c as u32);
+        let g = h & 0xf0000000;
+        if g != 0 {
+            h ^= g >> 24;
+        }
+        h &= !g;
+    }
+    h
+}
+
+pub(super) fn synthetic_section(
+    (name, section_index): (&'static str, u16),
+    r#type: SectionType,
+    flags: Vec<SectionFlag>,
+    size: u64,
+    align: u64,
+    entsize: u64,
+) -> Section<'static> {
+    Section {
+        name: Cow::Borrowed(name),
+        r#type,
+        flags,
+        addr: 0,
+        offset: 0,
+        size,
+        data: Cow::Owned(vec![0; size as usize]),
+        align,
+        link: 0,
+        info: 0,
+        entsize,
+        inputs: vec![InputSection {
+            object_index: SYNTHETIC_OBJECT,
+            section_index,
+            offset: 0,
+            folded: false,
+            pieces: vec![],
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_count_leading_relative_relocations() {
+        let relocation = |offset, r#type| DynamicRelocation {
+            offset,
+            r#type,
+            symbol: 0,
+            addend: 0,
+        };
+        let mut relocations = vec![
+            relocation(0x30, RelocationType::Aarch64Relative),
+            relocation(0x10, RelocationType::Aarch64Abs64),
+            relocation(0x20, RelocationType::Aarch64Relative),
+        ];
+        assert_eq!(
+            sort_relocations(&mut relocations, RelocationType::Aarch64Relative),
+            2
+        );
+        assert_eq!(
+            relocations
+                .iter()
+                .map(|r| (r.offset, r.r#type))
+                .collect::<Vec<_>>(),
+            vec![
+                (0x20, RelocationType::Aarch64Relative),
+                (0x30, RelocationType::Aarch64Relative),
+                (0x10, RelocationType::Aarch64Abs64),
+            ]
+        );
+    }
+
+    #[test]
+    fn should_hash_names() {
+        assert_eq!(elf_hash(b""), 0);
+        assert_eq!(elf_hash(b"printf"), 0x077905a6);
+        assert_eq!(elf_hash(b"__libc_start_main"), 0x0177ff8e);
+    }
+}
@@ -0,0 +1,529 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::elf::header::Encoding;
+use crate::elf::relocation::RelocationType;
+use crate::elf::section::{SectionFlag, SectionType};
+use crate::error::{LinkerError, Result};
+
+use super::Linker;
+use super::dynamic::{GotEntry, synthetic_section};
+use super::gc::InputSectionId;
+use super::merge::MergedSection;
+use super::output::{MergePiece, ResolvedSymbol, Section};
+
+/// Name and section index (within the synthetic object) of the binary search table
+/// of the FDEs.
+const EH_FRAME_HDR: (&str, u16) = (".eh_frame_hdr", 14);
+
+/// `DW_EH_PE_*` pointer encodings
+const DW_EH_PE_ABSPTR: u8 = 0x00;
+const DW_EH_PE_UDATA4: u8 = 0x03;
+const DW_EH_PE_SDATA4: u8 = 0x0b;
+const DW_EH_PE_PCREL: u8 = 0x10;
+const DW_EH_PE_DATAREL: u8 = 0x30;
+const DW_EH_PE_OMIT: u8 = 0xff;
+
+/// Size of the header of `.eh_frame_hdr`, before the table
+const EH_FRAME_HDR_HEADER_SIZE: u64 = 12;
+
+/// A CIE or FDE of an `.eh_frame` section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Record {
+    /// Offset of the record in the section, starting with its length field.
+    pub offset: u64,
+    /// Size of the record, including the length field.
+    pub size: u64,
+    /// For an FDE, the offset of its CIE in the section.
+    pub cie: Option<u64>,
+}
+
+impl Record {
+    fn contains(&self, offset: u64) -> bool {
+        self.offset <= offset && offset < self.offset + self.size
+    }
+}
+
+/// Splits the contents of an `.eh_frame` section into CIEs and FDEs, up to the
+/// zero terminator if there is one. Lengths and CIE pointers are in the byte order
+/// of the file.
+pub(super) fn split_records(
+    data: &[u8],
+    byte_order: Encoding,
+) -> std::result::Result<Vec<Record>, String> {
+    let mut records = Vec::new();
+    let mut offset = 0;
+
+    while offset + 4 <= data.len() {
+        let length = byte_order.read_u32(&data[offset..]) as usize;
+        if length == 0 {
+            break;
+        }
+        if length == 0xffffffff {
+            return Err("64-bit DWARF records are not supported".to_string());
+        }
+        let end = offset + 4 + length;
+        if length < 4 || end > data.len() {
+            return Err(format!("record at {:#x} is truncated", offset));
+        }
+
+        // an FDE starts with the distance from this field back to its CIE
+        let id = byte_order.read_u32(&data[offset + 4..]) as u64;
+        let cie = if id == 0 {
+            None
+        } else {
+            let cie = (offset as u64 + 4)
+                .checked_sub(id)
+                .ok_or_else(|| format!("FDE at {:#x} points before the section", offset))?;
+            Some(cie)
+        };
+
+        records.push(Record {
+            offset: offset as u64,
+            size: (end - offset) as u64,
+            cie,
+        });
+        offset = end;
+    }
+
+    Ok(records)
+}
+
+/// Identifies a CIE by its contents and relocations, as `(offset, type, symbol, addend)`.
+type CieKey<'a> = (&'a [u8], Vec<(u64, RelocationType, GotEntry, i64)>);
+
+impl Linker {
+    /// Merges the `.eh_frame` sections of the live input sections.
+    ///
+    /// FDEs are only kept if the function they describe is placed in the output, and
+    /// CIEs only if a kept FDE uses them. CIEs with the same contents and relocations
+    /// are stored once. The CIE pointers of the FDEs are updated to match, and the
+    /// section ends with a zero terminator, like the `.eh_frame` of GNU ld and LLD.
+    ///
+    /// Returns `None` if no record is kept. Input sections without kept records are
+    /// not members of the merged section.
+    pub(super) fn merge_eh_frames(
+        &self,
+        live_sections: &HashSet<InputSectionId>,
+        folded_sections: &HashMap<InputSectionId, InputSectionId>,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> Result<Option<MergedSection>> {
+        let mut merged = MergedSection {
+            align: 1,
+            ..Default::default()
+        };
+        let mut cies: HashMap<CieKey, u64> = HashMap::new();
+        let byte_order = self.encoding();
+
+        for (obj_idx, section_idx) in self.eh_frame_sections(live_sections) {
+            let header = &self.objects[obj_idx].section_headers[section_idx as usize];
+            let data = header.section_raw_data.as_slice();
+            let records =
+                split_records(data, byte_order).map_err(|message| LinkerError::Generic {
+                    message,
+                    context: Some(format!(
+                        "parsing .eh_frame of {}",
+                        self.object_file_name(obj_idx)
+                    )),
+                })?;
+            let relocations = self
+                .section_relocations((obj_idx, section_idx))
+                .collect::<Vec<_>>();
+
+            let mut pieces = Vec::new();
+            let mut cie_offsets = HashMap::new();
+            for fde in records.iter().filter(|r| r.cie.is_some()) {
+                let function = relocations
+                    .iter()
+                    .find(|r| r.offset == fde.offset + 8)
+                    .and_then(|r| self.referenced_section(obj_idx, r, resolved_symbols));
+                if !function.is_some_and(|id| {
+                    live_sections.contains(&id) && !folded_sections.contains_key(&id)
+                }) {
+                    continue;
+                }
+
+                let cie_offset = fde.cie.unwrap_or_default();
+                let Some(cie) = records
+                    .iter()
+                    .find(|r| r.offset == cie_offset && r.cie.is_none())
+                else {
+                    return Err(LinkerError::Generic {
+                        message: format!("FDE at {:#x} has no CIE", fde.offset),
+                        context: Some(format!(
+                            "parsing .eh_frame of {}",
+                            self.object_file_name(obj_idx)
+                        )),
+                    });
+                };
+
+                let cie_output = match cie_offsets.get(&cie.offset) {
+                    Some(&output) => output,
+                    None => {
+                        let key = (
+                            &data[cie.offset as usize..(cie.offset + cie.size) as usize],
+                            relocations
+                                .iter()
+                                .filter(|r| cie.contains(r.offset))
+                                .map(|r| {
+                                    (
+                                        r.offset - cie.offset,
+                                        r.info.r#type,
+                                        self.got_entry(obj_idx, r),
+                                        r.addend,
+                                    )
+                                })
+                                .collect(),
+                        );
+                        let output = match cies.get(&key) {
+                            Some(&output) => output,
+                            None => {
+                                let output = merged.data.len() as u64;
+                                merged.data.extend_from_slice(key.0);
+                                pieces.push(MergePiece {
+                                    input_offset: cie.offset,
+                                    size: cie.size,
+                                    output_offset: output,
+                                });
+                                cies.insert(key, output);
+                                output
+                            }
+                        };
+                        cie_offsets.insert(cie.offset, output);
+                        output
+                    }
+                };
+
+                let output = merged.data.len() as u64;
+                let start = merged.data.len();
+                merged.data.extend_from_slice(
+                    &data[fde.offset as usize..(fde.offset + fde.size) as usize],
+                );
+                let cie_pointer = (output + 4 - cie_output) as u32;
+                merged.data[start + 4..start + 8]
+                    .copy_from_slice(&byte_order.u32_bytes(cie_pointer));
+                pieces.push(MergePiece {
+                    input_offset: fde.offset,
+                    size: fde.size,
+                    output_offset: output,
+                });
+            }
+
+            if !pieces.is_empty() {
+                pieces.sort_by_key(|p| p.input_offset);
+                merged.align = merged.align.max(header.addralign);
+                merged.members.push(((obj_idx, section_idx), pieces));
+            }
+        }
+
+        if merged.members.is_empty() {
+            return Ok(None);
+        }
+        merged.data.extend_from_slice(&byte_order.u32_bytes(0));
+        Ok(Some(merged))
+    }
+
+    /// Returns, for each function with an FDE, the sections that its FDE and CIE
+    /// refer to besides the function, such as its LSDA and personality routine.
+    ///
+    /// These sections are needed as long as the function is.
+    pub(super) fn unwind_references(
+        &self,
+        sections: &HashSet<InputSectionId>,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> HashMap<InputSectionId, Vec<InputSectionId>> {
+        let mut references: HashMap<InputSectionId, Vec<InputSectionId>> = HashMap::new();
+
+        for (obj_idx, section_idx) in self.eh_frame_sections(sections) {
+            let header = &self.objects[obj_idx].section_headers[section_idx as usize];
+            // malformed sections are reported when they are merged
+            let Ok(records) = split_records(&header.section_raw_data, self.encoding()) else {
+                continue;
+            };
+            let relocations = self
+                .section_relocations((obj_idx, section_idx))
+                .collect::<Vec<_>>();
+
+            for fde in records.iter().filter(|r| r.cie.is_some()) {
+                let Some(function) = relocations
+                    .iter()
+                    .find(|r| r.offset == fde.offset + 8)
+                    .and_then(|r| self.referenced_section(obj_idx, r, resolved_symbols))
+                else {
+                    continue;
+                };
+                let cie = records.iter().find(|r| Some(r.offset) == fde.cie);
+                let referenced = relocations
+                    .iter()
+                    .filter(|r| {
+                        (fde.contains(r.offset) && r.offset != fde.offset + 8)
+                            || cie.is_some_and(|cie| cie.contains(r.offset))
+                    })
+                    .filter_map(|r| self.referenced_section(obj_idx, r, resolved_symbols));
+                references.entry(function).or_default().extend(referenced);
+            }
+        }
+
+        references
+    }
+
+    /// Returns the `.eh_frame` sections among `sections`, in command line order.
+    fn eh_frame_sections(&self, sections: &HashSet<InputSectionId>) -> Vec<InputSectionId> {
+        let mut eh_frames: Vec<InputSectionId> = sections
+            .iter()
+            .copied()
+            .filter(|&(obj_idx, section_idx)| {
+                is_eh_frame(&self.objects[obj_idx].section_headers[section_idx as usize].name)
+            })
+            .collect();
+        eh_frames.sort();
+        eh_frames
+    }
+}
+
+/// Returns true for the sections holding unwind information.
+pub(super) fn is_eh_frame(name: &str) -> bool {
+    name == ".eh_frame"
+}
+
+/// Creates `.eh_frame_hdr` for the FDEs of the merged `.eh_frame`, if any. Its
+/// contents are filled in by [`fill_eh_frame_hdr`] once `.eh_frame` is relocated.
+pub(super) fn create_eh_frame_hdr(
+    output_sections: &[Section<'static>],
+    byte_order: Encoding,
+) -> Option<Section<'static>> {
+    let eh_frame = output_sections.iter().find(|s| is_eh_frame(&s.name))?;
+    let fde_count = split_records(&eh_frame.data, byte_order)
+        .ok()?
+        .iter()
+        .filter(|r| r.cie.is_some())
+        .count() as u64;
+
+    Some(synthetic_section(
+        EH_FRAME_HDR,
+        SectionType::ProgBits,
+        vec![SectionFlag::Alloc],
+        EH_FRAME_HDR_HEADER_SIZE + fde_count * 8,
+        4,
+        0,
+    ))
+}
+
+/// Writes `.eh_frame_hdr`: a pointer to `.eh_frame`, and a table of the initial
+/// locations of the functions and the addresses of their FDEs, sorted for binary
+/// search by the unwinder.
+pub(super) fn fill_eh_frame_hdr(
+    output_sections: &mut [Section<'static>],
+    byte_order: Encoding,
+) -> Result<()> {
+    let (Some(hdr_idx), Some(eh_frame)) = (
+        output_sections
+            .iter()
+            .position(|s| s.name == EH_FRAME_HDR.0),
+        output_sections.iter().find(|s| is_eh_frame(&s.name)),
+    ) else {
+        return Ok(());
+    };
+    let error = |message: String| LinkerError::Generic {
+        message,
+        context: Some("writing .eh_frame_hdr".to_string()),
+    };
+
+    let data = eh_frame.data.as_ref();
+    let records = split_records(data, byte_order).map_err(error)?;
+    let mut encodings = HashMap::new();
+    let mut table = Vec::new();
+    for fde in records.iter().filter(|r| r.cie.is_some()) {
+        let cie = fde.cie.unwrap_or_default();
+        let encoding = match encodings.get(&cie) {
+            Some(&encoding) => encoding,
+            None => {
+                let encoding = fde_encoding(&data[cie as usize..]).map_err(error)?;
+                encodings.insert(cie, encoding);
+                encoding
+            }
+        };
+        let pc_begin = fde.offset + 8;
+        let location = read_encoded(
+            data,
+            pc_begin as usize,
+            encoding,
+            eh_frame.addr + pc_begin,
+            byte_order,
+        )
+        .map_err(error)?;
+        table.push((location, eh_frame.addr + fde.offset));
+    }
+    table.sort();
+
+    let hdr_addr = output_sections[hdr_idx].addr;
+    let relative = |addr: u64| {
+        i32::try_from(addr.wrapping_sub(hdr_addr) as i64)
+            .map_err(|_| error(format!("{:#x} is too far from .eh_frame_hdr", addr)))
+    };
+    let eh_frame_ptr = i32::try_from(eh_frame.addr.wrapping_sub(hdr_addr + 4) as i64)
+        .map_err(|_| error(".eh_frame is too far from .eh_frame_hdr".to_string()))?;
+
+    let mut hdr = vec![
+        1,
+        DW_EH_PE_PCREL | DW_EH_PE_SDATA4,
+        DW_EH_PE_UDATA4,
+        DW_EH_PE_DATAREL | DW_EH_PE_SDATA4,
+    ];
+    hdr.extend(byte_order.u32_bytes(eh_frame_ptr as u32));
+    hdr.extend(byte_order.u32_bytes(table.len() as u32));
+    for (location, fde) in table {
+        hdr.extend(byte_order.u32_bytes(relative(location)? as u32));
+        hdr.extend(byte_order.u32_bytes(relative(fde)? as u32));
+    }
+
+    output_sections[hdr_idx].data = hdr.into();
+    Ok(())
+}
+
+/// Returns the encoding of the addresses in the FDEs of a CIE, given by the `R`
+/// character of its augmentation string.
+fn fde_encoding(cie: &[u8]) -> std::result::Result<u8, String> {
+    let truncated = || "CIE is truncated".to_string();
+    let version = *cie.get(8).ok_or_else(truncated)?;
+    let augmentation_end = cie
+        .get(9..)
+        .and_then(|rest| rest.iter().position(|&b| b == 0))
+        .ok_or_else(truncated)?
+        + 9;
+    let augmentation = &cie[9..augmentation_end];
+    if !augmentation.starts_with(b"z") {
+        return Ok(DW_EH_PE_ABSPTR);
+    }
+
+    // code alignment factor, data alignment factor and return address register
+    let mut pos = augmentation_end + 1;
+    read_uleb128(cie, &mut pos).ok_or_else(truncated)?;
+    read_uleb128(cie, &mut pos).ok_or_else(truncated)?;
+    if version == 1 {
+        pos += 1;
+    } else {
+        read_uleb128(cie, &mut pos).ok_or_else(truncated)?;
+    }
+    // augmentation data length
+    read_uleb128(cie, &mut pos).ok_or_else(truncated)?;
+
+    for &c in &augmentation[1..] {
+        match c {
+            b'R' => return cie.get(pos).copied().ok_or_else(truncated),
+            b'P' => {
+                let encoding = *cie.get(pos).ok_or_else(truncated)?;
+                pos += 1 + encoded_size(encoding)?;
+            }
+            b'L' => pos += 1,
+            b'S' | b'B' | b'G' => {}
+            _ => {
+                return Err(format!(
+                    "unknown augmentation string '{}'",
+                    String::from_utf8_lossy(augmentation)
+                ));
+            }
+        }
+    }
+    Ok(DW_EH_PE_ABSPTR)
+}
+
+/// Returns the size of a value with the given `DW_EH_PE_*` encoding.
+fn encoded_size(encoding: u8) -> std::result::Result<usize, String> {
+    match encoding & 0x0f {
+        0x00 | 0x04 | 0x0c => Ok(8),
+        0x02 | 0x0a => Ok(2),
+        0x03 | 0x0b => Ok(4),
+        _ => Err(format!("unsupported pointer encoding {:#x}", encoding)),
+    }
+}
+
+/// Reads a value with the given `DW_EH_PE_*` encoding at `pos`, where `addr` is the
+/// address of `pos` in the output.
+fn read_encoded(
+    data: &[u8],
+    pos: usize,
+    encoding: u8,
+    addr: u64,
+    byte_order: Encoding,
+) -> std::result::Result<u64, String> {
+    if encoding == DW_EH_PE_OMIT {
+        return Err("FDE without initial location".to_string());
+    }
+    let size = encoded_size(encoding)?;
+    let bytes = data
+        .get(pos..pos + size)
+        .ok_or_else(|| "FDE is truncated".to_string())?;
+    let value = match encoding & 0x0f {
+        0x02 => byte_order.read_u16(bytes) as u64,
+        0x0a => byte_order.read_u16(bytes) as i16 as u64,
+        0x03 => byte_order.read_u32(bytes) as u64,
+        0x0b => byte_order.read_u32(bytes) as i32 as u64,
+        _ => byte_order.read_u64(bytes),
+    };
+    match encoding & 0x70 {
+        0x00 => Ok(value),
+        DW_EH_PE_PCREL => Ok(addr.wrapping_add(value)),
+        _ => Err(format!("unsupported pointer encoding {:#x}", encoding)),
+    }
+}
+
+fn read_uleb128(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        if shift < 64 {
+            value |= ((byte & 0x7f) as u64) << shift;
+        }
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::header::{Class, Data};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_split_records() {
+        for data in [Data::Lsb, Data::Msb] {
+            let byte_order = Encoding {
+                class: Class::Bit64,
+                data,
+            };
+            let mut data = Vec::new();
+            // CIE with an empty program
+            data.extend(byte_order.u32_bytes(12));
+            data.extend(byte_order.u32_bytes(0));
+            data.extend([1, b'z', b'R', 0, 1, 0x78, 30, 1]);
+            // FDE pointing back 20 bytes to the CIE
+            data.extend(byte_order.u32_bytes(12));
+            data.extend(byte_order.u32_bytes(20));
+            data.extend([0; 8]);
+            // terminator
+            data.extend(byte_order.u32_bytes(0));
+
+            assert_eq!(
+                split_records(&data, byte_order).unwrap(),
+                vec![
+                    Record {
+                        offset: 0,
+                        size: 16,
+                        cie: None
+                    },
+                    Record {
+                        offset: 16,
+                        size: 16,
+                        cie: Some(0)
+                    },
+                ]
+            );
+            assert!(split_records(&data[..20], byte_order).is_err());
+        }
+    }
+}
@@ -0,0 +1,29 @@
+// Big-endian AArch64: data is big-endian, while instructions stay little-endian.
+    .text
+    .globl _start
+    .type _start, %function
+_start:
+    .cfi_startproc
+    bl helper
+    adrp x0, value
+    add x0, x0, :lo12:value
+    ldr w1, [x0]
+    mov x8, #93
+    svc #0
+    .cfi_endproc
+
+    .globl helper
+    .type helper, %function
+helper:
+    .cfi_startproc
+    ret
+    .cfi_endproc
+
+    .data
+    .globl value
+value:
+    .word 0x11223344
+pointer:
+    .xword value
+distance:
+    .word value - .
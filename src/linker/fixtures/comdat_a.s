@@ -0,0 +1,17 @@
+    .text
+    .global _start
+_start:
+    bl      inline_fn
+    bl      use_b
+    mov     x8, #93
+    svc     #0
+
+    .section .text.inline_fn,"axG",@progbits,inline_fn,comdat
+    .global inline_fn
+inline_fn:
+    adr     x0, .Lmsg
+    ret
+
+    .section .rodata.inline_fn,"aG",@progbits,inline_fn,comdat
+.Lmsg:
+    .asciz "a"
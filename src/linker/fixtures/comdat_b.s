@@ -0,0 +1,16 @@
+    .text
+    .global use_b
+use_b:
+    // refers to a local symbol of the discarded copy of the group
+    adr     x1, .Lmsg
+    b       inline_fn
+
+    .section .text.inline_fn,"axG",@progbits,inline_fn,comdat
+    .global inline_fn
+inline_fn:
+    adr     x0, .Lmsg
+    ret
+
+    .section .rodata.inline_fn,"aG",@progbits,inline_fn,comdat
+.Lmsg:
+    .asciz "b"
@@ -0,0 +1,23 @@
+// Conditional branches, test branches and a literal load whose targets are in
+// other sections, as with -ffunction-sections.
+    .section .text._start,"ax"
+    .globl _start
+    .type _start, %function
+_start:
+    cmp x0, #0
+    b.eq done
+    cbz x1, done
+    tbnz w2, #3, done
+    ldr x3, literal
+    tbz w4, #0, far
+    ret
+
+    .section .text.done,"ax"
+    .type done, %function
+done:
+    ret
+
+    .section .rodata.literal,"a"
+    .p2align 3
+literal:
+    .quad 0x1122334455667788
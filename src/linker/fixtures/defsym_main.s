@@ -0,0 +1,7 @@
+    .text
+    .global _start
+_start:
+    adr     x0, entry_alias
+    nop
+    mov     x8, #93
+    svc     #0
@@ -0,0 +1,15 @@
+// Calls a function and reads a variable of libgreet.so.
+    .text
+    .globl _start
+    .type _start, %function
+_start:
+    bl greet
+    adrp x0, :got:counter
+    ldr x0, [x0, :got_lo12:counter]
+    ldr w0, [x0]
+    ret
+
+    .data
+    .globl greeter
+greeter:
+    .quad greet
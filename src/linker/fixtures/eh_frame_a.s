@@ -0,0 +1,21 @@
+// Functions with unwind information in separate sections, one of them unused, so
+// that --gc-sections drops its FDE.
+    .section .text._start,"ax"
+    .globl _start
+    .type _start, %function
+_start:
+    .cfi_startproc
+    stp x29, x30, [sp, #-16]!
+    .cfi_def_cfa_offset 16
+    bl helper
+    ldp x29, x30, [sp], #16
+    ret
+    .cfi_endproc
+
+    .section .text.unused,"ax"
+    .globl unused
+    .type unused, %function
+unused:
+    .cfi_startproc
+    ret
+    .cfi_endproc
@@ -0,0 +1,8 @@
+// A second object whose CIE is identical to the one of eh_frame_a.o.
+    .text
+    .globl helper
+    .type helper, %function
+helper:
+    .cfi_startproc
+    ret
+    .cfi_endproc
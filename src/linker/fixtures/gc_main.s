@@ -0,0 +1,32 @@
+    .section .text._start,"ax"
+    .global _start
+_start:
+    bl      used_func
+    adr     x0, used_data
+    mov     x8, #93
+    svc     #0
+
+    .section .text.used_func,"ax"
+    .global used_func
+used_func:
+    ret
+
+    .section .text.unused_func,"ax"
+    .global unused_func
+unused_func:
+    bl      used_func
+    adr     x0, unused_data
+    ret
+
+    .section .data.used_data,"aw"
+    .global used_data
+used_data:
+    .word 1
+
+    .section .data.unused_data,"aw"
+unused_data:
+    .word 2
+
+    .section .rodata.retained,"aR"
+retained:
+    .word 3
@@ -0,0 +1,16 @@
+// Loads the address of a variable from the GOT, as code compiled with -fPIC does.
+    .text
+    .globl _start
+    .type _start, %function
+_start:
+    adrp x0, :got:value
+    ldr x0, [x0, :got_lo12:value]
+    adrp x1, _GLOBAL_OFFSET_TABLE_
+    ldr x1, [x1, :gotpage_lo15:value]
+    ret
+
+    .data
+    .globl value
+    .type value, %object
+value:
+    .quad 7
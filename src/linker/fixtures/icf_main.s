@@ -0,0 +1,58 @@
+    .section .text._start,"ax"
+    .global _start
+_start:
+    bl      f1
+    bl      f3
+    bl      f4
+    bl      f5
+    bl      f6
+    adr     x0, f2
+    adr     x1, str_a
+    adr     x2, str_b
+    mov     x8, #93
+    svc     #0
+
+    .section .text.f1,"ax"
+    .global f1
+f1:
+    mov     w0, #1
+    ret
+
+    // identical to f1, but its address is taken
+    .section .text.f2,"ax"
+    .global f2
+f2:
+    mov     w0, #1
+    ret
+
+    .section .text.f3,"ax"
+    .global f3
+f3:
+    mov     w0, #3
+    ret
+
+    .section .text.f4,"ax"
+    .global f4
+f4:
+    b       f1
+
+    .section .text.f5,"ax"
+    .global f5
+f5:
+    b       f1
+
+    // identical to f4 once f2 is folded into f1
+    .section .text.f6,"ax"
+    .global f6
+f6:
+    b       f2
+
+    .section .rodata.str_a,"a"
+    .global str_a
+str_a:
+    .asciz "hello"
+
+    .section .rodata.str_b,"a"
+    .global str_b
+str_b:
+    .asciz "hello"
@@ -0,0 +1,29 @@
+// Calls and takes the address of an IFUNC symbol, as static glibc does with memcpy,
+// and walks .rela.iplt like its startup code.
+    .text
+    .globl _start
+    .type _start, %function
+_start:
+    adrp x0, __rela_iplt_start
+    add x0, x0, :lo12:__rela_iplt_start
+    adrp x1, __rela_iplt_end
+    add x1, x1, :lo12:__rela_iplt_end
+    bl memcpy
+    adrp x2, memcpy
+    add x2, x2, :lo12:memcpy
+    ret
+
+    .globl memcpy
+    .type memcpy, %gnu_indirect_function
+memcpy:
+    adr x0, memcpy_generic
+    ret
+
+    .type memcpy_generic, %function
+memcpy_generic:
+    ret
+
+    .data
+    .globl memcpy_ptr
+memcpy_ptr:
+    .quad memcpy
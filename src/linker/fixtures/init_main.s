@@ -0,0 +1,43 @@
+// Registers constructors and destructors with priorities, through both
+// .init_array and the older .ctors, and walks the arrays like the C runtime.
+    .text
+    .globl _start
+    .type _start, %function
+_start:
+    adrp x0, __init_array_start
+    add x0, x0, :lo12:__init_array_start
+    adrp x1, __init_array_end
+    add x1, x1, :lo12:__init_array_end
+    adrp x2, __fini_array_start
+    add x2, x2, :lo12:__fini_array_start
+    adrp x3, __preinit_array_start
+    add x3, x3, :lo12:__preinit_array_start
+    ret
+
+    .type ctor, %function
+ctor:
+    ret
+
+    .section .init_array,"aw",@init_array
+    .p2align 3
+    .quad ctor
+
+    .section .init_array.00200,"aw",@init_array
+    .p2align 3
+    .quad ctor + 200
+
+    .section .ctors.65435,"aw",@progbits
+    .p2align 3
+    .quad ctor + 100
+
+    .section .init_array.00300,"aw",@init_array
+    .p2align 3
+    .quad ctor + 300
+
+    .section .fini_array,"aw",@fini_array
+    .p2align 3
+    .quad ctor + 1
+
+    .section .dtors,"aw",@progbits
+    .p2align 3
+    .quad ctor + 2
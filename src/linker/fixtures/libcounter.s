@@ -0,0 +1,34 @@
+// A shared object that uses a variable through the GOT and calls into another library.
+    .text
+    .globl greet
+    .type greet, %function
+greet:
+    adrp x0, :got:counter
+    ldr x0, [x0, :got_lo12:counter]
+    ldr w1, [x0]
+    add w1, w1, #1
+    str w1, [x0]
+    b puts
+
+    .globl internal
+    .type internal, %function
+internal:
+    ret
+
+    .globl secret
+    .hidden secret
+    .type secret, %function
+secret:
+    ret
+
+    .data
+    .globl counter
+    .type counter, %object
+counter:
+    .word 0
+
+    .balign 8
+    .globl table
+    .type table, %object
+table:
+    .quad greet
@@ -0,0 +1,20 @@
+    .text
+    .global _start
+_start:
+    adr     x0, .Lhello
+    adr     x1, .Lworld
+    adr     x2, .Lpi
+    bl      greet
+    mov     x8, #93
+    svc     #0
+
+    .section .rodata.str1.1,"aMS",@progbits,1
+.Lhello:
+    .asciz "hello"
+.Lworld:
+    .asciz "world"
+
+    .section .rodata.cst8,"aM",@progbits,8
+    .p2align 3
+.Lpi:
+    .quad 0x400921fb54442d18
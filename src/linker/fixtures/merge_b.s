@@ -0,0 +1,20 @@
+    .text
+    .global greet
+greet:
+    adr     x0, .Lhello
+    adr     x1, .Llo
+    adr     x2, .Lpi
+    ret
+
+    .section .rodata.str1.1,"aMS",@progbits,1
+.Llo:
+    .asciz "lo"
+.Lhello:
+    .asciz "hello"
+
+    .section .rodata.cst8,"aM",@progbits,8
+    .p2align 3
+.Le:
+    .quad 0x4005bf0a8b145769
+.Lpi:
+    .quad 0x400921fb54442d18
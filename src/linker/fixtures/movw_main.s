@@ -0,0 +1,16 @@
+// Addresses built with movz/movk/movn sequences, as with -mcmodel=large.
+    .text
+    .globl _start
+    .type _start, %function
+_start:
+    movz x0, #:abs_g3:wide
+    movk x0, #:abs_g2_nc:wide
+    movk x0, #:abs_g1_nc:wide
+    movk x0, #:abs_g0_nc:wide
+    movz x1, #:abs_g1:value
+    movk x1, #:abs_g0_nc:value
+    movz x2, #:abs_g0_s:negative
+    movz x3, #:prel_g1:value
+    movk x3, #:prel_g0_nc:value
+    movz x4, #:prel_g0:_start
+    ret
@@ -0,0 +1,24 @@
+// Absolute pointers that a position-independent executable relocates at load time.
+    .text
+    .globl _start
+    .type _start, %function
+_start:
+    adrp x0, pointers
+    add x0, x0, :lo12:pointers
+    adrp x1, _DYNAMIC
+    add x1, x1, :lo12:_DYNAMIC
+    ret
+
+    .section .rodata
+message:
+    .asciz "hello"
+
+    .globl answer
+    .set answer, 42
+
+    .data
+    .globl pointers
+pointers:
+    .quad _start
+    .quad message + 2
+    .quad answer
@@ -0,0 +1,6 @@
+    .text
+    .global _start
+_start:
+    mov     x0, #0
+    mov     x8, #93
+    svc     #0
@@ -0,0 +1,4 @@
+    .data
+    .global plugin_register
+plugin_register:
+    .word 42
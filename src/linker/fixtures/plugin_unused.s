@@ -0,0 +1,4 @@
+    .data
+    .global plugin_unused
+plugin_unused:
+    .word 7
@@ -0,0 +1,23 @@
+// RV32 code: 32-bit inputs give a 32-bit executable, with 16-byte symbols and
+// addresses stored in 4 bytes.
+    .text
+    .globl _start
+_start:
+    call helper
+    lui a0, %hi(value)
+    lw a1, %lo(value)(a0)
+    li a7, 93
+    ecall
+
+    .globl helper
+    .type helper, @function
+helper:
+    ret
+    .size helper, 4
+
+    .data
+    .globl value
+value:
+    .word 1
+pointer:
+    .word value
@@ -0,0 +1,33 @@
+// RV64 code assembled with relaxation enabled: `call helper` can become `jal`, while
+// `far` is out of its range. Deleting bytes moves `done`, which `diff` refers to,
+// and shrinks `_start`.
+    .text
+    .globl _start
+    .type _start, @function
+_start:
+    call helper
+    call far
+    lui a0, %hi(value)
+    lw a1, %lo(value)(a0)
+    sw a1, %lo(value)(a0)
+.Lpcrel:
+    auipc a2, %pcrel_hi(value)
+    addi a2, a2, %pcrel_lo(.Lpcrel)
+    beq a0, a1, done
+    j done
+    .p2align 3
+done:
+    li a7, 93
+    ecall
+    .size _start, . - _start
+
+    .globl helper
+helper:
+    ret
+
+    .data
+    .globl value
+value:
+    .word 1
+diff:
+    .word done - _start
@@ -0,0 +1,7 @@
+// An absolute address in code, which position-independent output cannot relocate.
+    .text
+    .globl f
+    .type f, %function
+f:
+    ret
+    .quad f
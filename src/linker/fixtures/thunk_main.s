@@ -0,0 +1,17 @@
+// Branches to `far`, which is defined out of branch range with --defsym, from two
+// input sections. The code of `helper` moves when the thunks of `_start` are added.
+    .section .text._start,"ax"
+    .globl _start
+    .type _start, %function
+_start:
+    bl far
+    b far
+    bl helper
+    ret
+
+    .section .text.helper,"ax"
+    .globl helper
+    .type helper, %function
+helper:
+    bl far
+    ret
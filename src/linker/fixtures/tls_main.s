@@ -0,0 +1,34 @@
+// Accesses thread-local variables with the local-exec, initial-exec and TLS
+// descriptor models.
+    .text
+    .globl _start
+    .type _start, %function
+_start:
+    mrs x8, tpidr_el0
+    add x0, x8, :tprel_hi12:counter
+    add x0, x0, :tprel_lo12_nc:counter
+    adrp x1, :gottprel:flag
+    ldr x1, [x1, :gottprel_lo12:flag]
+    adrp x0, :tlsdesc:buffer
+    ldr x1, [x0, :tlsdesc_lo12:buffer]
+    add x0, x0, :tlsdesc_lo12:buffer
+    .tlsdesccall buffer
+    blr x1
+    ret
+
+    .section .tdata,"awT",@progbits
+    .globl counter
+    .type counter, %object
+    .p2align 2
+counter:
+    .word 1
+    .type flag, %object
+flag:
+    .word 2
+
+    .section .tbss,"awT",@nobits
+    .globl buffer
+    .type buffer, %object
+    .p2align 4
+buffer:
+    .zero 64
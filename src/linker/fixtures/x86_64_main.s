@@ -0,0 +1,44 @@
+// x86-64 program that exits with status 42, computed through GOT loads,
+// calls and absolute addresses
+    .text
+    .globl _start
+    .type _start, @function
+_start:
+    call *helper@GOTPCREL(%rip)
+    movq value@GOTPCREL(%rip), %rcx
+    addl (%rcx), %eax
+    movl $table, %esi
+    movq $table, %rdx
+    subq %rdx, %rsi
+    addl %esi, %eax
+    movq table(%rip), %rdx
+    leaq _start(%rip), %rsi
+    cmpq %rdx, %rsi
+    jne fail
+    movl %eax, %edi
+    call exit
+
+fail:
+    movl $1, %edi
+    call exit
+
+    .globl exit
+exit:
+    movl $60, %eax
+    syscall
+
+    .type helper, @function
+helper:
+    jmp *compute@GOTPCREL(%rip)
+
+compute:
+    movl $2, %eax
+    ret
+
+    .data
+    .globl value
+value:
+    .long 40
+    .balign 8
+table:
+    .quad _start
@@ -0,0 +1,166 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::elf::relocation::RelocationAddend;
+use crate::elf::section::{self, SectionFlag, SectionType};
+use crate::elf::symbol::{Binding, Symbol};
+
+use super::Linker;
+use super::eh_frame::is_eh_frame;
+use super::output::ResolvedSymbol;
+
+/// Sections that the default GNU ld linker script wraps in `KEEP`.
+const KEEP_SECTION_PREFIXES: [&str; 7] = [
+    ".init",
+    ".fini",
+    ".ctors",
+    ".dtors",
+    ".init_array",
+    ".fini_array",
+    ".preinit_array",
+];
+
+/// An input section, identified by `(object index, section index)`.
+pub(super) type InputSectionId = (usize, u16);
+
+impl Linker {
+    /// Collects the input sections that are included in the output.
+    ///
+    /// Members of discarded COMDAT groups are never live. Every other allocated
+    /// section is live unless `--gc-sections` is given. In that case,
+    /// only the sections reachable from the roots through relocations are live. The
+    /// roots are the sections defining the entry symbol, the `-u`/`--require-defined`
+    /// symbols and the symbols used by `--defsym`, plus the sections that must always
+    /// be kept (`SHF_GNU_RETAIN` and the sections `KEEP`ed by the default linker script).
+    pub(super) fn collect_live_sections(
+        &self,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> HashSet<InputSectionId> {
+        let allocated = self.allocated_sections();
+
+        if !self.options.gc_sections {
+            return allocated.into_iter().collect();
+        }
+
+        let root_symbols = [self.options.entry_symbol()]
+            .into_iter()
+            .chain(self.options.undefined.iter().map(String::as_str))
+            .chain(self.options.require_defined.iter().map(String::as_str))
+            .chain(self.options.defsym.iter().flat_map(|d| d.expr.symbols()))
+            // exported symbols may be used by anything that loads the shared object
+            .chain(
+                resolved_symbols
+                    .values()
+                    .filter(|symbol| self.is_exported(symbol))
+                    .map(|symbol| symbol.name.as_str()),
+            );
+
+        let mut worklist: Vec<InputSectionId> = root_symbols
+            .filter_map(|name| resolved_symbols.get(name))
+            .filter(|symbol| is_regular_section(symbol.shndx))
+            .map(|symbol| (symbol.object_index, symbol.shndx))
+            .chain(allocated.iter().copied().filter(|&(obj_idx, section_idx)| {
+                is_kept_section(&self.objects[obj_idx].section_headers[section_idx as usize])
+            }))
+            .collect();
+
+        // unwind information does not keep functions alive, but functions keep their
+        // LSDA and personality routine alive
+        let allocated_set = allocated.iter().copied().collect();
+        let unwind_references = self.unwind_references(&allocated_set, resolved_symbols);
+
+        let mut live = HashSet::new();
+        while let Some(id @ (obj_idx, section_idx)) = worklist.pop() {
+            if !allocated.contains(&id) || !live.insert(id) {
+                continue;
+            }
+            worklist.extend(unwind_references.get(&id).into_iter().flatten().copied());
+
+            let relocations = self.objects[obj_idx]
+                .relocations
+                .iter()
+                .filter(|r| r.target_section == section_idx)
+                .flat_map(|r| r.entries.iter());
+            for reloc in relocations {
+                if let Some(target) = self.referenced_section(obj_idx, reloc, resolved_symbols) {
+                    worklist.push(target);
+                }
+            }
+        }
+
+        // the records of discarded functions are dropped when `.eh_frame` is merged
+        live.extend(allocated.iter().copied().filter(|&(obj_idx, section_idx)| {
+            is_eh_frame(&self.objects[obj_idx].section_headers[section_idx as usize].name)
+        }));
+
+        live
+    }
+
+    /// Returns the allocated input sections that are not members of a discarded
+    /// COMDAT group, in input order.
+    pub(super) fn allocated_sections(&self) -> BTreeSet<InputSectionId> {
+        self.objects
+            .iter()
+            .enumerate()
+            .flat_map(|(obj_idx, obj)| {
+                obj.section_headers
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, header)| header.flags.contains(&SectionFlag::Alloc))
+                    .map(move |(section_idx, _)| (obj_idx, section_idx as u16))
+            })
+            .filter(|&id| !self.is_discarded(id))
+            .collect()
+    }
+
+    /// Returns the section that defines the symbol a relocation refers to.
+    pub(super) fn referenced_section(
+        &self,
+        obj_idx: usize,
+        reloc: &RelocationAddend,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> Option<InputSectionId> {
+        let symbol: &Symbol = self.objects[obj_idx]
+            .symbols
+            .get(reloc.info.symbol_index as usize)?;
+
+        let (object_index, shndx) = if symbol.info.binding == Binding::Local {
+            (obj_idx, symbol.shndx)
+        } else {
+            let resolved = resolved_symbols.get(self.reference_name(symbol).as_ref())?;
+            (resolved.object_index, resolved.shndx)
+        };
+
+        if !is_regular_section(shndx) {
+            return None;
+        }
+        let id = (object_index, shndx);
+        if self.is_discarded(id) {
+            return self.comdat_replacement(id);
+        }
+        Some(id)
+    }
+}
+
+/// Returns true if `shndx` refers to a section header rather than a reserved index.
+pub(super) fn is_regular_section(shndx: u16) -> bool {
+    // SHN_UNDEF is 0 and reserved indices start at SHN_LORESERVE (0xff00)
+    shndx != 0 && shndx < 0xff00
+}
+
+fn is_kept_section(header: &section::Header) -> bool {
+    if header.flags.contains(&SectionFlag::GnuRetain)
+        || matches!(
+            header.r#type,
+            SectionType::InitArray | SectionType::FiniArray | SectionType::PreInitArray
+        )
+    {
+        return true;
+    }
+
+    KEEP_SECTION_PREFIXES.iter().any(|prefix| {
+        header
+            .name
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+    })
+}
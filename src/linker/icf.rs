@@ -0,0 +1,187 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::elf::relocation::RelocationAddend;
+use crate::elf::section::{SectionFlag, SectionType};
+use crate::elf::symbol::{Binding, SymbolIndex};
+
+use super::Linker;
+use super::eh_frame::is_eh_frame;
+use super::gc::{InputSectionId, is_regular_section};
+use super::options::Icf;
+use super::output::ResolvedSymbol;
+
+/// What a relocation in a folding candidate points at.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum RelocationTarget {
+    /// An offset in a candidate section, identified by its current equivalence class.
+    Class(usize, u64),
+    /// An offset in a section that is not a candidate.
+    Section(InputSectionId, u64),
+    /// An absolute value.
+    Absolute(u64),
+    /// A symbol that could not be resolved to a section.
+    Unknown(u32),
+}
+
+impl Linker {
+    /// Folds input sections with identical contents and equivalent relocations (`--icf`).
+    ///
+    /// Candidates are live code and read-only data sections. Sections are first grouped
+    /// by contents and relocation types, offsets and addends, and the groups are then
+    /// refined by the classes of the sections their relocations point to until no
+    /// group splits any further. In `safe` mode, sections whose address is taken (they
+    /// are referenced by anything other than a branch) are never folded.
+    ///
+    /// Folded sections are removed from `live_sections`. The returned map gives the
+    /// section each folded section was merged into.
+    pub(super) fn fold_identical_sections(
+        &self,
+        live_sections: &mut HashSet<InputSectionId>,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> HashMap<InputSectionId, InputSectionId> {
+        if self.options.icf == Icf::None {
+            return HashMap::new();
+        }
+
+        let address_taken = match self.options.icf {
+            Icf::Safe => self.address_taken_sections(live_sections, resolved_symbols),
+            _ => HashSet::new(),
+        };
+
+        let mut candidates: Vec<InputSectionId> = live_sections
+            .iter()
+            .copied()
+            .filter(|id| self.is_icf_candidate(*id) && !address_taken.contains(id))
+            .collect();
+        candidates.sort();
+
+        // initial classes: same flags, contents and relocations (ignoring their targets)
+        let mut classes: HashMap<InputSectionId, usize> = HashMap::new();
+        {
+            let mut keys = HashMap::new();
+            for &id @ (obj_idx, section_idx) in &candidates {
+                let header = &self.objects[obj_idx].section_headers[section_idx as usize];
+                let relocations: Vec<_> = self
+                    .section_relocations(id)
+                    .map(|r| (r.offset, r.info.r#type as u32, r.addend))
+                    .collect();
+                let flags = header
+                    .flags
+                    .iter()
+                    .fold(0, |bits, &flag| bits | flag as u64);
+                let key = (flags, header.section_raw_data.as_slice(), relocations);
+                let next = keys.len();
+                classes.insert(id, *keys.entry(key).or_insert(next));
+            }
+        }
+
+        // refine classes by the classes of relocation targets until they are stable
+        let mut class_count = classes.values().collect::<HashSet<_>>().len();
+        loop {
+            let mut keys = HashMap::new();
+            let mut refined = HashMap::new();
+            for &id in &candidates {
+                let targets: Vec<_> = self
+                    .section_relocations(id)
+                    .map(|r| self.relocation_target(id.0, r, &classes, resolved_symbols))
+                    .collect();
+                let next = keys.len();
+                refined.insert(id, *keys.entry((classes[&id], targets)).or_insert(next));
+            }
+            classes = refined;
+
+            if keys.len() == class_count {
+                break;
+            }
+            class_count = keys.len();
+        }
+
+        let mut groups: BTreeMap<usize, Vec<InputSectionId>> = BTreeMap::new();
+        for &id in &candidates {
+            groups.entry(classes[&id]).or_default().push(id);
+        }
+
+        let mut folded = HashMap::new();
+        for members in groups.values().filter(|members| members.len() > 1) {
+            let leader = members[0];
+            for &member in &members[1..] {
+                live_sections.remove(&member);
+                folded.insert(member, leader);
+            }
+        }
+
+        folded
+    }
+
+    fn is_icf_candidate(&self, (obj_idx, section_idx): InputSectionId) -> bool {
+        let header = &self.objects[obj_idx].section_headers[section_idx as usize];
+        let has = |flag| header.flags.contains(&flag);
+        header.r#type == SectionType::ProgBits
+            && header.size > 0
+            && has(SectionFlag::Alloc)
+            && !has(SectionFlag::Write)
+            && !has(SectionFlag::Tls)
+            && !has(SectionFlag::GnuRetain)
+            // unwind information is merged record by record instead
+            && !is_eh_frame(&header.name)
+    }
+
+    /// Returns the relocations applied to an input section.
+    pub(super) fn section_relocations(
+        &self,
+        (obj_idx, section_idx): InputSectionId,
+    ) -> impl Iterator<Item = &RelocationAddend> {
+        self.objects[obj_idx]
+            .relocations
+            .iter()
+            .filter(move |r| r.target_section == section_idx)
+            .flat_map(|r| r.entries.iter())
+    }
+
+    /// Collects the sections that are referenced by relocations other than branches.
+    fn address_taken_sections(
+        &self,
+        live_sections: &HashSet<InputSectionId>,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> HashSet<InputSectionId> {
+        live_sections
+            .iter()
+            .flat_map(|&id| self.section_relocations(id).map(move |r| (id.0, r)))
+            .filter(|(_, r)| !self.target().is_branch(r.info.r#type))
+            .filter_map(|(obj_idx, r)| self.referenced_section(obj_idx, r, resolved_symbols))
+            .collect()
+    }
+
+    fn relocation_target(
+        &self,
+        obj_idx: usize,
+        reloc: &RelocationAddend,
+        classes: &HashMap<InputSectionId, usize>,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> RelocationTarget {
+        let symbol_index = reloc.info.symbol_index;
+        let Some(symbol) = self.objects[obj_idx].symbols.get(symbol_index as usize) else {
+            return RelocationTarget::Unknown(symbol_index);
+        };
+
+        let (object_index, shndx, value) = if symbol.info.binding == Binding::Local {
+            (obj_idx, symbol.shndx, symbol.value)
+        } else {
+            match resolved_symbols.get(self.reference_name(symbol).as_ref()) {
+                Some(resolved) => (resolved.object_index, resolved.shndx, resolved.value),
+                None => return RelocationTarget::Unknown(symbol_index),
+            }
+        };
+
+        if SymbolIndex::Abs == shndx {
+            return RelocationTarget::Absolute(value);
+        }
+        if !is_regular_section(shndx) {
+            return RelocationTarget::Unknown(symbol_index);
+        }
+        match classes.get(&(object_index, shndx)) {
+            Some(&class) => RelocationTarget::Class(class, value),
+            None => RelocationTarget::Section((object_index, shndx), value),
+        }
+    }
+}
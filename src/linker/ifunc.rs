@@ -0,0 +1,135 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use crate::elf::symbol::{self, Binding, Symbol};
+use crate::error::{LinkerError, Result};
+
+use super::Linker;
+use super::dynamic::{DynamicSymbols, GOT_ENTRY_SIZE, IGOT_PLT, IPLT, RELA_IPLT, SYNTHETIC_OBJECT};
+use super::output::{DynamicRelocation, ResolvedSymbol, Section};
+use super::shared::SHARED_OBJECT;
+
+/// Symbols at the start and end of `.rela.iplt`, which the startup code of static
+/// executables uses to apply the `R_AARCH64_IRELATIVE` relocations.
+pub(super) const RELA_IPLT_START: &str = "__rela_iplt_start";
+pub(super) const RELA_IPLT_END: &str = "__rela_iplt_end";
+
+impl Linker {
+    /// Returns the resolution of `symbol` if it is an IFUNC (`STT_GNU_IFUNC`) symbol
+    /// defined in the output.
+    ///
+    /// The value of an IFUNC symbol is the address of a resolver, which returns the
+    /// address of the implementation to use.
+    pub(super) fn ifunc_symbol<'a>(
+        &self,
+        symbol: &Symbol,
+        resolved_symbols: &'a HashMap<String, ResolvedSymbol>,
+    ) -> Option<&'a ResolvedSymbol> {
+        if symbol.info.binding == Binding::Local {
+            return None;
+        }
+        resolved_symbols
+            .get(self.reference_name(symbol).as_ref())
+            .filter(|resolved| {
+                resolved.is_defined
+                    && resolved.object_index != SHARED_OBJECT
+                    && resolved.info.r#type == symbol::Type::Loos
+            })
+    }
+
+    /// Fills `.iplt`, `.igot.plt` and `.rela.iplt`, and gives the IFUNC symbols the
+    /// address of their `.iplt` entry, which all references to them use.
+    ///
+    /// Each slot of `.igot.plt` initially holds the address of the resolver. The
+    /// startup code replaces it with the address the resolver returns, as told by
+    /// the `R_AARCH64_IRELATIVE` relocations between `__rela_iplt_start` and
+    /// `__rela_iplt_end`.
+    pub(super) fn assign_iplt_addresses(
+        &self,
+        output_sections: &mut [Section<'static>],
+        dynamic_symbols: &DynamicSymbols,
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) -> Result<()> {
+        if let Some(name) = dynamic_symbols.iplt.first().filter(|_| self.is_dynamic()) {
+            return Err(LinkerError::Generic {
+                message: format!(
+                    "IFUNC symbol '{}' is only supported in static executables",
+                    name
+                ),
+                context: Some("creating .iplt".to_string()),
+            });
+        }
+        if let Some(name) = dynamic_symbols
+            .iplt
+            .first()
+            .filter(|_| self.target().plt_sizes().is_none())
+        {
+            return Err(LinkerError::Generic {
+                message: format!(
+                    "IFUNC symbol '{}' is not supported for {}",
+                    name,
+                    self.machine()
+                ),
+                context: Some("creating .iplt".to_string()),
+            });
+        }
+
+        let address = |name: &str| {
+            output_sections
+                .iter()
+                .find(|s| s.name == name)
+                .map(|s| (s.addr, s.size))
+        };
+        let end = resolved_symbols
+            .get_mut(RELA_IPLT_END)
+            .filter(|end| end.object_index == SYNTHETIC_OBJECT);
+        if let (Some((rela_iplt, size)), Some(end)) = (address(RELA_IPLT.0), end) {
+            end.value = rela_iplt + size;
+        }
+        let (Some((iplt, _)), Some((igot_plt, _))) = (address(IPLT.0), address(IGOT_PLT.0)) else {
+            return Ok(());
+        };
+
+        let mut iplt_data = Vec::new();
+        let mut igot_plt_data = Vec::new();
+        let mut rela_iplt_data = Vec::new();
+        for (idx, name) in dynamic_symbols.iplt.iter().enumerate() {
+            let Some(symbol) = resolved_symbols.get_mut(name) else {
+                continue;
+            };
+            let entry = iplt + idx as u64 * self.plt_sizes().1;
+            let slot = igot_plt + idx as u64 * GOT_ENTRY_SIZE;
+            let resolver = symbol.value;
+
+            iplt_data.extend(self.target().plt_entry(entry, slot));
+            igot_plt_data.extend(self.encoding().u64_bytes(resolver));
+            rela_iplt_data.extend(
+                DynamicRelocation {
+                    offset: slot,
+                    r#type: self.target().dynamic_relocations().irelative,
+                    symbol: 0,
+                    addend: resolver as i64,
+                }
+                .to_bytes(self.encoding()),
+            );
+
+            // the symbol now stands for the function the resolver picks
+            symbol.value = entry;
+            symbol.info.r#type = symbol::Type::Func;
+            symbol.object_index = SYNTHETIC_OBJECT;
+            symbol.shndx = IPLT.1;
+        }
+
+        for section in output_sections.iter_mut() {
+            let data = match section.name.as_ref() {
+                ".iplt" => &iplt_data,
+                ".igot.plt" => &igot_plt_data,
+                ".rela.iplt" => &rela_iplt_data,
+                _ => continue,
+            };
+            section.data = Cow::Owned(data.clone());
+        }
+
+        Ok(())
+    }
+}
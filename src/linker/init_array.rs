@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use crate::elf::ELF;
+use crate::elf::section::SectionType;
+use crate::elf::symbol::{self, Binding, SymbolIndex};
+
+use super::Linker;
+use super::dynamic::SYNTHETIC_OBJECT;
+use super::gc::InputSectionId;
+use super::output::{ResolvedSymbol, Section};
+
+/// The arrays of function pointers run at startup and exit, with the symbols that
+/// the C runtime uses to find their bounds.
+const ARRAY_SECTIONS: [(&str, &str, &str); 3] = [
+    (
+        ".preinit_array",
+        "__preinit_array_start",
+        "__preinit_array_end",
+    ),
+    (".init_array", "__init_array_start", "__init_array_end"),
+    (".fini_array", "__fini_array_start", "__fini_array_end"),
+];
+
+/// Priority of the input sections without one, which come after all the others.
+const DEFAULT_PRIORITY: u32 = 65536;
+
+/// Returns the output section that `.ctors` and `.dtors` are merged into, as with
+/// the default GNU ld linker script.
+pub(super) fn array_section_name(name: &str) -> Option<&'static str> {
+    match name {
+        ".ctors" => Some(".init_array"),
+        ".dtors" => Some(".fini_array"),
+        _ => None,
+    }
+}
+
+/// Returns the type of the output section `name`.
+pub(super) fn array_section_type(name: &str) -> Option<SectionType> {
+    match name {
+        ".init_array" => Some(SectionType::InitArray),
+        ".fini_array" => Some(SectionType::FiniArray),
+        ".preinit_array" => Some(SectionType::PreInitArray),
+        _ => None,
+    }
+}
+
+/// Returns the priority of an input section of `.init_array` or `.fini_array`, which
+/// orders the constructors: `.init_array.NNNNN` runs before the constructors with a
+/// higher priority. `.ctors` runs from the end, so `.ctors.NNNNN` has priority
+/// `65535 - NNNNN`.
+fn init_priority(name: &str) -> Option<u32> {
+    let (reversed, rest) = [
+        (".init_array", false),
+        (".fini_array", false),
+        (".ctors", true),
+        (".dtors", true),
+    ]
+    .into_iter()
+    .find_map(|(prefix, reversed)| name.strip_prefix(prefix).map(|rest| (reversed, rest)))?;
+    if rest.is_empty() {
+        return Some(DEFAULT_PRIORITY);
+    }
+
+    let priority = rest.strip_prefix('.')?.parse::<u32>().ok();
+    Some(match priority {
+        Some(priority) if priority < DEFAULT_PRIORITY && reversed => 65535 - priority,
+        Some(priority) if priority < DEFAULT_PRIORITY => priority,
+        _ => DEFAULT_PRIORITY,
+    })
+}
+
+/// Returns the input sections of `objects` in the order they are placed in the
+/// output: command line order, except that the sections of `.init_array` and
+/// `.fini_array` are sorted by [`init_priority`].
+pub(super) fn input_order(objects: &[ELF]) -> Vec<InputSectionId> {
+    let mut order: Vec<InputSectionId> = objects
+        .iter()
+        .enumerate()
+        .flat_map(|(obj_idx, obj)| {
+            (0..obj.section_headers.len()).map(move |section_idx| (obj_idx, section_idx as u16))
+        })
+        .collect();
+    let name = |(obj_idx, section_idx): InputSectionId| {
+        objects[obj_idx].section_headers[section_idx as usize]
+            .name
+            .as_str()
+    };
+
+    // the sorted sections take the places of the sections with a priority
+    let slots: Vec<usize> = (0..order.len())
+        .filter(|&idx| init_priority(name(order[idx])).is_some())
+        .collect();
+    let mut sorted: Vec<InputSectionId> = slots.iter().map(|&idx| order[idx]).collect();
+    sorted.sort_by_key(|&id| init_priority(name(id)));
+    for (slot, id) in slots.into_iter().zip(sorted) {
+        order[slot] = id;
+    }
+
+    order
+}
+
+impl Linker {
+    /// Defines the `__init_array_start`-style symbols that are referenced. Their
+    /// values are set by [`Linker::assign_array_boundaries`] once the layout is known.
+    pub(super) fn define_array_symbols(
+        &self,
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) {
+        if self.options.relocatable {
+            return;
+        }
+        let names = ARRAY_SECTIONS
+            .iter()
+            .flat_map(|&(_, start, end)| [start, end]);
+        for name in names {
+            if resolved_symbols.get(name).is_none_or(|s| s.is_defined) {
+                continue;
+            }
+            resolved_symbols.insert(
+                name.to_string(),
+                ResolvedSymbol {
+                    name: name.to_string(),
+                    value: 0,
+                    size: 0,
+                    info: symbol::Info {
+                        r#type: symbol::Type::NoType,
+                        binding: Binding::Global,
+                    },
+                    visibility: symbol::Visibility::Hidden,
+                    shndx: SymbolIndex::Abs as u16,
+                    object_index: SYNTHETIC_OBJECT,
+                    is_defined: true,
+                },
+            );
+        }
+    }
+
+    /// Sets the `__init_array_start`-style symbols to the bounds of their output
+    /// section. Without the section, they stay at 0, so that the array is empty.
+    pub(super) fn assign_array_boundaries(
+        &self,
+        output_sections: &[Section<'static>],
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) {
+        for (section_name, start, end) in ARRAY_SECTIONS {
+            let Some(section) = output_sections.iter().find(|s| s.name == section_name) else {
+                continue;
+            };
+            let Some(first) = section.inputs.first() else {
+                continue;
+            };
+            for (name, value) in [(start, section.addr), (end, section.addr + section.size)] {
+                let Some(symbol) = resolved_symbols
+                    .get_mut(name)
+                    .filter(|s| s.object_index == SYNTHETIC_OBJECT)
+                else {
+                    continue;
+                };
+                // attributed to the section so that the symbol table refers to it
+                symbol.value = value;
+                symbol.object_index = first.object_index;
+                symbol.shndx = first.section_index;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_sort_by_init_priority() {
+        assert_eq!(init_priority(".init_array.00100"), Some(100));
+        assert_eq!(init_priority(".fini_array"), Some(DEFAULT_PRIORITY));
+        assert_eq!(init_priority(".ctors.65435"), Some(100));
+        assert_eq!(init_priority(".dtors.foo"), Some(DEFAULT_PRIORITY));
+        assert_eq!(init_priority(".init_arrayx"), None);
+        assert_eq!(init_priority(".data"), None);
+    }
+}
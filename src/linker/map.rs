@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use super::Linker;
+use super::dynamic::SYNTHETIC_OBJECT;
+use super::output::{InputSection, ResolvedSymbol, Section};
+use super::thunk::{THUNK_OBJECT, Thunks};
+
+impl Linker {
+    /// Describes the layout in the format of the map files of LLD: each output
+    /// section, followed by its input sections and the symbols defined in them.
+    /// Thunks are listed as symbols named `__AArch64ADRPThunk_<target>`.
+    pub(super) fn render_map(
+        &self,
+        output_sections: &[Section<'static>],
+        thunks: &Thunks,
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> String {
+        let mut symbols: HashMap<(usize, u16), Vec<&ResolvedSymbol>> = HashMap::new();
+        for symbol in resolved_symbols.values() {
+            symbols
+                .entry((symbol.object_index, symbol.shndx))
+                .or_default()
+                .push(symbol);
+        }
+
+        let mut map = String::new();
+        let _ = writeln!(
+            map,
+            "             VMA     Size Align Out     In      Symbol"
+        );
+        for section in output_sections {
+            let _ = writeln!(
+                map,
+                "{:>16x} {:>8x} {:>5} {}",
+                section.addr, section.size, section.align, section.name
+            );
+
+            let mut inputs: Vec<&InputSection> =
+                section.inputs.iter().filter(|i| !i.folded).collect();
+            inputs.sort_by_key(|input| input.offset);
+            for input in inputs {
+                let addr = section.addr + input.offset;
+                let (name, size, align) = self.describe_input(section, input, thunks);
+                let _ = writeln!(
+                    map,
+                    "{:>16x} {:>8x} {:>5}         {}",
+                    addr, size, align, name
+                );
+
+                if input.object_index == THUNK_OBJECT {
+                    let area = &thunks.areas[input.section_index as usize];
+                    for (idx, target) in area.targets.iter().enumerate() {
+                        let _ = writeln!(
+                            map,
+                            "{:>16x} {:>8x}                       __AArch64ADRPThunk_{}",
+                            addr + idx as u64 * thunks.thunk_size,
+                            thunks.thunk_size,
+                            self.thunk_target_name(target)
+                        );
+                    }
+                    continue;
+                }
+
+                let mut defined = symbols
+                    .get(&(input.object_index, input.section_index))
+                    .cloned()
+                    .unwrap_or_default();
+                defined.sort_by(|a, b| (a.value, &a.name).cmp(&(b.value, &b.name)));
+                for symbol in defined {
+                    let _ = writeln!(
+                        map,
+                        "{:>16x} {:>8x}                       {}",
+                        symbol.value, symbol.size, symbol.name
+                    );
+                }
+            }
+        }
+        map
+    }
+
+    /// Returns the name, size and alignment shown for an input section.
+    fn describe_input(
+        &self,
+        section: &Section<'static>,
+        input: &InputSection,
+        thunks: &Thunks,
+    ) -> (String, u64, u64) {
+        match input.object_index {
+            THUNK_OBJECT => {
+                let area = &thunks.areas[input.section_index as usize];
+                let (obj_idx, section_idx) = area.caller;
+                let caller = &self.objects[obj_idx].section_headers[section_idx as usize];
+                (
+                    format!(
+                        "<internal>:(thunks for {}:({}))",
+                        self.object_file_name(obj_idx),
+                        caller.name
+                    ),
+                    area.targets.len() as u64 * thunks.thunk_size,
+                    4,
+                )
+            }
+            SYNTHETIC_OBJECT => (
+                format!("<internal>:({})", section.name),
+                section.size,
+                section.align,
+            ),
+            obj_idx => {
+                let header = &self.objects[obj_idx].section_headers[input.section_index as usize];
+                // merged sections only contribute the pieces that were kept
+                let size = if input.pieces.is_empty() {
+                    header.size
+                } else {
+                    input.pieces.iter().map(|p| p.size).sum()
+                };
+                (
+                    format!("{}:({})", self.object_file_name(obj_idx), header.name),
+                    size,
+                    header.addralign,
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::elf::ELF;
+use crate::elf::section::{Header, SectionFlag, SectionType};
+
+use super::gc::InputSectionId;
+use super::output::MergePiece;
+use super::section::{align, output_section_name};
+
+/// The contents of mergeable input sections (`SHF_MERGE`) that go into the same
+/// output section, with identical pieces stored once.
+#[derive(Debug, Default)]
+pub(super) struct MergedSection {
+    /// Deduplicated contents.
+    pub data: Vec<u8>,
+    /// Alignment of the merged contents.
+    pub align: u64,
+    /// The input sections merged here, with the location of each of their pieces.
+    pub members: Vec<(InputSectionId, Vec<MergePiece>)>,
+}
+
+/// A mergeable input section split into `(offset, bytes)` pieces.
+type SplitSection<'a> = (InputSectionId, Vec<(u64, &'a [u8])>);
+
+/// Merges the live mergeable sections of all objects.
+///
+/// Input sections are merged together if they go into the same output section and
+/// have the same flags and entry size. Their contents are split into pieces: NUL
+/// terminated strings for `SHF_STRINGS` sections, and `sh_entsize` sized records
+/// otherwise. Identical pieces are stored only once. With `tail_merge`, a string
+/// that is a suffix of another string is stored as part of the longer string.
+///
+/// Returns the merged sections, and the index of the merged section each input
+/// section belongs to.
+pub(super) fn merge_mergeable_sections(
+    objects: &[ELF],
+    live_sections: &HashSet<InputSectionId>,
+    tail_merge: bool,
+) -> (Vec<MergedSection>, HashMap<InputSectionId, usize>) {
+    let mut keys: HashMap<(&str, bool, u64), usize> = HashMap::new();
+    let mut inputs: Vec<Vec<SplitSection>> = Vec::new();
+    let mut membership = HashMap::new();
+
+    for (obj_idx, obj) in objects.iter().enumerate() {
+        for (section_idx, header) in obj.section_headers.iter().enumerate() {
+            let id = (obj_idx, section_idx as u16);
+            if !live_sections.contains(&id) || !is_mergeable(obj, section_idx as u16, header) {
+                continue;
+            }
+
+            let strings = header.flags.contains(&SectionFlag::Strings);
+            let key = (output_section_name(&header.name), strings, header.entsize);
+            let next = inputs.len();
+            let merged_idx = *keys.entry(key).or_insert(next);
+            if merged_idx == next {
+                inputs.push(Vec::new());
+            }
+
+            inputs[merged_idx].push((id, split_pieces(header, strings)));
+            membership.insert(id, merged_idx);
+        }
+    }
+
+    let merged = inputs
+        .into_iter()
+        .map(|members| {
+            let &((obj_idx, section_idx), _) = &members[0];
+            let header = &objects[obj_idx].section_headers[section_idx as usize];
+            let align = members
+                .iter()
+                .map(|((obj_idx, section_idx), _)| {
+                    objects[*obj_idx].section_headers[*section_idx as usize].addralign
+                })
+                .max()
+                .unwrap_or(1)
+                .max(1);
+            let strings = header.flags.contains(&SectionFlag::Strings);
+
+            if tail_merge && strings && header.entsize == 1 && align == 1 {
+                merge_tails(members)
+            } else {
+                merge_pieces(members, align)
+            }
+        })
+        .collect();
+
+    (merged, membership)
+}
+
+/// Returns true if the input section can be split into pieces and merged.
+fn is_mergeable(obj: &ELF, section_idx: u16, header: &Header) -> bool {
+    header.flags.contains(&SectionFlag::Merge)
+        && header.r#type == SectionType::ProgBits
+        && header.entsize > 0
+        && header.size % header.entsize == 0
+        // pieces cannot be moved around if the section itself has relocations
+        && !obj
+            .relocations
+            .iter()
+            .any(|r| r.target_section == section_idx && !r.entries.is_empty())
+}
+
+/// Splits the contents of a mergeable section into `(offset, bytes)` pieces.
+fn split_pieces(header: &Header, strings: bool) -> Vec<(u64, &[u8])> {
+    let data = header.section_raw_data.as_slice();
+    let entsize = header.entsize as usize;
+
+    if !strings {
+        return data
+            .chunks(entsize)
+            .enumerate()
+            .map(|(i, record)| ((i * entsize) as u64, record))
+            .collect();
+    }
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (i, unit) in data.chunks(entsize).enumerate() {
+        if unit.iter().all(|&b| b == 0) {
+            let end = (i + 1) * entsize;
+            pieces.push((start as u64, &data[start..end]));
+            start = end;
+        }
+    }
+    // a string without terminator is kept as is
+    if start < data.len() {
+        pieces.push((start as u64, &data[start..]));
+    }
+    pieces
+}
+
+/// Stores each distinct piece once, in order of first appearance.
+fn merge_pieces(members: Vec<SplitSection<'_>>, piece_align: u64) -> MergedSection {
+    let mut merged = MergedSection {
+        align: piece_align,
+        ..Default::default()
+    };
+    let mut offsets: HashMap<&[u8], u64> = HashMap::new();
+
+    for (id, pieces) in members {
+        let pieces = pieces
+            .into_iter()
+            .map(|(input_offset, bytes)| {
+                let output_offset = *offsets.entry(bytes).or_insert_with(|| {
+                    let offset = align(merged.data.len() as u64, merged.align);
+                    merged.data.resize(offset as usize, 0);
+                    merged.data.extend_from_slice(bytes);
+                    offset
+                });
+                MergePiece {
+                    input_offset,
+                    size: bytes.len() as u64,
+                    output_offset,
+                }
+            })
+            .collect();
+        merged.members.push((id, pieces));
+    }
+
+    merged
+}
+
+/// Stores each distinct string once, and stores strings that are a suffix of
+/// another string as part of it (e.g. `"bar"` is found at the end of `"foobar"`).
+fn merge_tails(members: Vec<SplitSection<'_>>) -> MergedSection {
+    let mut strings: Vec<&[u8]> = members
+        .iter()
+        .flat_map(|(_, pieces)| pieces.iter().map(|(_, bytes)| *bytes))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    // a suffix sorts right after the strings that end with it
+    strings.sort_by(|a, b| b.iter().rev().cmp(a.iter().rev()));
+
+    let mut merged = MergedSection {
+        align: 1,
+        ..Default::default()
+    };
+    let mut offsets: HashMap<&[u8], u64> = HashMap::new();
+    let mut previous: Option<(&[u8], u64)> = None;
+
+    for string in strings {
+        let offset = match previous {
+            Some((longer, offset)) if longer.ends_with(string) => {
+                offset + (longer.len() - string.len()) as u64
+            }
+            _ => {
+                let offset = merged.data.len() as u64;
+                merged.data.extend_from_slice(string);
+                previous = Some((string, offset));
+                offset
+            }
+        };
+        offsets.insert(string, offset);
+    }
+
+    for (id, pieces) in members {
+        let pieces = pieces
+            .into_iter()
+            .map(|(input_offset, bytes)| MergePiece {
+                input_offset,
+                size: bytes.len() as u64,
+                output_offset: offsets[bytes],
+            })
+            .collect();
+        merged.members.push((id, pieces));
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn strings(data: &[u8]) -> Header {
+        Header {
+            name_idx: 0,
+            name: ".rodata.str1.1".to_string(),
+            r#type: SectionType::ProgBits,
+            flags: vec![SectionFlag::Alloc, SectionFlag::Merge, SectionFlag::Strings],
+            addr: 0,
+            offset: 0,
+            size: data.len() as u64,
+            link: 0,
+            info: 0,
+            addralign: 1,
+            entsize: 1,
+            section_raw_data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn should_split_strings() {
+        let header = strings(b"foo\0bar\0");
+        assert_eq!(
+            split_pieces(&header, true),
+            vec![(0, &b"foo\0"[..]), (4, &b"bar\0"[..])]
+        );
+    }
+
+    #[test]
+    fn should_merge_tails() {
+        let a = strings(b"foobar\0bar\0");
+        let b = strings(b"ar\0baz\0");
+        let merged = merge_tails(vec![
+            ((0, 1), split_pieces(&a, true)),
+            ((1, 1), split_pieces(&b, true)),
+        ]);
+
+        assert_eq!(merged.data, b"baz\0foobar\0");
+        let offsets: Vec<_> = merged
+            .members
+            .iter()
+            .flat_map(|(_, pieces)| pieces.iter().map(|p| p.output_offset))
+            .collect();
+        assert_eq!(offsets, vec![4, 7, 8, 0]);
+    }
+}
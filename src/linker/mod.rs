@@ -1,22 +1,60 @@
+mod archive;
+mod comdat;
+pub mod defsym;
+mod dynamic;
+mod eh_frame;
+mod gc;
+mod icf;
+mod ifunc;
+mod init_array;
+mod map;
+mod merge;
+pub mod options;
 pub mod output;
+mod relax;
+mod relocatable;
 mod relocation;
 mod section;
+mod shared;
 mod symbol;
+mod target;
+mod thunk;
+mod tls;
+pub mod version_script;
 mod writer;
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
-use crate::elf::ELF;
+use crate::elf::{ELF, header};
 use crate::error::{LinkerError, Result};
 use crate::parser;
 
+pub use options::Options;
 pub use section::{BASE_ADDR, align};
 
 #[derive(Debug, Default)]
 pub struct Linker {
     objects: Vec<ELF>,
     object_names: Vec<String>,
+    /// Members of the archives, `None` once pulled into the link.
+    archive_members: Vec<Option<archive::ArchiveMember>>,
+    /// Signatures of the COMDAT groups kept so far, mapped to the object that defines them.
+    comdat_groups: HashMap<String, usize>,
+    /// Members of discarded COMDAT groups, mapped to the matching section of the kept group.
+    discarded_sections: HashMap<gc::InputSectionId, Option<gc::InputSectionId>>,
+    /// Shared objects linked against, in command line order.
+    shared_objects: Vec<shared::SharedObject>,
+    /// Machine of the inputs, `Machine::None` until the first one is added.
+    machine: header::Machine,
+    /// Class and byte order of the inputs, with `Class::None` until the first one is added.
+    encoding: header::Encoding,
+    /// Flags for the ELF header (`e_flags`), combined from those of the inputs.
+    flags: u32,
+    options: Options,
+    /// What the last link did to the input sections.
+    report: output::Report,
 }
 
 impl Linker {
@@ -24,60 +62,75 @@ impl Linker {
         Linker {
             objects: Vec::new(),
             object_names: Vec::new(),
+            archive_members: Vec::new(),
+            comdat_groups: HashMap::new(),
+            discarded_sections: HashMap::new(),
+            shared_objects: Vec::new(),
+            machine: header::Machine::None,
+            encoding: header::Encoding::default(),
+            flags: 0,
+            options: Options::default(),
+            report: output::Report::default(),
         }
     }
 
+    pub fn with_options(options: Options) -> Self {
+        Linker {
+            options,
+            ..Linker::new()
+        }
+    }
+
+    /// Returns what the last call to [`Linker::link_to_file`] did to the input
+    /// sections, such as the sections removed by `--gc-sections`.
+    pub fn report(&self) -> &output::Report {
+        &self.report
+    }
+
     pub fn add_objects(&mut self, paths: &[&Path]) -> Result<()> {
         for path in paths {
             let obj = fs::read(path).map_err(|e| LinkerError::Io {
                 error: e,
                 context: Some(format!("reading file: {}", path.display())),
             })?;
-            let elf = parser::parse_elf(&obj)
-                .map_err(|e| match e {
-                    nom::Err::Error(parse_err) | nom::Err::Failure(parse_err) => {
-                        LinkerError::Parse {
-                            error: parse_err,
-                            context: Some(format!("parsing file: {}", path.display())),
-                        }
-                    }
-                    nom::Err::Incomplete(_) => LinkerError::Generic {
-                        message: "Incomplete input data".to_string(),
-                        context: Some(format!("parsing file: {}", path.display())),
-                    },
-                })?
-                .1;
-            self.objects.push(elf);
-            self.object_names.push(path.display().to_string());
+            self.add_input(
+                path.display().to_string(),
+                &obj,
+                format!("parsing file: {}", path.display()),
+            )?;
         }
         Ok(())
     }
 
     pub fn link_to_file(&mut self, inputs: Vec<Vec<u8>>) -> Result<Vec<u8>> {
         for (idx, input) in inputs.iter().enumerate() {
-            let obj = parser::parse_elf(input)
-                .map_err(|e| match e {
-                    nom::Err::Error(parse_err) | nom::Err::Failure(parse_err) => {
-                        LinkerError::Parse {
-                            error: parse_err,
-                            context: Some(format!("parsing input object {}", idx)),
-                        }
-                    }
-                    nom::Err::Incomplete(_) => LinkerError::Generic {
-                        message: "Incomplete input data".to_string(),
-                        context: Some(format!("parsing input object {}", idx)),
-                    },
-                })?
-                .1;
-            self.objects.push(obj);
-            self.object_names.push(format!("input_{}", idx));
+            self.add_input(
+                format!("input_{}", idx),
+                input,
+                format!("parsing input object {}", idx),
+            )?;
         }
+        self.extract_archive_members()?;
+        self.check_target_options()?;
+
         let mut resolved_symbols = self.resolve_symbols()?;
-        let (output_sections, section_name_offsets) =
-            self.layout_sections(&mut resolved_symbols)?;
+        let (output_sections, section_name_offsets) = if self.options.relocatable {
+            self.layout_relocatable(&mut resolved_symbols)?
+        } else {
+            let (output_sections, section_name_offsets, report) =
+                self.layout_sections(&mut resolved_symbols)?;
+            self.report = report;
+            (output_sections, section_name_offsets)
+        };
+        if let (Some(path), Some(map)) = (&self.options.map, &self.report.map) {
+            fs::write(path, map).map_err(|e| LinkerError::Io {
+                error: e,
+                context: Some(format!("writing map file: {}", path.display())),
+            })?;
+        }
         let mut out = std::io::Cursor::new(Vec::new());
 
-        self.write_executable(
+        self.write_output(
             &mut out,
             resolved_symbols,
             output_sections,
@@ -86,12 +139,49 @@ impl Linker {
 
         Ok(out.into_inner())
     }
+
+    /// Adds an object file, a static library or a shared object to the link.
+    ///
+    /// Archive members are kept aside and only become part of the link once
+    /// [`Linker::extract_archive_members`] finds that they are needed.
+    fn add_input(&mut self, name: String, data: &[u8], context: String) -> Result<()> {
+        if parser::archive::is_archive(data) {
+            let archive = parser::archive::parse(data)
+                .map_err(|e| parse_error(e, context.clone()))?
+                .1;
+            return self.add_archive(&name, archive, &context);
+        }
+
+        let elf = parser::parse_elf(data)
+            .map_err(|e| parse_error(e, context))?
+            .1;
+        self.check_machine(&name, &elf.header)?;
+        if elf.header.r#type == header::Type::Dyn {
+            self.add_shared_object(name, elf);
+        } else {
+            self.add_object(name, elf);
+        }
+        Ok(())
+    }
+}
+
+fn parse_error(e: nom::Err<parser::error::ParseError>, context: String) -> LinkerError {
+    match e {
+        nom::Err::Error(parse_err) | nom::Err::Failure(parse_err) => LinkerError::Parse {
+            error: parse_err,
+            context: Some(context),
+        },
+        nom::Err::Incomplete(_) => LinkerError::Generic {
+            message: "Incomplete input data".to_string(),
+            context: Some(context),
+        },
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::elf::section;
+    use crate::elf::{dynamic, relocation, section, symbol};
     use pretty_assertions::assert_eq;
     use std::path::Path;
 
@@ -227,7 +317,11 @@ mod tests {
         let mut resolved_symbols = linker.resolve_symbols().unwrap();
         let mut output_sections = linker.layout_sections(&mut resolved_symbols).unwrap().0;
         linker
-            .apply_relocations(&mut output_sections, &resolved_symbols)
+            .apply_relocations(
+                &mut output_sections,
+                &resolved_symbols,
+                &thunk::Thunks::new(12),
+            )
             .unwrap();
 
         let text_section = output_sections.iter().find(|s| s.name == ".text").unwrap();
@@ -245,4 +339,1381 @@ mod tests {
 
         assert!(addr_field != 0, "Address field not updated by relocation");
     }
+
+    #[test]
+    fn test_wrap_symbol() {
+        let main_o = Path::new("src/linker/fixtures/wrap_main.o");
+        let malloc_o = Path::new("src/linker/fixtures/wrap_malloc.o");
+
+        let mut options = Options::default();
+        options.wrap.insert("malloc".to_string());
+
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[main_o, malloc_o]).unwrap();
+
+        let mut resolved_symbols = linker.resolve_symbols().unwrap();
+        assert!(
+            !resolved_symbols.contains_key("__real_malloc"),
+            "__real_malloc should be bound to malloc"
+        );
+
+        let output_sections = linker.layout_sections(&mut resolved_symbols).unwrap().0;
+        let text_section = output_sections.iter().find(|s| s.name == ".text").unwrap();
+
+        let adr_target = |offset: usize| {
+            let instruction =
+                u32::from_le_bytes(text_section.data[offset..offset + 4].try_into().unwrap());
+            let immlo = ((instruction >> 29) & 0x3) as u64;
+            let immhi = ((instruction >> 5) & 0x7FFFF) as u64;
+            text_section.addr + offset as u64 + ((immhi << 2) | immlo)
+        };
+
+        assert_eq!(
+            adr_target(0),
+            resolved_symbols["__wrap_malloc"].value,
+            "Reference to malloc should be bound to __wrap_malloc"
+        );
+        assert_eq!(
+            adr_target(4),
+            resolved_symbols["malloc"].value,
+            "Reference to __real_malloc should be bound to malloc"
+        );
+    }
+
+    #[test]
+    fn test_defsym() {
+        let main_o = Path::new("src/linker/fixtures/defsym_main.o");
+
+        let options = Options {
+            defsym: vec![
+                "firmware_version=0x1234".parse().unwrap(),
+                // refers to a symbol assigned later on the command line
+                "start_alias=entry_alias-8".parse().unwrap(),
+                "entry_alias=_start+8".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[main_o]).unwrap();
+
+        let mut resolved_symbols = linker.resolve_symbols().unwrap();
+        let output_sections = linker.layout_sections(&mut resolved_symbols).unwrap().0;
+
+        let version = &resolved_symbols["firmware_version"];
+        assert_eq!(version.value, 0x1234);
+        assert!(symbol::SymbolIndex::Abs == version.shndx);
+
+        let start = &resolved_symbols["_start"];
+        let alias = &resolved_symbols["entry_alias"];
+        assert_eq!(alias.value, start.value + 8);
+        assert_eq!(
+            alias.shndx, start.shndx,
+            "entry_alias should be relative to .text"
+        );
+        assert_eq!(resolved_symbols["start_alias"].value, start.value);
+
+        let text_section = output_sections.iter().find(|s| s.name == ".text").unwrap();
+        let instruction = u32::from_le_bytes(text_section.data[0..4].try_into().unwrap());
+        assert_eq!(
+            instruction & 0x60FFFFE0,
+            (8 >> 2) << 5,
+            "adr should point 8 bytes ahead"
+        );
+
+        let options = Options {
+            defsym: vec![
+                "entry_alias=_start".parse().unwrap(),
+                "a=b+1".parse().unwrap(),
+                "b=a-1".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[main_o]).unwrap();
+        let mut resolved_symbols = linker.resolve_symbols().unwrap();
+        let err = linker.layout_sections(&mut resolved_symbols).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid expression 'a=(b + 0x1)': circular reference between --defsym symbols"
+        );
+
+        // a reference to the symbol being assigned is to its input definition
+        let options = Options {
+            defsym: vec![
+                "entry_alias=_start".parse().unwrap(),
+                "_start=_start+4".parse().unwrap(),
+            ],
+            ..Default::default()
+        };
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[main_o]).unwrap();
+        let mut resolved_symbols = linker.resolve_symbols().unwrap();
+        let output_sections = linker.layout_sections(&mut resolved_symbols).unwrap().0;
+        let text_section = output_sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!(resolved_symbols["_start"].value, text_section.addr + 4);
+        assert_eq!(resolved_symbols["entry_alias"].value, text_section.addr + 4);
+    }
+
+    #[test]
+    fn test_undefined_extracts_archive_member() {
+        let main_o = Path::new("src/linker/fixtures/plugin_main.o");
+        let lib = Path::new("src/linker/fixtures/libplugin.a");
+
+        let mut linker = Linker::new();
+        linker.add_objects(&[main_o, lib]).unwrap();
+        linker.extract_archive_members().unwrap();
+        assert_eq!(
+            linker.objects.len(),
+            1,
+            "Unreferenced members should stay out"
+        );
+
+        let options = Options {
+            undefined: vec!["plugin_register".to_string()],
+            ..Default::default()
+        };
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[main_o, lib]).unwrap();
+        linker.extract_archive_members().unwrap();
+        assert_eq!(
+            linker.object_names,
+            vec![
+                "src/linker/fixtures/plugin_main.o",
+                "src/linker/fixtures/libplugin.a(plugin_registration_with_a_long_name.o)",
+            ]
+        );
+
+        let resolved_symbols = linker.resolve_symbols().unwrap();
+        assert!(resolved_symbols["plugin_register"].is_defined);
+        assert!(!resolved_symbols.contains_key("plugin_unused"));
+    }
+
+    #[test]
+    fn test_require_defined() {
+        let main_o = Path::new("src/linker/fixtures/plugin_main.o");
+
+        let options = Options {
+            require_defined: vec!["plugin_register".to_string()],
+            ..Default::default()
+        };
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[main_o]).unwrap();
+        linker.extract_archive_members().unwrap();
+
+        let err = linker.resolve_symbols().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Required symbol 'plugin_register' is not defined"
+        );
+    }
+
+    #[test]
+    fn test_gc_sections() {
+        let main_o = Path::new("src/linker/fixtures/gc_main.o");
+
+        let options = Options {
+            gc_sections: true,
+            ..Default::default()
+        };
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[main_o]).unwrap();
+
+        let mut resolved_symbols = linker.resolve_symbols().unwrap();
+        let live_sections = linker.collect_live_sections(&resolved_symbols);
+        let mut live_names: Vec<_> = live_sections
+            .iter()
+            .map(|&(obj_idx, idx)| {
+                linker.objects[obj_idx].section_headers[idx as usize]
+                    .name
+                    .as_str()
+            })
+            .collect();
+        live_names.sort();
+        assert_eq!(
+            live_names,
+            vec![
+                ".data.used_data",
+                ".rodata.retained",
+                ".text._start",
+                ".text.used_func"
+            ]
+        );
+
+        let (output_sections, _, report) = linker.layout_sections(&mut resolved_symbols).unwrap();
+        assert!(!resolved_symbols.contains_key("unused_func"));
+        let removed: Vec<_> = report
+            .removed_sections
+            .iter()
+            .map(|removed| (removed.file.as_str(), removed.section.as_str()))
+            .collect();
+        let main_o = main_o.to_str().unwrap();
+        assert_eq!(
+            removed,
+            vec![
+                (main_o, ".text"),
+                (main_o, ".text.unused_func"),
+                (main_o, ".data.unused_data")
+            ]
+        );
+
+        let text_section = output_sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!(text_section.size, 0x14, "unused_func should be discarded");
+
+        // bl used_func
+        let instruction = u32::from_le_bytes(text_section.data[0..4].try_into().unwrap());
+        let target = text_section.addr + ((instruction & 0x3FFFFFF) << 2) as u64;
+        assert_eq!(target, resolved_symbols["used_func"].value);
+
+        let data_section = output_sections.iter().find(|s| s.name == ".data").unwrap();
+        assert_eq!(data_section.data.as_ref(), &[1, 0, 0, 0]);
+    }
+
+    fn fold_sections(icf: options::Icf) -> (Linker, Vec<(String, String)>) {
+        let main_o = Path::new("src/linker/fixtures/icf_main.o");
+
+        let options = Options {
+            icf,
+            ..Default::default()
+        };
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[main_o]).unwrap();
+
+        let resolved_symbols = linker.resolve_symbols().unwrap();
+        let mut live_sections = linker.collect_live_sections(&resolved_symbols);
+        let section_name = |(obj_idx, idx): (usize, u16)| {
+            linker.objects[obj_idx].section_headers[idx as usize]
+                .name
+                .clone()
+        };
+        let mut folded: Vec<_> = linker
+            .fold_identical_sections(&mut live_sections, &resolved_symbols)
+            .into_iter()
+            .map(|(folded, kept)| (section_name(folded), section_name(kept)))
+            .collect();
+        folded.sort();
+
+        (linker, folded)
+    }
+
+    #[test]
+    fn test_icf_all() {
+        let (linker, folded) = fold_sections(options::Icf::All);
+        let pair = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(
+            folded,
+            vec![
+                pair(".rodata.str_b", ".rodata.str_a"),
+                pair(".text.f2", ".text.f1"),
+                pair(".text.f5", ".text.f4"),
+                pair(".text.f6", ".text.f4"),
+            ]
+        );
+
+        let mut resolved_symbols = linker.resolve_symbols().unwrap();
+        let (output_sections, _, report) = linker.layout_sections(&mut resolved_symbols).unwrap();
+        let identical: Vec<_> = report
+            .folded_sections
+            .iter()
+            .map(|(kept, folded)| {
+                let folded: Vec<_> = folded.iter().map(|s| s.section.as_str()).collect();
+                (kept.section.as_str(), folded)
+            })
+            .collect();
+        assert_eq!(
+            identical,
+            vec![
+                (".text.f1", vec![".text.f2"]),
+                (".text.f4", vec![".text.f5", ".text.f6"]),
+                (".rodata.str_a", vec![".rodata.str_b"]),
+            ]
+        );
+        assert_eq!(resolved_symbols["f1"].value, resolved_symbols["f2"].value);
+        assert_eq!(resolved_symbols["f4"].value, resolved_symbols["f6"].value);
+        assert_eq!(
+            resolved_symbols["str_a"].value,
+            resolved_symbols["str_b"].value
+        );
+
+        // _start, f1, f3 and f4
+        let text_section = output_sections.iter().find(|s| s.name == ".text").unwrap();
+        assert_eq!(text_section.size, 0x28 + 0x8 + 0x8 + 0x4);
+
+        // bl f6 is redirected to f4
+        let instruction = u32::from_le_bytes(text_section.data[0x10..0x14].try_into().unwrap());
+        let target = text_section.addr + 0x10 + ((instruction & 0x3FFFFFF) << 2) as u64;
+        assert_eq!(target, resolved_symbols["f4"].value);
+    }
+
+    #[test]
+    fn test_icf_safe() {
+        let (_, folded) = fold_sections(options::Icf::Safe);
+        // f2 and the strings have their address taken
+        assert_eq!(
+            folded,
+            vec![(".text.f5".to_string(), ".text.f4".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_comdat_groups() {
+        let a_o = Path::new("src/linker/fixtures/comdat_a.o");
+        let b_o = Path::new("src/linker/fixtures/comdat_b.o");
+
+        let mut linker = Linker::new();
+        linker.add_objects(&[a_o, b_o]).unwrap();
+
+        let group = &linker.objects[1].groups[0];
+        assert_eq!(group.signature, "inline_fn");
+        assert!(group.is_comdat());
+        assert!(linker.is_discarded((1, group.members[0])));
+
+        // inline_fn is defined by both objects, but only the first copy is kept
+        let mut resolved_symbols = linker.resolve_symbols().unwrap();
+        assert_eq!(resolved_symbols["inline_fn"].object_index, 0);
+
+        let output_sections = linker.layout_sections(&mut resolved_symbols).unwrap().0;
+        let text_section = output_sections.iter().find(|s| s.name == ".text").unwrap();
+        // _start, use_b and one copy of inline_fn
+        assert_eq!(text_section.size, 0x10 + 0x8 + 0x8);
+
+        let rodata_section = output_sections
+            .iter()
+            .find(|s| s.name == ".rodata")
+            .unwrap();
+        assert_eq!(rodata_section.data.as_ref(), b"a\0");
+
+        // adr x1, .Lmsg in use_b is redirected to the kept copy of the string
+        let use_b = (resolved_symbols["use_b"].value - text_section.addr) as usize;
+        let instruction =
+            u32::from_le_bytes(text_section.data[use_b..use_b + 4].try_into().unwrap());
+        let imm = (((instruction >> 5) & 0x7FFFF) << 2) | ((instruction >> 29) & 0x3);
+        assert_eq!(
+            resolved_symbols["use_b"].value + imm as u64,
+            rodata_section.addr
+        );
+    }
+
+    /// Decodes the target of the ADR instruction at `addr`.
+    fn adr_target(section: &output::Section, addr: u64) -> u64 {
+        let pos = (addr - section.addr) as usize;
+        let instruction = u32::from_le_bytes(section.data[pos..pos + 4].try_into().unwrap());
+        let imm = (((instruction >> 5) & 0x7FFFF) << 2) | ((instruction >> 29) & 0x3);
+        // sign-extend the 21-bit immediate
+        addr.wrapping_add_signed(((imm << 11) as i32 >> 11) as i64)
+    }
+
+    fn link_merge_sections(optimize: u8) -> (Vec<output::Section<'static>>, u64) {
+        let a_o = Path::new("src/linker/fixtures/merge_a.o");
+        let b_o = Path::new("src/linker/fixtures/merge_b.o");
+
+        let options = Options {
+            optimize,
+            ..Default::default()
+        };
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[a_o, b_o]).unwrap();
+
+        let mut resolved_symbols = linker.resolve_symbols().unwrap();
+        let output_sections = linker.layout_sections(&mut resolved_symbols).unwrap().0;
+        (output_sections, resolved_symbols["greet"].value)
+    }
+
+    #[test]
+    fn test_merge_sections() {
+        let (output_sections, greet) = link_merge_sections(0);
+        let text_section = output_sections.iter().find(|s| s.name == ".text").unwrap();
+        let rodata_section = output_sections
+            .iter()
+            .find(|s| s.name == ".rodata")
+            .unwrap();
+
+        let pi = 0x400921fb54442d18_u64.to_le_bytes();
+        let e = 0x4005bf0a8b145769_u64.to_le_bytes();
+        let expected = [&b"hello\0world\0lo\0\0"[..], &pi, &e].concat();
+        assert_eq!(rodata_section.data.as_ref(), expected.as_slice());
+
+        // references from the second object point into the merged contents
+        let rodata = rodata_section.addr;
+        assert_eq!(adr_target(text_section, greet), rodata);
+        assert_eq!(adr_target(text_section, greet + 4), rodata + 12);
+        assert_eq!(adr_target(text_section, greet + 8), rodata + 16);
+    }
+
+    #[test]
+    fn test_merge_string_tails() {
+        let (output_sections, greet) = link_merge_sections(2);
+        let text_section = output_sections.iter().find(|s| s.name == ".text").unwrap();
+        let rodata_section = output_sections
+            .iter()
+            .find(|s| s.name == ".rodata")
+            .unwrap();
+
+        assert_eq!(&rodata_section.data[..12], b"hello\0world\0");
+        // "lo" is the tail of "hello"
+        assert_eq!(adr_target(text_section, greet + 4), rodata_section.addr + 3);
+    }
+
+    #[test]
+    fn test_relocatable_output() {
+        let inputs = vec![
+            fs::read("src/linker/fixtures/comdat_a.o").unwrap(),
+            fs::read("src/linker/fixtures/comdat_b.o").unwrap(),
+        ];
+
+        let options = Options {
+            relocatable: true,
+            ..Default::default()
+        };
+        let object = Linker::with_options(options)
+            .link_to_file(inputs.clone())
+            .unwrap();
+
+        let elf = parser::parse_elf(&object).unwrap().1;
+        assert_eq!(elf.header.r#type, crate::elf::header::Type::Rel);
+        assert_eq!(elf.groups.len(), 1);
+        assert_eq!(elf.groups[0].signature, "inline_fn");
+        let globals: Vec<_> = elf
+            .symbols
+            .iter()
+            .filter(|s| s.info.binding == symbol::Binding::Global)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(globals, vec!["_start", "inline_fn", "use_b"]);
+
+        // the relocatable object can be linked into an executable
+        let executable = Linker::new().link_to_file(vec![object]).unwrap();
+        let elf = parser::parse_elf(&executable).unwrap().1;
+        let text = elf
+            .section_headers
+            .iter()
+            .find(|s| s.name == ".text")
+            .unwrap();
+        let address = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap().value;
+
+        // bl inline_fn
+        let pos = (address("_start") - text.addr) as usize;
+        let instruction =
+            u32::from_le_bytes(text.section_raw_data[pos..pos + 4].try_into().unwrap());
+        let target = address("_start") + ((instruction & 0x3FFFFFF) << 2) as u64;
+        assert_eq!(target, address("inline_fn"));
+    }
+
+    #[test]
+    fn test_static_pie() {
+        let options = Options {
+            pie: true,
+            no_dynamic_linker: true,
+            ..Default::default()
+        };
+        let executable = Linker::with_options(options)
+            .link_to_file(vec![fs::read("src/linker/fixtures/pie_main.o").unwrap()])
+            .unwrap();
+
+        let elf = parser::parse_elf(&executable).unwrap().1;
+        assert_eq!(elf.header.r#type, crate::elf::header::Type::Dyn);
+        let address = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap().value;
+        let section = |name: &str| elf.section_headers.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(elf.header.entry, address("_start"));
+        assert!(address("_start") < BASE_ADDR);
+        assert_eq!(address("_DYNAMIC"), section(".dynamic").addr);
+
+        // pointers to sections are relocated, the absolute symbol is not
+        let rela_dyn = elf
+            .relocations
+            .iter()
+            .find(|r| r.entries.len() == 2)
+            .unwrap();
+        let relocations: Vec<_> = rela_dyn
+            .entries
+            .iter()
+            .map(|r| (r.offset, r.info.r#type, r.addend))
+            .collect();
+        let pointers = address("pointers");
+        assert_eq!(
+            relocations,
+            vec![
+                (
+                    pointers,
+                    relocation::RelocationType::Aarch64Relative,
+                    address("_start") as i64
+                ),
+                (
+                    pointers + 8,
+                    relocation::RelocationType::Aarch64Relative,
+                    (section(".rodata").addr + 2) as i64
+                ),
+            ]
+        );
+
+        let data = section(".data");
+        let offset = (pointers - data.addr) as usize + 16;
+        assert_eq!(
+            data.section_raw_data[offset..offset + 8],
+            42u64.to_le_bytes()
+        );
+    }
+
+    #[test]
+    fn test_link_against_shared_object() {
+        let mut linker = Linker::new();
+        linker
+            .add_objects(&[
+                Path::new("src/linker/fixtures/dyn_main.o"),
+                Path::new("src/linker/fixtures/libgreet.so"),
+            ])
+            .unwrap();
+        let executable = linker.link_to_file(vec![]).unwrap();
+
+        let elf = parser::parse_elf(&executable).unwrap().1;
+        assert_eq!(elf.needed(), vec!["libgreet.so".to_string()]);
+        assert!(elf.dynamic.iter().any(|e| e.tag == dynamic::Tag::Debug));
+        let dynamic_symbols: Vec<_> = elf
+            .dynamic_symbols
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(dynamic_symbols, vec!["", "counter", "greet"]);
+
+        // the call goes through the PLT entry after the 32-byte header
+        let section = |name: &str| elf.section_headers.iter().find(|s| s.name == name).unwrap();
+        let text = section(".text");
+        let bl = u32::from_le_bytes(text.section_raw_data[..4].try_into().unwrap());
+        assert_eq!(
+            text.addr + ((bl & 0x3FFFFFF) << 2) as u64,
+            section(".plt").addr + 32
+        );
+
+        let mut relocations: Vec<_> = elf
+            .relocations
+            .iter()
+            .flat_map(|r| r.entries.iter())
+            .map(|r| {
+                (
+                    r.info.r#type,
+                    elf.dynamic_symbols[r.info.symbol_index as usize]
+                        .name
+                        .as_str(),
+                )
+            })
+            .collect();
+        relocations.sort_by_key(|&(r#type, _)| r#type as u32);
+        assert_eq!(
+            relocations,
+            vec![
+                (relocation::RelocationType::Aarch64Abs64, "greet"),
+                (relocation::RelocationType::Aarch64GlobDat, "counter"),
+                (relocation::RelocationType::Aarch64JumpSlot, "greet"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shared_object() {
+        let options = Options {
+            shared: true,
+            soname: Some("libcounter.so.1".to_string()),
+            ..Default::default()
+        };
+        let library = Linker::with_options(options)
+            .link_to_file(vec![fs::read("src/linker/fixtures/libcounter.o").unwrap()])
+            .unwrap();
+
+        let elf = parser::parse_elf(&library).unwrap().1;
+        assert_eq!(elf.header.r#type, crate::elf::header::Type::Dyn);
+        assert_eq!(elf.soname().as_deref(), Some("libcounter.so.1"));
+        assert!(!elf.dynamic.iter().any(|e| e.tag == dynamic::Tag::Debug));
+
+        // hidden symbols stay local, undefined ones are left to the dynamic loader
+        let dynamic_symbols: Vec<_> = elf
+            .dynamic_symbols
+            .iter()
+            .map(|s| (s.name.as_str(), symbol::SymbolIndex::Undefined == s.shndx))
+            .collect();
+        assert_eq!(
+            dynamic_symbols,
+            vec![
+                ("", true),
+                ("counter", false),
+                ("greet", false),
+                ("internal", false),
+                ("puts", true),
+                ("table", false),
+            ]
+        );
+
+        // references to its own symbols are bound within the library
+        let mut relocations: Vec<_> = elf
+            .relocations
+            .iter()
+            .flat_map(|r| r.entries.iter())
+            .map(|r| r.info.r#type)
+            .collect();
+        relocations.sort_by_key(|&r#type| r#type as u32);
+        assert_eq!(
+            relocations,
+            vec![
+                relocation::RelocationType::Aarch64JumpSlot,
+                relocation::RelocationType::Aarch64Relative,
+                relocation::RelocationType::Aarch64Relative,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_shared_object_version_script() {
+        let options = Options {
+            shared: true,
+            version_script: Some("{ global: greet; local: *; };".parse().unwrap()),
+            ..Default::default()
+        };
+        let library = Linker::with_options(options)
+            .link_to_file(vec![fs::read("src/linker/fixtures/libcounter.o").unwrap()])
+            .unwrap();
+
+        let elf = parser::parse_elf(&library).unwrap().1;
+        let dynamic_symbols: Vec<_> = elf
+            .dynamic_symbols
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(dynamic_symbols, vec!["", "greet", "puts"]);
+    }
+
+    #[test]
+    fn test_shared_object_text_relocation() {
+        let options = Options {
+            shared: true,
+            ..Default::default()
+        };
+        let err = Linker::with_options(options)
+            .link_to_file(vec![fs::read("src/linker/fixtures/textrel.o").unwrap()])
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("would need a text relocation"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_got_relocations() {
+        let link = |relax: bool| {
+            let options = Options {
+                relax,
+                ..Default::default()
+            };
+            let executable = Linker::with_options(options)
+                .link_to_file(vec![fs::read("src/linker/fixtures/got_main.o").unwrap()])
+                .unwrap();
+            parser::parse_elf(&executable).unwrap().1
+        };
+        let instructions = |elf: &ELF| -> Vec<u32> {
+            let text = elf
+                .section_headers
+                .iter()
+                .find(|s| s.name == ".text")
+                .unwrap();
+            text.section_raw_data
+                .chunks(4)
+                .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+                .collect()
+        };
+
+        // the slot holds the address of the symbol and both loads read it
+        let elf = link(false);
+        let address = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap().value;
+        let got = elf
+            .section_headers
+            .iter()
+            .find(|s| s.name == ".got")
+            .unwrap();
+        assert_eq!(address("_GLOBAL_OFFSET_TABLE_"), got.addr);
+        assert_eq!(got.section_raw_data, address("value").to_le_bytes());
+        let imm12 = (((got.addr & 0xFFF) >> 3) << 10) as u32;
+        let code = instructions(&elf);
+        assert_eq!(code[1], 0xf9400000 | imm12);
+        assert_eq!(code[3], 0xf9400021 | imm12);
+
+        // with --relax, the first load becomes an add of the address of the symbol
+        let elf = link(true);
+        let address = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap().value;
+        let code = instructions(&elf);
+        assert_eq!(
+            code[1],
+            0x91000000 | (((address("value") & 0xFFF) as u32) << 10)
+        );
+        assert_eq!(code[3] & 0xffc003ff, 0xf9400021);
+    }
+
+    #[test]
+    fn test_thread_local_storage() {
+        let executable = Linker::with_options(Options::default())
+            .link_to_file(vec![fs::read("src/linker/fixtures/tls_main.o").unwrap()])
+            .unwrap();
+        let (_, elf) = parser::parse_elf(&executable).unwrap();
+        let section = |name: &str| elf.section_headers.iter().find(|s| s.name == name).unwrap();
+
+        // .tbss follows .tdata in the TLS template and takes no space in the image
+        let (tdata, tbss) = (section(".tdata"), section(".tbss"));
+        assert_eq!(tdata.addr % 16, 0);
+        assert_eq!(tbss.addr, tdata.addr + 16);
+        assert_eq!(section(".got").addr, tbss.addr);
+
+        // symbols hold their offset in the TLS template
+        let value = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap().value;
+        assert_eq!(
+            (value("counter"), value("flag"), value("buffer")),
+            (0, 4, 16)
+        );
+
+        // offsets from the thread pointer skip the 16-byte thread control block
+        let code: Vec<u32> = section(".text")
+            .section_raw_data
+            .chunks(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        assert_eq!(code[1], 0x91400100);
+        assert_eq!(code[2], 0x91000000 | (16 << 10));
+        // the initial-exec access loads the offset of flag from the GOT
+        assert_eq!(section(".got").section_raw_data, 20u64.to_le_bytes());
+        // the TLS descriptor sequence is relaxed to local-exec
+        let nop = 0xd503201f;
+        assert_eq!(&code[5..9], &[0xd2a00000, 0xf2800000 | (32 << 5), nop, nop]);
+    }
+
+    #[test]
+    fn test_ifunc() {
+        let link = |options: Options| {
+            Linker::with_options(options)
+                .link_to_file(vec![fs::read("src/linker/fixtures/ifunc_main.o").unwrap()])
+        };
+        let executable = link(Options::default()).unwrap();
+        let (_, elf) = parser::parse_elf(&executable).unwrap();
+        let section = |name: &str| elf.section_headers.iter().find(|s| s.name == name).unwrap();
+        let symbol = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap();
+
+        // memcpy stands for its .iplt entry, which jumps through the .igot.plt slot
+        let iplt = section(".iplt");
+        let memcpy = symbol("memcpy");
+        assert_eq!(memcpy.value, iplt.addr);
+        assert_eq!(memcpy.info.r#type, symbol::Type::Func);
+        assert_eq!(section(".data").section_raw_data, iplt.addr.to_le_bytes());
+
+        // the slot holds the resolver until the startup code applies .rela.iplt
+        let igot_plt = section(".igot.plt");
+        let resolver = section(".text").addr + 0x20;
+        assert_eq!(igot_plt.section_raw_data, resolver.to_le_bytes());
+        let rela_iplt = section(".rela.iplt");
+        assert_eq!(elf.relocations.len(), 1);
+        let relocations = &elf.relocations[0].entries;
+        assert_eq!(relocations[0].offset, igot_plt.addr);
+        assert_eq!(
+            relocations[0].info.r#type,
+            relocation::RelocationType::Aarch64IRelative
+        );
+        assert_eq!(relocations[0].addend, resolver as i64);
+        assert_eq!(symbol("__rela_iplt_start").value, rela_iplt.addr);
+        assert_eq!(symbol("__rela_iplt_end").value, rela_iplt.addr + 24);
+
+        let err = link(Options {
+            pie: true,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("IFUNC symbol 'memcpy' is only supported in static executables")
+        );
+    }
+
+    #[test]
+    fn test_init_arrays() {
+        let executable = Linker::with_options(Options::default())
+            .link_to_file(vec![fs::read("src/linker/fixtures/init_main.o").unwrap()])
+            .unwrap();
+        let (_, elf) = parser::parse_elf(&executable).unwrap();
+        let section = |name: &str| elf.section_headers.iter().find(|s| s.name == name).unwrap();
+        let address = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap().value;
+        let pointers = |name: &str| -> Vec<u64> {
+            section(name)
+                .section_raw_data
+                .chunks(8)
+                .map(|word| u64::from_le_bytes(word.try_into().unwrap()) - address("ctor"))
+                .collect()
+        };
+
+        // .ctors is merged into .init_array, and constructors run by priority
+        let init_array = section(".init_array");
+        assert_eq!(init_array.r#type, section::SectionType::InitArray);
+        assert_eq!(pointers(".init_array"), vec![100, 200, 300, 0]);
+        assert_eq!(
+            section(".fini_array").r#type,
+            section::SectionType::FiniArray
+        );
+        assert_eq!(pointers(".fini_array"), vec![1, 2]);
+
+        assert_eq!(address("__init_array_start"), init_array.addr);
+        assert_eq!(address("__init_array_end"), init_array.addr + 32);
+        assert_eq!(address("__fini_array_start"), section(".fini_array").addr);
+        // without .preinit_array, the array is empty
+        assert_eq!(address("__preinit_array_start"), 0);
+    }
+
+    #[test]
+    fn test_eh_frame() {
+        let options = Options {
+            gc_sections: true,
+            ..Default::default()
+        };
+        let executable = Linker::with_options(options)
+            .link_to_file(vec![
+                fs::read("src/linker/fixtures/eh_frame_a.o").unwrap(),
+                fs::read("src/linker/fixtures/eh_frame_b.o").unwrap(),
+            ])
+            .unwrap();
+        let (_, elf) = parser::parse_elf(&executable).unwrap();
+        let section = |name: &str| elf.section_headers.iter().find(|s| s.name == name).unwrap();
+        let address = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap().value;
+        let word = |data: &[u8], offset: usize| {
+            i32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+        };
+
+        // the identical CIEs are merged, and the FDE of the discarded function dropped
+        let eh_frame = section(".eh_frame");
+        let records =
+            eh_frame::split_records(&eh_frame.section_raw_data, elf.header.ident.encoding())
+                .unwrap();
+        assert_eq!(records.iter().filter(|r| r.cie.is_none()).count(), 1);
+        assert!(records.iter().skip(1).all(|r| r.cie == Some(0)));
+        assert_eq!(records.len(), 3);
+        // followed by the zero terminator
+        let last = records.last().unwrap();
+        assert_eq!(
+            &eh_frame.section_raw_data[(last.offset + last.size) as usize..],
+            &[0, 0, 0, 0]
+        );
+
+        let hdr = section(".eh_frame_hdr");
+        let data = &hdr.section_raw_data;
+        assert_eq!(&data[0..4], &[1, 0x1b, 0x03, 0x3b]);
+        assert_eq!(
+            (hdr.addr + 4).wrapping_add_signed(word(data, 4) as i64),
+            eh_frame.addr
+        );
+        assert_eq!(word(data, 8), 2);
+        // the table is sorted by the start address of the functions
+        let locations: Vec<u64> = (0..2)
+            .map(|i| hdr.addr.wrapping_add_signed(word(data, 12 + i * 8) as i64))
+            .collect();
+        assert_eq!(locations, vec![address("_start"), address("helper")]);
+
+        // PT_GNU_EH_FRAME, read from the program header table
+        let phnum = elf.header.phnum as usize;
+        let program_header = executable[64..64 + phnum * 56]
+            .chunks(56)
+            .find(|ph| u32::from_le_bytes(ph[0..4].try_into().unwrap()) == 0x6474e550)
+            .unwrap();
+        let field = |offset: usize| {
+            u64::from_le_bytes(program_header[offset..offset + 8].try_into().unwrap())
+        };
+        assert_eq!(field(16), hdr.addr);
+        assert_eq!(field(40), 12 + 2 * 8);
+    }
+
+    #[test]
+    fn test_branch_thunks() {
+        let main_o = Path::new("src/linker/fixtures/thunk_main.o");
+        let options = Options {
+            defsym: vec!["far=0x10000000".parse().unwrap()],
+            map: Some("thunks.map".into()),
+            ..Default::default()
+        };
+        let mut linker = Linker::with_options(options);
+        linker.add_objects(&[main_o]).unwrap();
+
+        let mut resolved_symbols = linker.resolve_symbols().unwrap();
+        let (output_sections, _, report) = linker.layout_sections(&mut resolved_symbols).unwrap();
+        let text = output_sections.iter().find(|s| s.name == ".text").unwrap();
+        let word = |addr: u64| {
+            let offset = (addr - text.addr) as usize;
+            u32::from_le_bytes(text.data[offset..offset + 4].try_into().unwrap())
+        };
+        let branch_target = |addr: u64| {
+            let imm26 = (word(addr) & 0x3FFFFFF) << 6;
+            addr.wrapping_add_signed((imm26 as i32 >> 4) as i64)
+        };
+
+        // both branches of _start share the thunk placed after it, which moves helper
+        let start = resolved_symbols["_start"].value;
+        let helper = resolved_symbols["helper"].value;
+        let thunk = start + 16;
+        assert_eq!(branch_target(start), thunk);
+        assert_eq!(branch_target(start + 4), thunk);
+        assert_eq!(helper, thunk + 12);
+        assert_eq!(branch_target(start + 8), helper);
+        assert_eq!(branch_target(helper), helper + 8);
+        // adrp x16, far; add x16, x16, :lo12:far; br x16
+        let pages = (0x10000000 - (thunk & !0xFFF)) >> 12;
+        let immlo = (pages & 0x3) << 29;
+        let immhi = ((pages >> 2) & 0x7FFFF) << 5;
+        assert_eq!(word(thunk), 0x90000010 | (immlo | immhi) as u32);
+        assert_eq!(word(thunk + 4), 0x91000210);
+        assert_eq!(word(thunk + 8), 0xd61f0200);
+
+        // layout only renders the map, writing it is left to link_to_file
+        let contents = report.map.unwrap();
+        assert!(!Path::new("thunks.map").exists());
+        assert!(contents.contains(&format!(
+            "{:>16x}        c     4         <internal>:(thunks for src/linker/fixtures/thunk_main.o:(.text._start))",
+            thunk
+        )));
+        assert_eq!(contents.matches("__AArch64ADRPThunk_far").count(), 2);
+    }
+
+    #[test]
+    fn test_conditional_branches() {
+        let link = |far: &str| {
+            let options = Options {
+                defsym: vec![format!("far={}", far).parse().unwrap()],
+                ..Default::default()
+            };
+            Linker::with_options(options)
+                .link_to_file(vec![fs::read("src/linker/fixtures/condbr_main.o").unwrap()])
+        };
+
+        let executable = link("_start+0x7ff4").unwrap();
+        let (_, elf) = parser::parse_elf(&executable).unwrap();
+        let section = |name: &str| elf.section_headers.iter().find(|s| s.name == name).unwrap();
+        let text = section(".text");
+        let start = elf
+            .symbols
+            .iter()
+            .find(|s| s.name == "_start")
+            .unwrap()
+            .value;
+        let done = elf.symbols.iter().find(|s| s.name == "done").unwrap().value;
+        let instruction = |addr: u64| {
+            let offset = (addr - text.addr) as usize;
+            u32::from_le_bytes(
+                text.section_raw_data[offset..offset + 4]
+                    .try_into()
+                    .unwrap(),
+            )
+        };
+        // the word offset of the target, in imm19 or imm14 starting at bit 5
+        let imm = |addr: u64, bits: u32| (instruction(addr) >> 5) & ((1 << bits) - 1);
+
+        assert_eq!(done, start + 0x1c);
+        assert_eq!(imm(start + 4, 19), (done - (start + 4)) as u32 / 4);
+        assert_eq!(imm(start + 8, 19), (done - (start + 8)) as u32 / 4);
+        assert_eq!(imm(start + 12, 14), (done - (start + 12)) as u32 / 4);
+        assert_eq!(
+            imm(start + 16, 19) as u64 * 4,
+            section(".rodata").addr - (start + 16)
+        );
+        // tbz at _start+0x14 reaches 0x7fe0 bytes forward
+        assert_eq!(imm(start + 20, 14), 0x7fe0 / 4);
+
+        let err = link("_start+0x8014").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Relocation error: Relocation overflow: 0x8000 is out of range [-0x8000, 0x7fff] by 0x1 (symbol: far) (object: input_0) (type: R_AARCH64_TSTBR14)"
+        );
+    }
+
+    #[test]
+    fn test_movw_relocations() {
+        let link = |value: &str, pie: bool| {
+            let options = Options {
+                defsym: ["wide=0x123456789abc", value, "negative=0-0x10"]
+                    .iter()
+                    .map(|d| d.parse().unwrap())
+                    .collect(),
+                pie,
+                ..Default::default()
+            };
+            Linker::with_options(options)
+                .link_to_file(vec![fs::read("src/linker/fixtures/movw_main.o").unwrap()])
+        };
+
+        let executable = link("value=0x12345", false).unwrap();
+        let (_, elf) = parser::parse_elf(&executable).unwrap();
+        let text = elf
+            .section_headers
+            .iter()
+            .find(|s| s.name == ".text")
+            .unwrap();
+        let code: Vec<u32> = text
+            .section_raw_data
+            .chunks(4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()))
+            .collect();
+        let imm16 = |instruction: u32| (instruction >> 5) & 0xFFFF;
+
+        // movz/movk of each 16-bit group of the address
+        let groups: Vec<u32> = code[0..4].iter().map(|&i| imm16(i)).collect();
+        assert_eq!(groups, vec![0, 0x1234, 0x5678, 0x9abc]);
+        assert_eq!((imm16(code[4]), imm16(code[5])), (0x1, 0x2345));
+        // negative values turn movz into movn of the inverted bits
+        assert_eq!(code[6], 0x92800000 | (0xf << 5) | 2);
+        let prel = 0x12345 - (text.addr as i64 + 0x1c);
+        assert_eq!(
+            code[7],
+            0x92a00000 | ((!(prel >> 16) as u32 & 0xFFFF) << 5) | 3
+        );
+        assert_eq!(imm16(code[8]), ((prel - 4) & 0xFFFF) as u32);
+        assert_eq!(code[9], 0x92800000 | (0x23 << 5) | 4);
+
+        let err = link("value=0x123456789", false).unwrap_err();
+        assert!(err.to_string().contains(
+            "0x123456789 is out of range [0x0, 0xffffffff] by 0x2345678a (symbol: value)"
+        ));
+        // absolute addresses cannot be built by position-independent code
+        let err = link("value=_start", true).unwrap_err();
+        assert!(err.to_string().contains("recompile with -fPIC"));
+    }
+
+    #[test]
+    fn test_x86_64_executable() {
+        let link = |relax: bool| {
+            let options = Options {
+                relax,
+                ..Default::default()
+            };
+            Linker::with_options(options)
+                .link_to_file(vec![fs::read("src/linker/fixtures/x86_64_main.o").unwrap()])
+                .unwrap()
+        };
+
+        for relax in [false, true] {
+            let executable = link(relax);
+            let (_, elf) = parser::parse_elf(&executable).unwrap();
+            assert_eq!(elf.header.machine, header::Machine::X86_64);
+            let section = |name: &str| elf.section_headers.iter().find(|s| s.name == name);
+            let text = section(".text").unwrap();
+            // segments start on 4 KiB pages, rather than 64 KiB ones as on AArch64
+            let data = section(".data").unwrap();
+            assert_eq!(data.addr % 0x1000, data.offset % 0x1000);
+            assert!(data.addr < BASE_ADDR + 0x10000);
+            // relaxed GOT loads become `addr32 call`, `lea` and `jmp; nop`
+            let opcodes = |offset: usize| &text.section_raw_data[offset..offset + 3];
+            if relax {
+                assert!(section(".got").is_none());
+                assert_eq!(opcodes(0), &[0x67, 0xe8, 0x45]);
+                assert_eq!(opcodes(6), &[0x48, 0x8d, 0x0d]);
+                assert_eq!(text.section_raw_data[0x4b], 0xe9);
+                assert_eq!(text.section_raw_data[0x50], 0x90);
+            } else {
+                assert_eq!(section(".got").unwrap().size, 24);
+                assert_eq!(opcodes(6), &[0x48, 0x8b, 0x0d]);
+            }
+
+            #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+            {
+                use std::os::unix::fs::PermissionsExt;
+
+                let dir = tempfile::tempdir().unwrap();
+                let path = dir.path().join("x86_64_main");
+                fs::write(&path, &executable).unwrap();
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+                let status = std::process::Command::new(&path).status().unwrap();
+                assert_eq!(status.code(), Some(42));
+            }
+        }
+    }
+
+    #[test]
+    fn test_riscv_relaxation() {
+        let link = |relax: bool| {
+            let options = Options {
+                relax,
+                defsym: vec!["far=0x10000000".parse().unwrap()],
+                ..Default::default()
+            };
+            Linker::with_options(options)
+                .link_to_file(vec![fs::read("src/linker/fixtures/riscv_main.o").unwrap()])
+                .unwrap()
+        };
+
+        for relax in [false, true] {
+            let executable = link(relax);
+            let (_, elf) = parser::parse_elf(&executable).unwrap();
+            assert_eq!(elf.header.machine, header::Machine::RiscV);
+            let section = |name: &str| elf.section_headers.iter().find(|s| s.name == name);
+            let symbol = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap().value;
+            let text = section(".text").unwrap();
+            let data = section(".data").unwrap();
+            let word = |offset: u64| {
+                let pos = offset as usize;
+                u32::from_le_bytes(text.section_raw_data[pos..pos + 4].try_into().unwrap())
+            };
+            let imm_i = |offset: u64| (word(offset) as i32 >> 20) as i64;
+            let imm_u = |offset: u64| (word(offset) & 0xFFFFF000) as i32 as i64;
+            let (start, done, value) = (symbol("_start"), symbol("done"), symbol("value"));
+            assert_eq!(start, text.addr);
+            // `done` keeps the alignment of `.p2align 3`
+            assert_eq!(done % 8, 0);
+
+            // `call helper` becomes `jal ra, helper` when relaxed, `call far` cannot
+            let mut pc = start;
+            if relax {
+                assert_eq!(word(0) & 0xFFF, 0x0EF);
+                pc += 4;
+            } else {
+                assert_eq!(word(0) & 0x7F, 0x17);
+                assert_eq!(start + (imm_u(0) + imm_i(4)) as u64, symbol("helper"));
+                pc += 8;
+            }
+            let at = |addr: u64| addr - text.addr;
+            assert_eq!(
+                pc as i64 + imm_u(at(pc)) + imm_i(at(pc) + 4),
+                0x10000000,
+                "auipc + jalr to far"
+            );
+            pc += 8;
+            // lui + lw, and auipc + addi to `value`
+            assert_eq!(imm_u(at(pc)) + imm_i(at(pc) + 4), value as i64);
+            pc += 12;
+            assert_eq!(pc as i64 + imm_u(at(pc)) + imm_i(at(pc) + 4), value as i64);
+            pc += 8;
+            // beq and j to `done`
+            let branch = word(at(pc));
+            let offset = ((branch >> 31) << 12)
+                | (((branch >> 7) & 0x1) << 11)
+                | (((branch >> 25) & 0x3F) << 5)
+                | (((branch >> 8) & 0xF) << 1);
+            assert_eq!(pc + offset as u64, done);
+            let jump = word(at(pc + 4));
+            let offset = ((jump >> 31) << 20)
+                | (((jump >> 12) & 0xFF) << 12)
+                | (((jump >> 20) & 0x1) << 11)
+                | (((jump >> 21) & 0x3FF) << 1);
+            assert_eq!(pc + 4 + offset as u64, done);
+
+            // `done - _start` follows the deleted bytes
+            let diff = u32::from_le_bytes(data.section_raw_data[4..8].try_into().unwrap());
+            assert_eq!(diff as u64, done - start);
+            assert_eq!(done - start, if relax { 0x28 } else { 0x30 });
+            let size = elf
+                .symbols
+                .iter()
+                .find(|s| s.name == "_start")
+                .unwrap()
+                .size;
+            assert_eq!(size, done + 8 - start);
+        }
+    }
+
+    #[test]
+    fn test_riscv_flags() {
+        let header = |flags: u32| {
+            let data = fs::read("src/linker/fixtures/riscv_main.o").unwrap();
+            let mut header = parser::parse_elf(&data).unwrap().1.header;
+            header.flags = flags;
+            header
+        };
+        let mut linker = Linker::new();
+        // double-float ABI, then RVC
+        linker.check_machine("a.o", &header(0x4)).unwrap();
+        linker.check_machine("b.o", &header(0x5)).unwrap();
+        assert_eq!(linker.flags, 0x5);
+
+        for flags in [0x0, 0x2, 0xc] {
+            let err = linker.check_machine("c.o", &header(flags)).unwrap_err();
+            assert!(
+                err.to_string()
+                    .contains("but the other inputs use the double-float ABI"),
+                "unexpected error: {}",
+                err
+            );
+        }
+        assert_eq!(linker.flags, 0x5);
+    }
+
+    #[test]
+    fn test_riscv_pcrel_lo_label_in_other_section() {
+        let mut elf = parser::parse_elf(&fs::read("src/linker/fixtures/riscv_main.o").unwrap())
+            .unwrap()
+            .1;
+        let data_idx = elf
+            .section_headers
+            .iter()
+            .position(|s| s.name == ".data")
+            .unwrap();
+        // a label with the offset of the `auipc`, but in `.data`
+        let label = elf
+            .symbols
+            .iter_mut()
+            .find(|s| s.name == ".Lpcrel")
+            .unwrap();
+        label.shndx = data_idx as u16;
+
+        let options = Options {
+            defsym: vec!["far=0x10000000".parse().unwrap()],
+            ..Default::default()
+        };
+        let mut linker = Linker::with_options(options);
+        linker.check_machine("riscv_main.o", &elf.header).unwrap();
+        linker.add_object("riscv_main.o".to_string(), elf);
+        let err = linker.link_to_file(vec![]).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("does not point at a R_RISCV_PCREL_HI20 relocation"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_elf32_output() {
+        let executable = Linker::new()
+            .link_to_file(vec![
+                fs::read("src/linker/fixtures/riscv32_main.o").unwrap(),
+            ])
+            .unwrap();
+        let (_, elf) = parser::parse_elf(&executable).unwrap();
+        assert_eq!(elf.header.ident.class, header::Class::Bit32);
+        assert_eq!(elf.header.machine, header::Machine::RiscV);
+        assert_eq!(
+            (
+                elf.header.ehsize,
+                elf.header.phentsize,
+                elf.header.shentsize
+            ),
+            (52, 32, 40)
+        );
+
+        let symbol = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(elf.header.entry, symbol("_start").value);
+        assert_eq!(symbol("helper").size, 4);
+        let symtab = elf.section_headers.iter().find(|s| s.name == ".symtab");
+        assert_eq!(symtab.unwrap().entsize, 16);
+
+        // `.word value` holds the 4-byte address of `value`
+        let data = elf.section_headers.iter().find(|s| s.name == ".data");
+        let pointer = &data.unwrap().section_raw_data[4..8];
+        assert_eq!(
+            u32::from_le_bytes(pointer.try_into().unwrap()) as u64,
+            symbol("value").value
+        );
+
+        let err = Linker::with_options(Options {
+            relocatable: true,
+            ..Default::default()
+        })
+        .link_to_file(vec![
+            fs::read("src/linker/fixtures/riscv32_main.o").unwrap(),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Linker error: -r is not supported for 32-bit RISC-V"
+        );
+    }
+
+    #[test]
+    fn test_big_endian_output() {
+        let executable = Linker::new()
+            .link_to_file(vec![
+                fs::read("src/linker/fixtures/aarch64_be_main.o").unwrap(),
+            ])
+            .unwrap();
+        let (_, elf) = parser::parse_elf(&executable).unwrap();
+        assert_eq!(elf.header.ident.data, header::Data::Msb);
+        assert_eq!(elf.header.machine, header::Machine::AArch64);
+
+        let symbol = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(elf.header.entry, symbol("_start").value);
+
+        // data follows the byte order of the output
+        let data = elf.section_headers.iter().find(|s| s.name == ".data");
+        let data = &data.unwrap().section_raw_data;
+        assert_eq!(data[0..4], 0x11223344u32.to_be_bytes());
+        assert_eq!(
+            u64::from_be_bytes(data[4..12].try_into().unwrap()),
+            symbol("value").value
+        );
+        assert_eq!(i32::from_be_bytes(data[12..16].try_into().unwrap()), -12);
+
+        // while instructions stay little-endian
+        let text = elf.section_headers.iter().find(|s| s.name == ".text");
+        let bl = u32::from_le_bytes(text.unwrap().section_raw_data[0..4].try_into().unwrap());
+        assert_eq!(bl >> 26, 0b100101);
+        let offset = ((bl & 0x03ff_ffff) << 6) as i32 >> 4;
+        assert_eq!(
+            symbol("_start").value as i64 + offset as i64,
+            symbol("helper").value as i64
+        );
+
+        let mut linker = Linker::new();
+        let err = linker
+            .add_objects(&[
+                Path::new("src/linker/fixtures/aarch64_be_main.o"),
+                Path::new("src/linker/fixtures/gc_main.o"),
+            ])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Linker error: src/linker/fixtures/gc_main.o is 64-bit little-endian, but the other inputs are 64-bit big-endian"
+        );
+    }
+
+    #[test]
+    fn test_rel_sections() {
+        let executable = Linker::new()
+            .link_to_file(vec![fs::read("src/linker/fixtures/rel_main.o").unwrap()])
+            .unwrap();
+        let (_, elf) = parser::parse_elf(&executable).unwrap();
+        let symbol = |name: &str| elf.symbols.iter().find(|s| s.name == name).unwrap().value;
+
+        // the addends stored in the relocated bytes are kept: `bl helper + 4`
+        let text = elf.section_headers.iter().find(|s| s.name == ".text");
+        let text = &text.unwrap().section_raw_data;
+        let bl = u32::from_le_bytes(text[0..4].try_into().unwrap());
+        assert_eq!(
+            symbol("_start") + (bl & 0x03ff_ffff) as u64 * 4,
+            symbol("helper") + 4
+        );
+        // `add x0, x0, :lo12:value + 4`
+        let add = u32::from_le_bytes(text[8..12].try_into().unwrap());
+        assert_eq!(
+            add,
+            0x91000000 | (((symbol("value") as u32 + 4) & 0xfff) << 10)
+        );
+        // `.xword value + 4`
+        let data = elf.section_headers.iter().find(|s| s.name == ".data");
+        let pointer = &data.unwrap().section_raw_data[8..16];
+        assert_eq!(
+            u64::from_le_bytes(pointer.try_into().unwrap()),
+            symbol("value") + 4
+        );
+    }
+
+    #[test]
+    fn test_mixed_machines() {
+        let mut linker = Linker::new();
+        let err = linker
+            .add_objects(&[
+                Path::new("src/linker/fixtures/x86_64_main.o"),
+                Path::new("src/linker/fixtures/gc_main.o"),
+            ])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Linker error: src/linker/fixtures/gc_main.o is for AArch64, but the other inputs are for x86-64"
+        );
+
+        let options = Options {
+            pie: true,
+            ..Default::default()
+        };
+        let err = Linker::with_options(options)
+            .link_to_file(vec![fs::read("src/linker/fixtures/x86_64_main.o").unwrap()])
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Linker error: -pie is not supported for x86-64"
+        );
+    }
 }
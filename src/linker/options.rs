@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::error::{LinkerError, Result};
+
+use super::defsym::SymbolAssignment;
+use super::version_script::VersionScript;
+
+/// Options that control how the linker resolves symbols and produces its output.
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Symbols given with `--wrap=symbol`.
+    ///
+    /// Undefined references to `symbol` are bound to `__wrap_symbol`, and undefined
+    /// references to `__real_symbol` are bound to `symbol`.
+    pub wrap: HashSet<String>,
+    /// Symbol assignments given with `--defsym=symbol=expression`.
+    pub defsym: Vec<SymbolAssignment>,
+    /// Symbols given with `-u`/`--undefined`.
+    ///
+    /// They are treated as undefined references, so archive members defining them
+    /// are pulled into the link.
+    pub undefined: Vec<String>,
+    /// Symbols given with `--require-defined`.
+    ///
+    /// Same as [`Options::undefined`], but the link fails if they remain undefined.
+    pub require_defined: Vec<String>,
+    /// Entry point symbol given with `-e`/`--entry`. Defaults to `_start`.
+    pub entry: Option<String>,
+    /// Discard input sections that are not reachable from the entry point (`--gc-sections`).
+    pub gc_sections: bool,
+    /// Print the sections discarded by `--gc-sections` (`--print-gc-sections`).
+    /// The linker lists them in its report, and the `yui` binary prints them.
+    pub print_gc_sections: bool,
+    /// Identical code folding mode given with `--icf`.
+    pub icf: Icf,
+    /// Print the sections folded by `--icf` (`--print-icf-sections`).
+    /// The linker lists them in its report, and the `yui` binary prints them.
+    pub print_icf_sections: bool,
+    /// Optimization level given with `-O`. Level 2 and above also merges strings
+    /// that are a suffix of another string.
+    pub optimize: u8,
+    /// Produce a relocatable object instead of an executable (`-r`).
+    pub relocatable: bool,
+    /// Produce a position-independent executable (`-pie`). It is loaded at an
+    /// arbitrary address and relocates itself using `.rela.dyn`.
+    pub pie: bool,
+    /// Do not request a dynamic linker (`--no-dynamic-linker`), as for static PIE.
+    pub no_dynamic_linker: bool,
+    /// Path of the dynamic loader given with `--dynamic-linker`/`-I`, recorded in
+    /// `.interp`. Defaults to the AArch64 glibc loader.
+    pub dynamic_linker: Option<String>,
+    /// Produce a shared object instead of an executable (`-shared`).
+    ///
+    /// References from the shared object to its own exported symbols are bound at
+    /// link time rather than through the GOT or PLT, as if `-Bsymbolic` was given:
+    /// a definition in the executable or in another library does not interpose them.
+    pub shared: bool,
+    /// Name recorded in `DT_SONAME` of a shared object (`-soname`).
+    pub soname: Option<String>,
+    /// Symbols to export from a shared object (`--version-script`). Without it, all
+    /// global symbols with default visibility are exported.
+    pub version_script: Option<VersionScript>,
+    /// Relax GOT loads of symbols with a fixed address into direct address
+    /// computations (`--relax`).
+    pub relax: bool,
+    /// Write a map of the output sections, the input sections and symbols placed in
+    /// them, and the thunks inserted for long branches to this file (`-Map`).
+    pub map: Option<PathBuf>,
+}
+
+/// Identical code folding mode (`--icf=none|safe|all`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Icf {
+    /// Do not fold sections.
+    #[default]
+    None,
+    /// Fold sections whose address is not taken.
+    Safe,
+    /// Fold all identical sections.
+    All,
+}
+
+impl FromStr for Icf {
+    type Err = LinkerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(Icf::None),
+            "safe" => Ok(Icf::Safe),
+            "all" => Ok(Icf::All),
+            _ => Err(LinkerError::invalid_argument(
+                format!("--icf={s}"),
+                "expected one of none, safe or all",
+            )),
+        }
+    }
+}
+
+impl Options {
+    /// Returns the name of the entry point symbol.
+    pub fn entry_symbol(&self) -> &str {
+        self.entry.as_deref().unwrap_or("_start")
+    }
+
+    /// Returns true if the output can be loaded at any address: a position-independent
+    /// executable or a shared object.
+    pub fn is_position_independent(&self) -> bool {
+        self.pie || self.shared
+    }
+}
@@ -1,5 +1,6 @@
 use std::borrow::Cow;
 
+use crate::elf::relocation::RelocationType;
 use crate::elf::{section, symbol};
 
 #[derive(Debug, Clone)]
@@ -12,6 +13,99 @@ pub struct Section<'a> {
     pub size: u64,
     pub data: Cow<'a, [u8]>,
     pub align: u64,
+    /// Index of a related section (`sh_link`), e.g. the string table of a symbol table.
+    pub link: u32,
+    /// Extra information (`sh_info`), e.g. the section a relocation section applies to.
+    pub info: u32,
+    /// Size of each entry (`sh_entsize`) for sections holding a table.
+    pub entsize: u64,
+    /// Input sections merged into this section, in placement order.
+    pub inputs: Vec<InputSection>,
+}
+
+/// An input section placed in an output section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputSection {
+    /// Index of the object file the section comes from.
+    pub object_index: usize,
+    /// Index of the section in the object's section header table.
+    pub section_index: u16,
+    /// Offset of the input section from the start of the output section.
+    pub offset: u64,
+    /// True if the section was folded into an identical section by `--icf`, or replaced
+    /// by the kept copy of its COMDAT group.
+    ///
+    /// A folded section shares the bytes of the section that replaces it, so its
+    /// relocations are not applied.
+    pub folded: bool,
+    /// Location of the pieces of a mergeable section (`SHF_MERGE`) or of the records
+    /// of an `.eh_frame` section, sorted by input offset. Empty for other sections,
+    /// which are copied as a whole.
+    pub pieces: Vec<MergePiece>,
+}
+
+/// A string or constant of a mergeable input section, or a record of an `.eh_frame`
+/// section, placed in the merged contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MergePiece {
+    /// Offset of the piece in the input section.
+    pub input_offset: u64,
+    /// Size of the piece in the input section.
+    pub size: u64,
+    /// Offset of the piece from the start of the input section's placement.
+    pub output_offset: u64,
+}
+
+impl InputSection {
+    /// Translates an offset in the input section into an offset in the output section.
+    pub fn output_offset(&self, offset: u64) -> u64 {
+        let piece_idx = self.pieces.partition_point(|p| p.input_offset <= offset);
+        match piece_idx.checked_sub(1).map(|idx| self.pieces[idx]) {
+            Some(piece) => self.offset + piece.output_offset + (offset - piece.input_offset),
+            None => self.offset + offset,
+        }
+    }
+
+    /// Translates an offset in the input section into an offset in the output section,
+    /// or returns `None` if the bytes there were dropped, such as the records of
+    /// `.eh_frame` that describe discarded functions or duplicate another record.
+    pub fn placed_offset(&self, offset: u64) -> Option<u64> {
+        if self.pieces.is_empty() {
+            return Some(self.offset + offset);
+        }
+        let piece_idx = self.pieces.partition_point(|p| p.input_offset <= offset);
+        let piece = self.pieces[piece_idx.checked_sub(1)?];
+        (offset < piece.input_offset + piece.size)
+            .then(|| self.offset + piece.output_offset + (offset - piece.input_offset))
+    }
+
+    /// Returns how many of the `size` bytes at `offset` in the input section are kept
+    /// in the output, which is less than `size` if relaxation deleted some of them.
+    pub fn placed_size(&self, offset: u64, size: u64) -> u64 {
+        if self.pieces.is_empty() {
+            return size;
+        }
+        let end = offset + size;
+        self.pieces
+            .iter()
+            .map(|p| {
+                let from = p.input_offset.max(offset);
+                let to = (p.input_offset + p.size).min(end);
+                to.saturating_sub(from)
+            })
+            .sum()
+    }
+}
+
+/// A relocation that is applied at load time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DynamicRelocation {
+    /// Address of the place to relocate.
+    pub offset: u64,
+    pub r#type: RelocationType,
+    /// Index of the symbol in the dynamic symbol table, 0 for relative relocations.
+    pub symbol: u32,
+    pub addend: i64,
 }
 
 #[derive(Debug, Clone)]
@@ -20,6 +114,8 @@ pub struct ResolvedSymbol {
     pub value: u64,
     pub size: u64,
     pub info: symbol::Info,
+    pub visibility: symbol::Visibility,
+    /// Section index in the defining object (not in the output file).
     pub shndx: u16,
     pub object_index: usize,
     pub is_defined: bool,
@@ -37,3 +133,24 @@ impl ResolvedSymbol {
         }
     }
 }
+
+/// What the link did to the input sections, for the caller to report
+/// (`--print-gc-sections` and `--print-icf-sections`), and the resulting layout
+/// (`-Map`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Contents of the map file, if one was requested with `-Map`.
+    pub map: Option<String>,
+    /// Sections removed by `--gc-sections`, in input order.
+    pub removed_sections: Vec<SectionName>,
+    /// Sections folded by `--icf`, as the section that is kept followed by the
+    /// sections folded into it, in input order.
+    pub folded_sections: Vec<(SectionName, Vec<SectionName>)>,
+}
+
+/// An input section, named after the file it comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionName {
+    pub file: String,
+    pub section: String,
+}
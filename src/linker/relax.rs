@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use crate::elf::header::Machine;
+use crate::elf::relocation::{RelocationAddend, RelocationType};
+use crate::error::Result;
+
+use super::Linker;
+use super::gc::InputSectionId;
+use super::output::{MergePiece, ResolvedSymbol, Section};
+use super::section::{InputSectionMap, align};
+
+/// `jal` reaches +/-1 MiB from the instruction.
+const JAL_RANGE: i64 = 1 << 20;
+
+/// Opcode of `jal`.
+const JAL: u32 = 0x6f;
+
+/// Bits of the destination register in I-type and J-type instructions.
+const RD_MASK: u32 = 0x1F << 7;
+
+impl Linker {
+    /// Relaxes the RISC-V calls marked with `R_RISCV_RELAX` whose target is in the
+    /// range of a single `jal`: the `jalr` of the `auipc` + `jalr` pair is deleted,
+    /// and the `auipc` becomes a `jal`, whose offset is set when relocations are
+    /// applied. The nops of
+    /// `R_RISCV_ALIGN` are trimmed to what keeps the alignment they were added for.
+    ///
+    /// Deleted bytes are left out of the pieces of the input section, which
+    /// translate the offsets of its symbols and relocations. The input section only
+    /// shrinks by a multiple of the output section's alignment, and is padded with
+    /// nops otherwise, so the sections that follow keep their alignment. A call
+    /// stays relaxed once it is, and the layout is repeated until this returns false.
+    pub(super) fn relax_calls(
+        &self,
+        output_sections: &mut [Section<'static>],
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+    ) -> Result<bool> {
+        if !self.options.relax || self.machine() != Machine::RiscV {
+            return Ok(false);
+        }
+        let input_sections = InputSectionMap::new(output_sections);
+        let mut relaxed = Vec::new();
+
+        for (obj_idx, obj) in self.objects.iter().enumerate() {
+            for relocation_section in &obj.relocations {
+                let caller = (obj_idx, relocation_section.target_section);
+                let Some((out_idx, input)) = input_sections
+                    .input(caller.0, caller.1)
+                    .filter(|(_, input)| !input.folded)
+                else {
+                    continue;
+                };
+                let entries = &relocation_section.entries;
+                let mut calls = Vec::new();
+                let mut changed = false;
+                for (idx, reloc) in entries.iter().enumerate() {
+                    if !is_call(reloc.info.r#type) || !is_relaxable(entries, idx) {
+                        continue;
+                    }
+                    if input.placed_offset(reloc.offset + 4).is_some() {
+                        let Some(place) = input.placed_offset(reloc.offset) else {
+                            continue;
+                        };
+                        let p = (output_sections[out_idx].addr + place) as i64;
+                        let s = self.relocation_symbol_address(
+                            obj_idx,
+                            reloc,
+                            output_sections,
+                            &input_sections,
+                            resolved_symbols,
+                        )? as i64;
+                        if !(-JAL_RANGE..JAL_RANGE).contains(&(s + reloc.addend - p)) {
+                            continue;
+                        }
+                        changed = true;
+                    }
+                    calls.push(reloc.offset);
+                }
+                if changed {
+                    let aligns: Vec<_> = entries
+                        .iter()
+                        .filter(|r| r.info.r#type == RelocationType::RiscvAlign)
+                        .map(|r| (r.offset, r.addend as u64))
+                        .collect();
+                    relaxed.push((out_idx, caller, calls, aligns));
+                }
+            }
+        }
+
+        for (out_idx, caller, calls, aligns) in &relaxed {
+            self.delete_bytes(&mut output_sections[*out_idx], *caller, calls, aligns);
+        }
+        Ok(!relaxed.is_empty())
+    }
+
+    /// Returns true if the relocation is a call whose `jalr` was deleted by
+    /// [`Linker::relax_calls`].
+    pub(super) fn is_relaxed_call(
+        &self,
+        (obj_idx, section_idx): InputSectionId,
+        reloc: &RelocationAddend,
+        input_sections: &InputSectionMap,
+    ) -> bool {
+        is_call(reloc.info.r#type)
+            && input_sections
+                .input(obj_idx, section_idx)
+                .is_some_and(|(_, input)| input.placed_offset(reloc.offset + 4).is_none())
+    }
+
+    /// Rebuilds the contents of an input section from its original bytes, without the
+    /// `jalr` of the relaxed `calls` and the nops of `aligns` that are not needed, and
+    /// moves the input sections that follow it.
+    fn delete_bytes(
+        &self,
+        section: &mut Section<'static>,
+        (obj_idx, section_idx): InputSectionId,
+        calls: &[u64],
+        aligns: &[(u64, u64)],
+    ) {
+        let header = &self.objects[obj_idx].section_headers[section_idx as usize];
+        let Some(input) = section.inputs.iter().find(|input| {
+            input.object_index == obj_idx && input.section_index == section_idx && !input.folded
+        }) else {
+            return;
+        };
+        let start = input.offset;
+        let old_size = placed_size(header.size, &input.pieces, section.align);
+        let pieces = relaxed_pieces(header.size, start, calls, aligns);
+        let new_size = placed_size(header.size, &pieces, section.align);
+
+        let mut data: Vec<u8> = if pieces.is_empty() {
+            header.section_raw_data.clone()
+        } else {
+            pieces
+                .iter()
+                .flat_map(|piece| {
+                    let from = piece.input_offset as usize;
+                    header.section_raw_data[from..from + piece.size as usize].iter()
+                })
+                .copied()
+                .collect()
+        };
+        // the auipc of a relaxed call becomes a jal to the register the jalr wrote
+        for &call in calls {
+            let call = call as usize;
+            let jalr = u32::from_le_bytes(
+                header.section_raw_data[call + 4..call + 8]
+                    .try_into()
+                    .unwrap(),
+            );
+            let placed = pieces
+                .iter()
+                .find(|piece| {
+                    (piece.input_offset..piece.input_offset + piece.size).contains(&(call as u64))
+                })
+                .map(|piece| (piece.output_offset + call as u64 - piece.input_offset) as usize);
+            if let Some(placed) = placed {
+                data[placed..placed + 4].copy_from_slice(&(JAL | (jalr & RD_MASK)).to_le_bytes());
+            }
+        }
+        let nop = self.target().nop();
+        while (data.len() as u64) < new_size {
+            data.push(nop[data.len() % nop.len()]);
+        }
+        section
+            .data
+            .to_mut()
+            .splice(start as usize..(start + old_size) as usize, data);
+
+        for other in section.inputs.iter_mut() {
+            if other.offset > start {
+                other.offset = other.offset + new_size - old_size;
+            } else if other.offset == start && other.folded {
+                // folded sections share the bytes of the section kept in their place
+                other.pieces = pieces.clone();
+            } else if other.object_index == obj_idx && other.section_index == section_idx {
+                other.pieces = pieces.clone();
+            }
+        }
+        section.size = section.size + new_size - old_size;
+    }
+}
+
+/// Returns true for the relocations of `auipc` + `jalr` calls.
+fn is_call(r#type: RelocationType) -> bool {
+    matches!(
+        r#type,
+        RelocationType::RiscvCall | RelocationType::RiscvCallPlt
+    )
+}
+
+/// Returns true if the relocation at `idx` is followed by an `R_RISCV_RELAX` at the
+/// same offset, which allows the linker to rewrite its instructions.
+fn is_relaxable(entries: &[RelocationAddend], idx: usize) -> bool {
+    entries.get(idx + 1).is_some_and(|next| {
+        next.info.r#type == RelocationType::RiscvRelax && next.offset == entries[idx].offset
+    })
+}
+
+/// Returns the pieces of an input section of `size` bytes placed at `base` in its
+/// output section that remain once the `jalr` after each of the `calls` is deleted,
+/// and each `R_RISCV_ALIGN` (offset and size of its nops) keeps only the nops that
+/// align the code after it. Empty when nothing is deleted.
+fn relaxed_pieces(size: u64, base: u64, calls: &[u64], aligns: &[(u64, u64)]) -> Vec<MergePiece> {
+    let mut deletions: Vec<(u64, Option<u64>)> = calls
+        .iter()
+        .map(|&offset| (offset, None))
+        .chain(aligns.iter().map(|&(offset, nops)| (offset, Some(nops))))
+        .collect();
+    deletions.sort();
+
+    let mut pieces = Vec::new();
+    let (mut input_offset, mut output_offset) = (0, 0);
+    for (offset, nops) in deletions {
+        let (from, len) = match nops {
+            None => (offset + 4, 4),
+            Some(nops) => {
+                let addr = base + output_offset + (offset - input_offset);
+                let keep = align(addr, (nops + 2).next_power_of_two()) - addr;
+                (offset + keep, nops - keep)
+            }
+        };
+        if len == 0 || from < input_offset {
+            continue;
+        }
+        if from > input_offset {
+            pieces.push(MergePiece {
+                input_offset,
+                size: from - input_offset,
+                output_offset,
+            });
+            output_offset += from - input_offset;
+        }
+        input_offset = from + len;
+    }
+    if input_offset == 0 {
+        return vec![];
+    }
+    pieces.push(MergePiece {
+        input_offset,
+        size: size - input_offset,
+        output_offset,
+    });
+    pieces
+}
+
+/// Returns how many bytes an input section of `size` bytes takes in its output
+/// section: deleted bytes are only given back in multiples of the alignment.
+fn placed_size(size: u64, pieces: &[MergePiece], alignment: u64) -> u64 {
+    let kept: u64 = pieces.iter().map(|piece| piece.size).sum();
+    let deleted = if pieces.is_empty() { 0 } else { size - kept };
+    size - deleted / alignment.max(1) * alignment.max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_delete_relaxed_bytes() {
+        // a call at 0 and 6 bytes of nops at 0x10 aligning to 8
+        let pieces = relaxed_pieces(0x20, 0x1000, &[0], &[(0x10, 6)]);
+        assert_eq!(
+            pieces,
+            vec![
+                MergePiece {
+                    input_offset: 0,
+                    size: 4,
+                    output_offset: 0,
+                },
+                // 0xc is aligned to 8 with 4 bytes of nops, the other 2 are deleted
+                MergePiece {
+                    input_offset: 8,
+                    size: 0xc,
+                    output_offset: 4,
+                },
+                MergePiece {
+                    input_offset: 0x16,
+                    size: 0xa,
+                    output_offset: 0x10,
+                },
+            ]
+        );
+        assert_eq!(placed_size(0x20, &pieces, 4), 0x1c);
+        assert_eq!(placed_size(0x20, &pieces, 8), 0x20);
+        assert_eq!(relaxed_pieces(0x20, 0x1000, &[], &[(0x10, 0)]), vec![]);
+    }
+}
@@ -0,0 +1,473 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::elf::header::Encoding;
+use crate::elf::section::{SectionFlag, SectionType};
+use crate::elf::symbol::{self, Binding};
+use crate::error::{LinkerError, ObjectContext, Result};
+
+use super::Linker;
+use super::gc::{InputSectionId, is_regular_section};
+use super::output::{InputSection, ResolvedSymbol, Section};
+use super::section::{
+    InputSectionMap, add_symbol_and_string_tables, align, append_input, write_symbol_entry,
+};
+
+/// Size of an `Elf64_Rela` entry
+const RELA_ENTRY_SIZE: u64 = 24;
+
+/// Size of a section index in an `SHT_GROUP` section
+const GROUP_ENTRY_SIZE: u64 = 4;
+
+/// A symbol written to the symbol table of a relocatable output.
+struct OutputSymbol<'a> {
+    name: &'a str,
+    value: u64,
+    size: u64,
+    info: symbol::Info,
+    visibility: symbol::Visibility,
+    shndx: u16,
+}
+
+/// Symbol table indices of the symbols of a relocatable output.
+struct SymbolIndices<'a> {
+    /// Number of output sections that have a section symbol, starting at index 1.
+    section_count: usize,
+    /// Local symbols, by `(object index, symbol index)` in the input.
+    locals: HashMap<(usize, usize), u32>,
+    /// Global symbols, by name.
+    globals: HashMap<&'a str, u32>,
+}
+
+impl Linker {
+    /// Lays out a relocatable object (`-r`).
+    ///
+    /// Input sections with the same name are merged, except for the members of section
+    /// groups, which keep their own output sections so that the final link can still
+    /// deduplicate the groups. Addresses are left unassigned. The relocations of the
+    /// input sections are rewritten against the merged sections and kept in `.rela.*`
+    /// sections, and both local and global symbols are preserved.
+    pub fn layout_relocatable(
+        &self,
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) -> Result<(Vec<Section<'static>>, HashMap<String, usize>)> {
+        let encoding = self.encoding();
+        // section groups that are kept, and the group of each of their members
+        let mut groups = Vec::new();
+        let mut group_of: HashMap<InputSectionId, InputSectionId> = HashMap::new();
+        for (obj_idx, obj) in self.objects.iter().enumerate() {
+            for group in &obj.groups {
+                if group
+                    .members
+                    .iter()
+                    .any(|&m| self.is_discarded((obj_idx, m)))
+                {
+                    continue;
+                }
+                for &member in &group.members {
+                    group_of.insert((obj_idx, member), (obj_idx, group.section_index));
+                }
+                groups.push((obj_idx, group));
+            }
+        }
+
+        // merge sections with the same name (and group)
+        let mut sections: Vec<Section<'static>> = Vec::new();
+        let mut section_groups: Vec<Option<InputSectionId>> = Vec::new();
+        let mut keys: HashMap<(&str, Option<InputSectionId>), usize> = HashMap::new();
+        for (obj_idx, obj) in self.objects.iter().enumerate() {
+            for (section_idx, header) in obj.section_headers.iter().enumerate() {
+                let id = (obj_idx, section_idx as u16);
+                if !is_copied_section(header.r#type) || self.is_discarded(id) {
+                    continue;
+                }
+
+                let group = group_of.get(&id).copied();
+                let next = sections.len();
+                let out_idx = *keys.entry((header.name.as_str(), group)).or_insert(next);
+                if out_idx == next {
+                    sections.push(Section {
+                        name: Cow::Owned(header.name.clone()),
+                        r#type: header.r#type,
+                        flags: vec![],
+                        addr: 0,
+                        offset: 0,
+                        size: 0,
+                        data: Cow::Owned(vec![]),
+                        align: 1,
+                        link: 0,
+                        info: 0,
+                        entsize: header.entsize,
+                        inputs: vec![],
+                    });
+                    section_groups.push(group);
+                }
+
+                let out = &mut sections[out_idx];
+                for flag in &header.flags {
+                    if !out.flags.contains(flag) {
+                        out.flags.push(*flag);
+                    }
+                }
+                // inputs with different entry sizes can no longer be merged as a whole
+                if out.entsize != header.entsize {
+                    out.entsize = 0;
+                    out.flags
+                        .retain(|f| !matches!(f, SectionFlag::Merge | SectionFlag::Strings));
+                }
+
+                let offset = append_input(
+                    out,
+                    header.r#type,
+                    &header.section_raw_data,
+                    header.size,
+                    header.addralign,
+                    self.target().nop(),
+                );
+                out.inputs.push(InputSection {
+                    object_index: obj_idx,
+                    section_index: section_idx as u16,
+                    offset,
+                    folded: false,
+                    pieces: vec![],
+                });
+            }
+        }
+
+        let input_sections = InputSectionMap::new(&sections);
+        // group sections come first, as they must precede their members
+        let section_index = |out_idx: usize| (1 + groups.len() + out_idx) as u16;
+        let output_shndx = |object_index: usize, shndx: u16| {
+            if !is_regular_section(shndx) {
+                return shndx;
+            }
+            input_sections
+                .get(object_index, shndx)
+                .map(|(out_idx, _)| section_index(out_idx))
+                .unwrap_or(0)
+        };
+
+        // values of global symbols become offsets in their output section
+        for symbol in resolved_symbols.values_mut() {
+            if let Some((_, offset)) = input_sections.get(symbol.object_index, symbol.shndx) {
+                symbol.value += offset;
+            }
+        }
+        self.assign_defsym_values(resolved_symbols)?;
+
+        // symbols: section symbols, local symbols, then global symbols
+        let mut symbols: Vec<OutputSymbol> = (0..sections.len())
+            .map(|out_idx| OutputSymbol {
+                name: "",
+                value: 0,
+                size: 0,
+                info: symbol::Info {
+                    r#type: symbol::Type::Section,
+                    binding: Binding::Local,
+                },
+                visibility: symbol::Visibility::Default,
+                shndx: section_index(out_idx),
+            })
+            .collect();
+        let mut indices = SymbolIndices {
+            section_count: sections.len(),
+            locals: HashMap::new(),
+            globals: HashMap::new(),
+        };
+
+        for (obj_idx, obj) in self.objects.iter().enumerate() {
+            for (symbol_idx, symbol) in obj.symbols.iter().enumerate().skip(1) {
+                if symbol.info.binding != Binding::Local
+                    || symbol.info.r#type == symbol::Type::Section
+                {
+                    continue;
+                }
+                let value = match input_sections.get(obj_idx, symbol.shndx) {
+                    Some((_, offset)) => symbol.value + offset,
+                    // defined in a discarded section
+                    None if is_regular_section(symbol.shndx) => continue,
+                    None => symbol.value,
+                };
+                // includes null symbol
+                indices
+                    .locals
+                    .insert((obj_idx, symbol_idx), symbols.len() as u32 + 1);
+                symbols.push(OutputSymbol {
+                    name: &symbol.name,
+                    value,
+                    size: symbol.size,
+                    info: symbol.info,
+                    visibility: symbol.other,
+                    shndx: output_shndx(obj_idx, symbol.shndx),
+                });
+            }
+        }
+        let local_count = symbols.len();
+
+        let mut globals: Vec<&ResolvedSymbol> = resolved_symbols
+            .values()
+            .filter(|s| s.info.binding != Binding::Local)
+            .collect();
+        globals.sort_by(|a, b| a.name.cmp(&b.name));
+        for global in globals {
+            indices
+                .globals
+                .insert(&global.name, symbols.len() as u32 + 1);
+            symbols.push(OutputSymbol {
+                name: &global.name,
+                value: global.value,
+                size: global.size,
+                info: global.info,
+                visibility: global.visibility,
+                shndx: output_shndx(global.object_index, global.shndx),
+            });
+        }
+
+        // relocations, rewritten against the output sections
+        let mut relocations: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for (obj_idx, obj) in self.objects.iter().enumerate() {
+            for relocation_section in &obj.relocations {
+                let Some((out_idx, offset)) =
+                    input_sections.get(obj_idx, relocation_section.target_section)
+                else {
+                    continue;
+                };
+                let data = relocations.entry(out_idx).or_default();
+
+                for reloc in &relocation_section.entries {
+                    let symbol_index = reloc.info.symbol_index as usize;
+                    let Some((output_index, section_offset)) =
+                        self.output_symbol_index(obj_idx, symbol_index, &input_sections, &indices)
+                    else {
+                        return Err(LinkerError::relocation_error(
+                            "Symbol refers to a discarded section",
+                            obj.symbols.get(symbol_index).map(|s| s.name.clone()),
+                            Some(ObjectContext {
+                                file_name: self.object_file_name(obj_idx),
+                                object_index: obj_idx,
+                            }),
+                            Some(reloc.info.r#type.to_string()),
+                        ));
+                    };
+
+                    let info = ((output_index as u64) << 32) | reloc.info.r#type.value() as u64;
+                    let addend = reloc.addend + section_offset as i64;
+                    data.extend_from_slice(&encoding.u64_bytes(offset + reloc.offset));
+                    data.extend_from_slice(&encoding.u64_bytes(info));
+                    data.extend_from_slice(&encoding.u64_bytes(addend as u64));
+                }
+            }
+        }
+
+        let symtab_index = (1 + groups.len() + sections.len() + relocations.len()) as u32;
+        let relocation_sections: Vec<Section<'static>> = relocations
+            .into_iter()
+            .map(|(out_idx, data)| {
+                let target = &sections[out_idx];
+                let mut flags = vec![SectionFlag::InfoLink];
+                if target.flags.contains(&SectionFlag::Group) {
+                    flags.push(SectionFlag::Group);
+                }
+                Section {
+                    name: Cow::Owned(format!(".rela{}", target.name)),
+                    r#type: SectionType::Rela,
+                    flags,
+                    addr: 0,
+                    offset: 0,
+                    size: data.len() as u64,
+                    data: Cow::Owned(data),
+                    align: 8,
+                    link: symtab_index,
+                    info: section_index(out_idx) as u32,
+                    entsize: RELA_ENTRY_SIZE,
+                    inputs: vec![],
+                }
+            })
+            .collect();
+
+        let mut group_sections = Vec::new();
+        for &(obj_idx, group) in &groups {
+            let id = Some((obj_idx, group.section_index));
+            let members = (0..sections.len())
+                .filter(|&out_idx| section_groups[out_idx] == id)
+                .map(|out_idx| section_index(out_idx) as u32);
+            let relocation_members = relocation_sections
+                .iter()
+                .enumerate()
+                .filter(|(_, rela)| section_groups[rela.info as usize - 1 - groups.len()] == id)
+                .map(|(rela_idx, _)| (1 + groups.len() + sections.len() + rela_idx) as u32);
+
+            let data: Vec<u8> = [group.flags]
+                .into_iter()
+                .chain(members)
+                .chain(relocation_members)
+                .flat_map(|word| encoding.u32_bytes(word))
+                .collect();
+
+            let signature =
+                self.objects[obj_idx].section_headers[group.section_index as usize].info;
+            let (signature_index, _) = self
+                .output_symbol_index(obj_idx, signature as usize, &input_sections, &indices)
+                .unwrap_or_default();
+
+            group_sections.push(Section {
+                name: Cow::Borrowed(".group"),
+                r#type: SectionType::Group,
+                flags: vec![],
+                addr: 0,
+                offset: 0,
+                size: data.len() as u64,
+                data: Cow::Owned(data),
+                align: GROUP_ENTRY_SIZE,
+                link: symtab_index,
+                info: signature_index,
+                entsize: GROUP_ENTRY_SIZE,
+                inputs: vec![],
+            });
+        }
+
+        let mut output_sections = group_sections;
+        output_sections.extend(sections);
+        output_sections.extend(relocation_sections);
+
+        // place sections right after the ELF header
+        let mut offset = encoding.class.header_size();
+        for section in output_sections.iter_mut() {
+            offset = align(offset, section.align);
+            section.offset = offset;
+            if section.r#type != SectionType::NoBits {
+                offset += section.size;
+            }
+        }
+
+        let (symtab_section, strtab_section) =
+            make_symbol_table(encoding, offset, &symbols, local_count);
+        Ok(add_symbol_and_string_tables(
+            output_sections,
+            symtab_section,
+            strtab_section,
+        ))
+    }
+
+    /// Returns the index in the output symbol table of a symbol of an input object.
+    ///
+    /// Section symbols are replaced by the section symbol of the output section. In
+    /// that case, the offset of the input section in the output section is also
+    /// returned, as it must be added to the addend of relocations.
+    fn output_symbol_index(
+        &self,
+        obj_idx: usize,
+        symbol_index: usize,
+        input_sections: &InputSectionMap,
+        indices: &SymbolIndices,
+    ) -> Option<(u32, u64)> {
+        if symbol_index == 0 {
+            return Some((0, 0));
+        }
+
+        let symbol = self.objects[obj_idx].symbols.get(symbol_index)?;
+        if symbol.info.binding != Binding::Local {
+            let name = self.reference_name(symbol);
+            return indices.globals.get(name.as_ref()).map(|&index| (index, 0));
+        }
+        if symbol.info.r#type != symbol::Type::Section {
+            return indices
+                .locals
+                .get(&(obj_idx, symbol_index))
+                .map(|&index| (index, 0));
+        }
+
+        let section = (obj_idx, symbol.shndx);
+        let (section_obj_idx, section_idx) = self.comdat_replacement(section).unwrap_or(section);
+        input_sections
+            .get(section_obj_idx, section_idx)
+            .filter(|&(out_idx, _)| out_idx < indices.section_count)
+            // section symbols come right after the null symbol
+            .map(|(out_idx, offset)| (out_idx as u32 + 1, offset))
+    }
+}
+
+/// Returns true if input sections of this type are copied to a relocatable output.
+///
+/// Symbol tables, string tables, relocations and groups are rebuilt instead.
+fn is_copied_section(r#type: SectionType) -> bool {
+    !matches!(
+        r#type,
+        SectionType::Null
+            | SectionType::SymTab
+            | SectionType::StrTab
+            | SectionType::Rela
+            | SectionType::Rel
+            | SectionType::Group
+            | SectionType::SymTabShndx
+    )
+}
+
+/// Builds the symbol table and its string table, placed from `offset`.
+fn make_symbol_table(
+    encoding: Encoding,
+    offset: u64,
+    symbols: &[OutputSymbol],
+    local_count: usize,
+) -> (Section<'static>, Section<'static>) {
+    // includes null string
+    let mut strtab: Vec<u8> = vec![0];
+    let mut symtab: Vec<u8> = Vec::new();
+
+    // add null symbol
+    write_symbol_entry(&mut symtab, encoding, 0, 0, 0, 0, 0, 0);
+
+    for symbol in symbols {
+        let name = if symbol.name.is_empty() {
+            0
+        } else {
+            let name = strtab.len() as u32;
+            strtab.extend_from_slice(symbol.name.as_bytes());
+            strtab.push(0);
+            name
+        };
+        write_symbol_entry(
+            &mut symtab,
+            encoding,
+            name,
+            symbol.value,
+            symbol.size,
+            symbol.info.into(),
+            symbol.visibility as u8,
+            symbol.shndx,
+        );
+    }
+
+    let strtab_section = Section {
+        name: Cow::Borrowed(".strtab"),
+        r#type: SectionType::StrTab,
+        flags: vec![],
+        addr: 0,
+        offset,
+        size: strtab.len() as u64,
+        data: Cow::Owned(strtab),
+        align: 1,
+        link: 0,
+        info: 0,
+        entsize: 0,
+        inputs: vec![],
+    };
+
+    let symtab_section = Section {
+        name: Cow::Borrowed(".symtab"),
+        r#type: SectionType::SymTab,
+        flags: vec![],
+        addr: 0,
+        offset: align(strtab_section.offset + strtab_section.size, 8),
+        size: symtab.len() as u64,
+        data: Cow::Owned(symtab),
+        align: 8,
+        link: 0,
+        // one past the last local symbol
+        info: local_count as u32 + 1,
+        entsize: encoding.class.symbol_size(),
+        inputs: vec![],
+    };
+
+    (symtab_section, strtab_section)
+}
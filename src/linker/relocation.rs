@@ -1,131 +1,531 @@
+use std::cell::Cell;
 use std::collections::HashMap;
 
-use crate::elf::relocation;
+use crate::elf::relocation::{self, RelocationType};
+use crate::elf::section::SectionFlag;
+use crate::elf::symbol::{self, Binding, SymbolIndex};
 use crate::error::{LinkerError, ObjectContext, Result};
 
 use super::Linker;
-use super::output::{ResolvedSymbol, Section};
+use super::dynamic::{DynamicSymbols, GOT};
+use super::gc::InputSectionId;
+use super::output::{DynamicRelocation, ResolvedSymbol, Section};
+use super::section::InputSectionMap;
+use super::target::RelocationValues;
+use super::thunk::Thunks;
+use super::tls::{TlsSegment, tls_segment};
 
 impl Linker {
-    pub fn apply_relocations(
+    /// Applies the relocations of all placed input sections.
+    ///
+    /// Branches that do not reach their target go through their thunk in `thunks`.
+    ///
+    /// Returns the relocations that have to be applied again at load time, such as
+    /// absolute addresses in position-independent output.
+    pub(super) fn apply_relocations(
         &self,
         output_sections: &mut [Section<'static>],
         resolved_symbols: &HashMap<String, ResolvedSymbol>,
-    ) -> Result<()> {
-        let section_indices: HashMap<String, usize> = output_sections
-            .iter()
-            .enumerate()
-            .map(|(i, sec)| (sec.name.to_string(), i))
-            .collect();
+        thunks: &Thunks,
+    ) -> Result<Vec<DynamicRelocation>> {
+        let dynamic_symbols = self.scan_dynamic_symbols(output_sections, resolved_symbols);
+        let context = RelocationContext {
+            input_sections: InputSectionMap::new(output_sections),
+            resolved_symbols,
+            got: output_sections.iter().position(|s| s.name == GOT.0),
+            tls: tls_segment(output_sections),
+            thunks,
+            filled_got_slots: vec![Cell::new(false); dynamic_symbols.got.len()],
+            dynamic_symbols,
+        };
+        let mut dynamic_relocations = Vec::new();
 
         for (obj_idx, obj) in self.objects.iter().enumerate() {
-            for reloc in &obj.relocations {
-                self.process_relocation(
-                    obj_idx,
-                    reloc,
-                    output_sections,
-                    &section_indices,
-                    resolved_symbols,
-                )?;
+            for relocation_section in &obj.relocations {
+                // relocations for discarded or non-allocated sections are not needed
+                let Some((target_idx, input)) = context
+                    .input_sections
+                    .input(obj_idx, relocation_section.target_section)
+                else {
+                    continue;
+                };
+                // folded sections share the bytes of the section that replaces them
+                if context
+                    .input_sections
+                    .is_folded(obj_idx, relocation_section.target_section)
+                {
+                    continue;
+                }
+
+                for reloc in &relocation_section.entries {
+                    // relocations of dropped `.eh_frame` records are not needed
+                    let Some(place) = input.placed_offset(reloc.offset) else {
+                        continue;
+                    };
+                    let dynamic_relocation = self.process_relocation(
+                        (obj_idx, relocation_section.target_section),
+                        reloc,
+                        (target_idx, place),
+                        output_sections,
+                        &context,
+                    )?;
+                    dynamic_relocations.extend(dynamic_relocation);
+                }
             }
         }
 
-        Ok(())
+        Ok(dynamic_relocations)
     }
 
-    fn process_relocation(
+    /// Returns the address of the symbol referenced by a relocation in `obj_idx`.
+    ///
+    /// Local symbols (including section symbols) are looked up in the object that
+    /// contains the relocation. Other symbols are looked up by name in `resolved_symbols`.
+    pub(super) fn relocation_symbol_address(
         &self,
         obj_idx: usize,
         reloc: &relocation::RelocationAddend,
-        output_sections: &mut [Section<'static>],
-        section_indices: &HashMap<String, usize>,
+        output_sections: &[Section<'static>],
+        input_sections: &InputSectionMap,
         resolved_symbols: &HashMap<String, ResolvedSymbol>,
-    ) -> Result<()> {
-        let object_file_name = self
-            .object_names
+    ) -> Result<u64> {
+        let object_context = || ObjectContext {
+            file_name: self.object_file_name(obj_idx),
+            object_index: obj_idx,
+        };
+
+        let symbol_index = reloc.info.symbol_index as usize;
+        let Some(symbol) = self.objects[obj_idx].symbols.get(symbol_index) else {
+            return Err(LinkerError::relocation_error(
+                format!("Symbol index out of range: {}", symbol_index),
+                None,
+                Some(object_context()),
+                Some(reloc.info.r#type.to_string()),
+            ));
+        };
+
+        if symbol.info.binding == Binding::Local {
+            if SymbolIndex::Abs == symbol.shndx {
+                return Ok(symbol.value);
+            }
+            // a section symbol plus addend may point into a different piece of a
+            // mergeable section than the section symbol itself
+            let offset = if symbol.info.r#type == symbol::Type::Section {
+                symbol.value.wrapping_add_signed(reloc.addend)
+            } else {
+                symbol.value
+            };
+            return input_sections
+                .address(output_sections, obj_idx, symbol.shndx, offset)
+                .map(|addr| addr.wrapping_add(symbol.value).wrapping_sub(offset))
+                .ok_or_else(|| {
+                    let message = match self.objects[obj_idx]
+                        .section_headers
+                        .get(symbol.shndx as usize)
+                    {
+                        Some(header) if self.is_discarded((obj_idx, symbol.shndx)) => format!(
+                            "Symbol is defined in discarded section '{}' of {}",
+                            header.name,
+                            self.object_file_name(obj_idx)
+                        ),
+                        _ => "Symbol refers to a discarded section".to_string(),
+                    };
+                    LinkerError::relocation_error(
+                        message,
+                        Some(symbol.name.clone()),
+                        Some(object_context()),
+                        Some(reloc.info.r#type.to_string()),
+                    )
+                });
+        }
+
+        let symbol_name = self.reference_name(symbol);
+        resolved_symbols
+            .get(symbol_name.as_ref())
+            .map(|s| s.value)
+            .ok_or_else(|| {
+                LinkerError::relocation_error(
+                    "Symbol is not resolved",
+                    Some(symbol_name.to_string()),
+                    Some(object_context()),
+                    Some(reloc.info.r#type.to_string()),
+                )
+            })
+    }
+
+    pub(super) fn object_file_name(&self, obj_idx: usize) -> String {
+        self.object_names
             .get(obj_idx)
             .cloned()
-            .unwrap_or_else(|| format!("object_{}", obj_idx));
-        match reloc.info.r#type {
-            relocation::RelocationType::Aarch64AdrPrelLo21 => {
-                let symbol_index = reloc.info.symbol_index as usize;
-                if symbol_index >= self.objects[obj_idx].symbols.len() {
-                    return Err(LinkerError::relocation_error(
-                        format!("Symbol index out of range: {}", symbol_index),
-                        None,
-                        Some(ObjectContext {
-                            file_name: object_file_name.clone(),
-                            object_index: obj_idx,
-                        }),
-                        Some("R_AARCH64_ADR_PREL_LO21".to_string()),
+            .unwrap_or_else(|| format!("object_{}", obj_idx))
+    }
+
+    fn process_relocation(
+        &self,
+        caller @ (obj_idx, _): InputSectionId,
+        reloc: &relocation::RelocationAddend,
+        (target_idx, place): (usize, u64),
+        output_sections: &mut [Section<'static>],
+        context: &RelocationContext,
+    ) -> Result<Option<DynamicRelocation>> {
+        let r#type = reloc.info.r#type;
+        let target = self.target();
+        // NONE, and markers such as those of RISC-V relaxation, patch nothing
+        if target.relocation_width(r#type) == 0 {
+            return Ok(None);
+        }
+        let resolved_symbols = context.resolved_symbols;
+
+        let symbol_addr = self.relocation_symbol_address(
+            obj_idx,
+            reloc,
+            output_sections,
+            &context.input_sections,
+            resolved_symbols,
+        )?;
+        let shared = self.objects[obj_idx]
+            .symbols
+            .get(reloc.info.symbol_index as usize)
+            .and_then(|symbol| self.shared_symbol(symbol, resolved_symbols));
+
+        let error = |message: String| {
+            let symbol_name = self.objects[obj_idx]
+                .symbols
+                .get(reloc.info.symbol_index as usize)
+                .map(|s| s.name.clone());
+            LinkerError::relocation_error(
+                message,
+                symbol_name,
+                Some(ObjectContext {
+                    file_name: self.object_file_name(obj_idx),
+                    object_index: obj_idx,
+                }),
+                Some(r#type.to_string()),
+            )
+        };
+
+        // global IFUNC symbols already have the address of their `.iplt` entry
+        if self.objects[obj_idx]
+            .symbols
+            .get(reloc.info.symbol_index as usize)
+            .is_some_and(|s| {
+                s.info.binding == Binding::Local && s.info.r#type == symbol::Type::Loos
+            })
+        {
+            return Err(error("Local IFUNC symbols are not supported".to_string()));
+        }
+
+        let target_section = &output_sections[target_idx];
+        let pos = place as usize;
+        // GOT loads of symbols with a fixed address can use the address directly, and
+        // relaxed calls are shorter
+        let relaxed = self.can_relax_got_load(caller, reloc, shared.is_some())
+            || self.is_relaxed_call(caller, reloc, &context.input_sections);
+        let width = if relaxed {
+            target.relaxed_width(r#type)
+        } else {
+            target.relocation_width(r#type)
+        };
+        if pos + width > target_section.data.len() {
+            return Err(error(format!(
+                "Relocation offset out of range: {}",
+                reloc.offset
+            )));
+        }
+
+        // S: symbol address, A: addend, P: address of the place being relocated
+        let s = symbol_addr as i64;
+        let a = reloc.addend;
+        let p = (target_section.addr + place) as i64;
+
+        let types = target.dynamic_relocations();
+        let mut dynamic = self
+            .needs_dynamic_relocation(obj_idx, reloc, resolved_symbols)
+            .then_some(DynamicRelocation {
+                offset: p as u64,
+                r#type: types.relative,
+                symbol: 0,
+                // the loader adds the load address to the link-time address (base 0)
+                addend: s + a,
+            });
+        if dynamic.is_some() && r#type != types.absolute {
+            return Err(error(
+                "Relocation cannot be used against a non-absolute symbol in position-independent output; recompile with -fPIC".to_string(),
+            ));
+        }
+
+        if let Some(shared) = shared {
+            match r#type {
+                // the loader stores the address of the symbol
+                _ if r#type == types.absolute => {
+                    dynamic = Some(DynamicRelocation {
+                        offset: p as u64,
+                        r#type: types.absolute,
+                        symbol: context
+                            .dynamic_symbols
+                            .symbol_index(&shared.name)
+                            .unwrap_or(0),
+                        addend: a,
+                    })
+                }
+                // branches go through the PLT entry, which S already is
+                _ if target.is_branch(r#type) => {}
+                // local-exec accesses are rejected below
+                _ if target.is_got_relocation(r#type) || target.is_tls_relocation(r#type) => {}
+                // executables use the PLT entry as the address of the function
+                _ if shared.info.r#type == symbol::Type::Func && !self.options.shared => {}
+                _ if self.options.shared => {
+                    return Err(error(
+                        "Relocation against a symbol resolved at load time cannot be used in a shared object; recompile with -fPIC".to_string(),
+                    ));
+                }
+                _ => {
+                    return Err(error(
+                        "Relocation against a symbol defined in a shared object would need a copy relocation, which is not supported; recompile with -fPIC".to_string(),
                     ));
                 }
+            }
+        }
 
-                let symbol_name = &self.objects[obj_idx].symbols[symbol_index].name;
+        // the loader only writes to writable sections
+        if dynamic.is_some() && !target_section.flags.contains(&SectionFlag::Write) {
+            return Err(error(format!(
+                "Relocation in read-only section '{}' would need a text relocation, which is not supported; recompile with -fPIC",
+                target_section.name
+            )));
+        }
 
-                let resolved_symbol = resolved_symbols.get(symbol_name).ok_or_else(|| {
-                    LinkerError::relocation_error(
-                        "Symbol is not resolved",
-                        Some(symbol_name.clone()),
-                        Some(ObjectContext {
-                            file_name: object_file_name.clone(),
-                            object_index: obj_idx,
-                        }),
-                        Some("R_AARCH64_ADR_PREL_LO21".to_string()),
-                    )
-                })?;
-
-                let text_section_idx =
-                    section_indices
-                        .get(".text")
-                        .ok_or_else(|| LinkerError::SectionNotFound {
-                            section_name: ".text".to_string(),
-                            context: Some("applying relocation".to_string()),
-                        })?;
-
-                let target_section = &mut output_sections[*text_section_idx];
-
-                if reloc.offset as usize >= target_section.data.len() {
-                    return Err(LinkerError::relocation_error(
-                        format!("Relocation offset out of range: {}", reloc.offset),
-                        Some(symbol_name.clone()),
-                        Some(ObjectContext {
-                            file_name: object_file_name,
-                            object_index: obj_idx,
-                        }),
-                        Some("R_AARCH64_ADR_PREL_LO21".to_string()),
-                    ));
+        // initial-exec TLS accesses load the offset from the thread pointer from the GOT
+        let tls_got = self.uses_tls_got_slot(r#type, shared.is_some());
+
+        // TPREL: offset of the variable from the thread pointer
+        let tprel = || {
+            if self.options.shared {
+                return Err(
+                    "Local-exec TLS relocation cannot be used in a shared object; recompile with -fPIC".to_string(),
+                );
+            }
+            if shared.is_some() {
+                return Err(
+                    "Local-exec TLS relocation cannot refer to a variable of a shared object"
+                        .to_string(),
+                );
+            }
+            context
+                .tls
+                .map(|tls| tls.tp_offset(s + a, target.tls_layout()))
+                .ok_or_else(|| "TLS relocation without TLS sections".to_string())
+        };
+
+        // G: address of the GOT slot of the symbol, GOT: address of the GOT
+        let (g, got) = if (target.is_got_relocation(r#type) && !relaxed) || tls_got {
+            let (got_idx, slot) = context
+                .got
+                .zip(
+                    context
+                        .dynamic_symbols
+                        .got_index(&self.got_entry(obj_idx, reloc)),
+                )
+                .ok_or_else(|| error("Symbol has no GOT slot".to_string()))?;
+            let tls = context.tls;
+            let got = &mut output_sections[got_idx];
+            // slots are as wide as an address
+            let encoding = self.encoding();
+            let slot_size = encoding.class.word_size() as usize;
+            let g = got.addr + (slot * slot_size) as u64;
+            let offset = slot * slot_size;
+            let mut write_slot = |value: i64| {
+                got.data.to_mut()[offset..offset + slot_size]
+                    .copy_from_slice(&encoding.word_bytes(value as u64))
+            };
+            // a slot is filled, and gets its dynamic relocation, by its first reference only
+            let filled = context.filled_got_slots[slot].replace(true);
+            dynamic = match shared {
+                _ if filled => None,
+                // resolved by the loader
+                Some(shared) => Some(DynamicRelocation {
+                    offset: g,
+                    r#type: if tls_got { types.tprel } else { types.glob_dat },
+                    symbol: context
+                        .dynamic_symbols
+                        .symbol_index(&shared.name)
+                        .unwrap_or(0),
+                    addend: 0,
+                }),
+                // where the TLS block of a shared object lies is only known at load time
+                None if tls_got && self.options.shared => Some(DynamicRelocation {
+                    offset: g,
+                    r#type: types.tprel,
+                    symbol: 0,
+                    addend: s + a - tls.map_or(0, |tls| tls.addr as i64),
+                }),
+                None if tls_got => {
+                    write_slot(tprel().map_err(error)?);
+                    None
                 }
+                None => {
+                    let value = if target.got_slot_includes_addend() {
+                        s + a
+                    } else {
+                        s
+                    };
+                    write_slot(value);
+                    (self.options.is_position_independent()
+                        && self.is_position_dependent(obj_idx, reloc, resolved_symbols))
+                    .then_some(DynamicRelocation {
+                        offset: g,
+                        r#type: types.relative,
+                        symbol: 0,
+                        addend: value,
+                    })
+                }
+            };
+            (g as i64, got.addr as i64)
+        } else {
+            (0, 0)
+        };
 
-                let instruction_addr = target_section.addr + reloc.offset;
-                let symbol_addr = resolved_symbol.value;
+        // branches that do not reach their target go through a thunk
+        let thunk = (target.uses_thunk(r#type) && !target.in_branch_range(s + a - p))
+            .then(|| {
+                context.thunks.address(
+                    output_sections,
+                    &context.input_sections,
+                    caller,
+                    &(self.got_entry(obj_idx, reloc), a),
+                )
+            })
+            .flatten();
 
-                // Calculates relative address with symbol (target address - PC value)
-                // PC is the current instruction address (instruction_addr)
-                let relative_addr =
-                    ((symbol_addr as i64) - (instruction_addr as i64) + reloc.addend) as i32;
+        // the value of the relocation at the address of the symbol, which this one
+        // takes instead of its own
+        let paired = match target.paired_relocation(r#type) {
+            Some(paired_type) => self
+                .paired_value(caller, reloc, paired_type, s, output_sections, context)?
+                .ok_or_else(|| {
+                    error(format!(
+                        "Symbol does not point at a {} relocation",
+                        paired_type
+                    ))
+                })?,
+            None => 0,
+        };
 
-                let pos = reloc.offset as usize;
-                let data = target_section.data.to_mut();
-                let instruction = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+        let values = RelocationValues {
+            s,
+            a,
+            p,
+            g,
+            got,
+            tprel: tprel(),
+            shared: shared.is_some(),
+            shared_output: self.options.shared,
+            relaxed,
+            tls_got,
+            thunk,
+            paired,
+            encoding: self.encoding(),
+        };
+        let data = output_sections[target_idx].data.to_mut();
+        let bytes = target
+            .apply_relocation(r#type, &values, data, pos)
+            .map_err(error)?;
+        data[pos..pos + bytes.len()].copy_from_slice(&bytes);
 
-                // Keeps opcode and register portion of the ADR instruction
-                // ADR instruction format: 0bxxx10000 iiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiii
-                // x: opcode, i: immbit, d: destination register
-                let opcode_rd = instruction & 0x9F00001F; // オペコードとレジスタ部分を保持
+        Ok(dynamic)
+    }
+}
 
-                // Encoding of ADR instructions (based on the ARMv8 Architecture Reference Manual)
-                // immhi: upper 19 bits of immediate (bits 5-23)
-                // immlo: lower 2 bits of immediate (bits 29-30)
-                let immlo = ((relative_addr & 0x3) as u32) << 29;
-                let immhi = (((relative_addr >> 2) & 0x7FFFF) as u32) << 5;
+impl Linker {
+    /// Returns the value of the relocation of `paired_type` that a relocation points
+    /// at, i.e. the distance from the instruction at `label` to its target.
+    ///
+    /// The symbol must label an instruction of the same section as the relocation.
+    fn paired_value(
+        &self,
+        (obj_idx, section_idx): InputSectionId,
+        reloc: &relocation::RelocationAddend,
+        paired_type: RelocationType,
+        label: i64,
+        output_sections: &[Section<'static>],
+        context: &RelocationContext,
+    ) -> Result<Option<i64>> {
+        let obj = &self.objects[obj_idx];
+        let Some(symbol) = obj
+            .symbols
+            .get(reloc.info.symbol_index as usize)
+            .filter(|symbol| symbol.shndx == section_idx)
+        else {
+            return Ok(None);
+        };
+        let hi = obj
+            .relocations
+            .iter()
+            .filter(|r| r.target_section == section_idx)
+            .flat_map(|r| r.entries.iter())
+            .find(|r| r.offset == symbol.value && r.info.r#type == paired_type);
+        let Some(hi) = hi else {
+            return Ok(None);
+        };
+        let target = self.relocation_symbol_address(
+            obj_idx,
+            hi,
+            output_sections,
+            &context.input_sections,
+            context.resolved_symbols,
+        )? as i64;
+        Ok(Some(target + hi.addend - label))
+    }
+}
 
-                let new_instruction = opcode_rd | immlo | immhi;
+/// State shared by the relocations of one link.
+struct RelocationContext<'a> {
+    input_sections: InputSectionMap,
+    resolved_symbols: &'a HashMap<String, ResolvedSymbol>,
+    dynamic_symbols: DynamicSymbols,
+    /// Index of `.got` in the output sections.
+    got: Option<usize>,
+    tls: Option<TlsSegment>,
+    thunks: &'a Thunks,
+    /// Slots of `.got` that have already been filled.
+    filled_got_slots: Vec<Cell<bool>>,
+}
 
-                data[pos..pos + 4].copy_from_slice(new_instruction.to_le_bytes().as_slice());
-            }
-        }
+/// Checks that `value` lies in `[min, max)`. The error tells how far outside the
+/// range the value is.
+pub(super) fn check_range(value: i64, min: i64, max: i64) -> std::result::Result<i64, String> {
+    let excess = if value < min {
+        min.abs_diff(value)
+    } else if value >= max {
+        value.abs_diff(max - 1)
+    } else {
+        return Ok(value);
+    };
+    Err(format!(
+        "Relocation overflow: {} is out of range [{}, {}] by {:#x}",
+        signed_hex(value),
+        signed_hex(min),
+        signed_hex(max - 1),
+        excess
+    ))
+}
+
+/// Checks that the distance to the target of a relocation is a multiple of `alignment`.
+pub(super) fn check_alignment(value: i64, alignment: i64) -> std::result::Result<i64, String> {
+    if value % alignment != 0 {
+        return Err(format!(
+            "Relocation target {} is not a multiple of {} bytes away",
+            signed_hex(value),
+            alignment
+        ));
+    }
+    Ok(value)
+}
 
-        Ok(())
+/// Formats a signed value in hexadecimal, with a minus sign for negative values.
+fn signed_hex(value: i64) -> String {
+    match value {
+        0.. => format!("{:#x}", value),
+        _ => format!("-{:#x}", value.unsigned_abs()),
     }
 }
@@ -1,174 +1,407 @@
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use crate::elf::ELF;
-use crate::elf::section::{self, SectionType};
-use crate::elf::symbol;
+use crate::elf::header::{Class, Encoding};
+use crate::elf::section::{SectionFlag, SectionType};
+use crate::elf::segument;
+use crate::elf::symbol::{self, SymbolIndex};
 use crate::error::Result;
 
 use super::Linker;
-use super::output::{ResolvedSymbol, Section};
+use super::eh_frame::{create_eh_frame_hdr, fill_eh_frame_hdr, is_eh_frame};
+use super::gc::{InputSectionId, is_regular_section};
+use super::init_array::{array_section_name, array_section_type, input_order};
+use super::merge;
+use super::output::{InputSection, Report, ResolvedSymbol, Section, SectionName};
+use super::shared::SHARED_OBJECT;
+use super::thunk::Thunks;
+use super::tls::{TlsSegment, tls_segment};
 
 /// Base address for the executable
 pub static BASE_ADDR: u64 = 0x400000;
 
+/// Alignment of loadable segments on AArch64, both in memory and in the file
+pub const PAGE_SIZE: u64 = 0x10000;
+
+/// The ELF header and the program headers are padded to a multiple of this size
+const HEADERS_ALIGN: u64 = 0x100;
+
+/// Input sections whose name starts with one of these prefixes (followed by `.`)
+/// are merged into the output section of the same name.
+const OUTPUT_SECTION_PREFIXES: [&str; 10] = [
+    ".text",
+    ".rodata",
+    ".data",
+    ".bss",
+    ".tdata",
+    ".tbss",
+    ".init_array",
+    ".fini_array",
+    ".ctors",
+    ".dtors",
+];
+
 impl Linker {
+    /// Lays out the output sections of an executable or a shared object, and reports
+    /// the input sections that were left out of them.
     pub fn layout_sections(
         &self,
         resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
-    ) -> Result<(Vec<Section<'static>>, HashMap<String, usize>)> {
-        let output_sections = self.merge_sections(&self.objects, resolved_symbols, BASE_ADDR)?;
-
-        let latest_section_offset = output_sections.iter().last().unwrap().offset;
-        let (symtab_section, strtab_section) =
-            self.make_symbol_section(latest_section_offset, resolved_symbols);
-
-        let mut shstrtab: Vec<u8> = Vec::new();
-        let mut section_name_offsets: HashMap<String, usize> = HashMap::new();
-
-        // add null string
-        shstrtab.push(0);
-
-        for section in output_sections.iter() {
-            section_name_offsets.insert(section.name.to_string(), shstrtab.len());
-            shstrtab.extend_from_slice(section.name.as_bytes());
-            shstrtab.push(0);
+    ) -> Result<(Vec<Section<'static>>, HashMap<String, usize>, Report)> {
+        let mut live_sections = self.collect_live_sections(resolved_symbols);
+        let removed_sections = self
+            .allocated_sections()
+            .into_iter()
+            .filter(|id| !live_sections.contains(id))
+            .map(|id| self.section_name(id))
+            .collect();
+        let mut folded_sections =
+            self.fold_identical_sections(&mut live_sections, resolved_symbols);
+
+        let mut identical: BTreeMap<InputSectionId, Vec<InputSectionId>> = BTreeMap::new();
+        for (&folded, &kept) in &folded_sections {
+            identical.entry(kept).or_default().push(folded);
         }
+        let mut report = Report {
+            map: None,
+            removed_sections,
+            folded_sections: identical
+                .into_iter()
+                .map(|(kept, mut folded)| {
+                    folded.sort();
+                    let folded = folded.into_iter().map(|id| self.section_name(id));
+                    (self.section_name(kept), folded.collect())
+                })
+                .collect(),
+        };
 
-        section_name_offsets.insert(".strtab".to_string(), shstrtab.len());
-        shstrtab.extend_from_slice(".strtab".as_bytes());
-        shstrtab.push(0);
-
-        section_name_offsets.insert(".symtab".to_string(), shstrtab.len());
-        shstrtab.extend_from_slice(".symtab".as_bytes());
-        shstrtab.push(0);
-
-        section_name_offsets.insert(".shstrtab".to_string(), shstrtab.len());
-        shstrtab.extend_from_slice(".shstrtab".as_bytes());
-        shstrtab.push(0);
+        // discarded COMDAT members share the location of their kept copy
+        for (&discarded, &replacement) in &self.discarded_sections {
+            if let Some(kept) = replacement {
+                let kept = folded_sections.get(&kept).copied().unwrap_or(kept);
+                folded_sections.insert(discarded, kept);
+            }
+        }
+        let (output_sections, thunks) = self.merge_sections(
+            &self.objects,
+            &live_sections,
+            &folded_sections,
+            resolved_symbols,
+            if self.options.is_position_independent() {
+                0
+            } else {
+                BASE_ADDR
+            },
+        )?;
+
+        if self.options.map.is_some() {
+            report.map = Some(self.render_map(&output_sections, &thunks, resolved_symbols));
+        }
 
-        let shstrtab_section = Section {
-            name: Cow::Borrowed(".shstrtab"),
-            r#type: SectionType::StrTab,
-            flags: vec![],
-            addr: 0,
-            offset: align(symtab_section.offset + symtab_section.size, 8),
-            size: shstrtab.len() as u64,
-            data: Cow::Owned(shstrtab),
-            align: 1,
-        };
+        let file_end = output_sections
+            .iter()
+            .filter(|s| s.r#type != SectionType::NoBits)
+            .map(|s| s.offset + s.size)
+            .max()
+            .unwrap_or(0);
+        let (symtab_section, strtab_section) =
+            self.make_symbol_section(file_end, &output_sections, resolved_symbols);
 
-        // Combine sections using push instead of concat to avoid clone
-        let mut section_tables = output_sections;
-        section_tables.push(symtab_section);
-        section_tables.push(strtab_section);
-        section_tables.push(shstrtab_section);
+        let (output_sections, section_name_offsets) =
+            add_symbol_and_string_tables(output_sections, symtab_section, strtab_section);
+        Ok((output_sections, section_name_offsets, report))
+    }
 
-        Ok((section_tables, section_name_offsets))
+    fn section_name(&self, (obj_idx, section_idx): InputSectionId) -> SectionName {
+        SectionName {
+            file: self.object_file_name(obj_idx),
+            section: self.objects[obj_idx].section_headers[section_idx as usize]
+                .name
+                .clone(),
+        }
     }
 
     /// Merges sections from multiple ELF object files into the output executable.
     ///
     /// This method:
-    /// 1. Combines all `.text` sections from input objects into one output `.text` section
-    /// 2. Combines all `.data` sections from input objects into one output `.data` section
-    /// 3. Updates symbol addresses based on their new positions in the merged sections
-    /// 4. Applies relocations to the merged sections
+    /// 1. Combines the live input sections into output sections (`.text.*` into `.text`,
+    ///    `.rodata.*` into `.rodata`, `.data.*` into `.data`, `.bss.*` into `.bss`,
+    ///    `.init_array.*` and `.ctors.*` into `.init_array` in priority order, and
+    ///    likewise for `.fini_array`; other allocatable sections keep their name).
+    ///    The contents of mergeable sections are deduplicated first, and `.eh_frame`
+    ///    keeps only the records of placed functions, with an `.eh_frame_hdr` to
+    ///    search them. Dynamically
+    ///    linked and position-independent executables also get the sections used by
+    ///    the dynamic loader, `.got` is added for GOT relocations, and `.iplt` for
+    ///    calls to IFUNC symbols.
+    /// 2. Orders the output sections as code, read-only data, writable data and bss,
+    ///    and assigns file offsets and addresses, starting a new page for each segment
+    /// 3. Updates symbol addresses based on their new positions in the merged sections.
+    ///    Branches that do not reach their target get a thunk, and steps 2 and 3 are
+    ///    repeated until all of them do.
+    /// 4. Applies relocations to the merged sections, and fills in the dynamic sections,
+    ///    including the relocations left for load time, and `.eh_frame_hdr`
     ///
     /// # Arguments
     ///
     /// * `objects` - A slice of ELF object files to be linked
+    /// * `live_sections` - The input sections to include, as `(object index, section index)`
+    /// * `folded_sections` - Input sections folded by `--icf` or discarded with their
+    ///   COMDAT group, mapped to the section that replaces them. They are placed at the
+    ///   same location as that section.
     /// * `resolved_symbols` - A mutable reference to a HashMap mapping symbol names to their resolved locations
-    /// * `base_addr` - The base address where the text section should be loaded
+    /// * `base_addr` - The base address where the first segment should be loaded (0 for
+    ///   position-independent executables)
     ///
     /// # Returns
     ///
-    /// * `Result<Vec<output::Section>, Error>` - A vector of output sections or an error
+    /// * `Result<(Vec<output::Section>, Thunks), Error>` - The output sections and the
+    ///   thunks placed in them, or an error
     fn merge_sections(
         &self,
         objects: &[ELF],
+        live_sections: &HashSet<InputSectionId>,
+        folded_sections: &HashMap<InputSectionId, InputSectionId>,
         resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
         base_addr: u64,
-    ) -> Result<Vec<Section<'static>>> {
-        let mut raw_text_section = vec![];
-        let mut raw_data_section = vec![];
-
-        let mut text_offsets = HashMap::new();
-        let mut data_offsets = HashMap::new();
-
-        let mut text_current_offset = 0;
-        let mut data_current_offset = 0;
-
-        for (obj_idx, obj) in objects.iter().enumerate() {
-            for (section_idx, section) in obj.section_headers.iter().enumerate() {
-                match section.name.as_str() {
-                    ".text" => {
-                        text_offsets.insert((obj_idx, section_idx as u16), text_current_offset);
-                        raw_text_section.extend_from_slice(&section.section_raw_data);
-                        text_current_offset += section.section_raw_data.len();
-                    }
-                    ".data" => {
-                        data_offsets.insert((obj_idx, section_idx as u16), data_current_offset);
-                        raw_data_section.extend_from_slice(&section.section_raw_data);
-                        data_current_offset += section.section_raw_data.len();
-                    }
-                    _ => {
-                        // TODO
-                    }
+    ) -> Result<(Vec<Section<'static>>, Thunks)> {
+        let mut output_sections: Vec<Section<'static>> = Vec::new();
+
+        // mergeable sections are placed as a whole where their first member appears,
+        // and so are the records of `.eh_frame`
+        let (mut merged_sections, mut merged_membership) =
+            merge::merge_mergeable_sections(objects, live_sections, self.options.optimize >= 2);
+        if let Some(eh_frame) =
+            self.merge_eh_frames(live_sections, folded_sections, resolved_symbols)?
+        {
+            for &(id, _) in &eh_frame.members {
+                merged_membership.insert(id, merged_sections.len());
+            }
+            merged_sections.push(eh_frame);
+        }
+        let mut placed_merged_sections = HashSet::new();
+
+        for (obj_idx, section_idx) in input_order(objects) {
+            let header = &objects[obj_idx].section_headers[section_idx as usize];
+            if !live_sections.contains(&(obj_idx, section_idx)) {
+                continue;
+            }
+            // `.eh_frame` sections whose records were all dropped
+            if is_eh_frame(&header.name) && !merged_membership.contains_key(&(obj_idx, section_idx))
+            {
+                continue;
+            }
+            let merged = match merged_membership.get(&(obj_idx, section_idx)) {
+                Some(&merged_idx) if !placed_merged_sections.insert(merged_idx) => continue,
+                Some(&merged_idx) => Some(&merged_sections[merged_idx]),
+                None => None,
+            };
+
+            let name = output_section_name(&header.name);
+            let out_idx = match output_sections.iter().position(|s| s.name == name) {
+                Some(idx) => idx,
+                None => {
+                    output_sections.push(Section {
+                        name: Cow::Owned(name.to_string()),
+                        r#type: array_section_type(name).unwrap_or(header.r#type),
+                        flags: vec![],
+                        addr: 0,
+                        offset: 0,
+                        size: 0,
+                        data: Cow::Owned(vec![]),
+                        align: 1,
+                        link: 0,
+                        info: 0,
+                        // arrays of pointers
+                        entsize: if array_section_type(name).is_some() {
+                            self.class().word_size()
+                        } else {
+                            0
+                        },
+                        inputs: vec![],
+                    });
+                    output_sections.len() - 1
                 }
+            };
+            let out = &mut output_sections[out_idx];
+
+            for flag in [
+                SectionFlag::Write,
+                SectionFlag::Alloc,
+                SectionFlag::ExecInstr,
+                SectionFlag::Tls,
+            ] {
+                if header.flags.contains(&flag) && !out.flags.contains(&flag) {
+                    out.flags.push(flag);
+                }
+            }
+
+            let offset = match merged {
+                Some(merged) => append_input(
+                    out,
+                    header.r#type,
+                    &merged.data,
+                    merged.data.len() as u64,
+                    merged.align,
+                    self.target().nop(),
+                ),
+                None => append_input(
+                    out,
+                    header.r#type,
+                    &header.section_raw_data,
+                    header.size,
+                    header.addralign,
+                    self.target().nop(),
+                ),
+            };
+
+            match merged {
+                Some(merged) => {
+                    out.inputs
+                        .extend(merged.members.iter().map(|(id, pieces)| InputSection {
+                            object_index: id.0,
+                            section_index: id.1,
+                            offset,
+                            folded: false,
+                            pieces: pieces.clone(),
+                        }))
+                }
+                None => out.inputs.push(InputSection {
+                    object_index: obj_idx,
+                    section_index: section_idx,
+                    offset,
+                    folded: false,
+                    pieces: vec![],
+                }),
             }
         }
 
-        // Place after ELF header and program header
-        let text_offset = 0x100;
-        let text_addr = align(base_addr + text_offset, 4);
-
-        let text_section = Section {
-            name: Cow::Borrowed(".text"),
-            r#type: section::SectionType::ProgBits,
-            flags: vec![section::SectionFlag::Alloc, section::SectionFlag::ExecInstr],
-            addr: text_addr,
-            offset: text_offset,
-            size: raw_text_section.len() as u64,
-            data: Cow::Owned(raw_text_section),
-            align: 4,
-        };
+        let InputSectionMap { placements } = InputSectionMap::new(&output_sections);
+        for (&(obj_idx, section_idx), kept) in folded_sections {
+            let Some((out_idx, kept)) = placements.get(kept) else {
+                continue;
+            };
+            output_sections[*out_idx].inputs.push(InputSection {
+                object_index: obj_idx,
+                section_index: section_idx,
+                folded: true,
+                ..kept.clone()
+            });
+        }
+
+        let dynamic_symbols = self.scan_dynamic_symbols(&output_sections, resolved_symbols);
+        output_sections.extend(self.create_dynamic_sections(&dynamic_symbols));
+        output_sections.extend(create_eh_frame_hdr(&output_sections, self.encoding()));
+
+        output_sections.sort_by_key(section_rank);
+        self.link_dynamic_sections(&mut output_sections);
+
+        // the TLS template is aligned to its strictest alignment, so that the offsets
+        // from the thread pointer keep the alignment of every variable
+        let is_tls = |s: &Section<'static>| s.flags.contains(&SectionFlag::Tls);
+        let tls_align = output_sections
+            .iter()
+            .filter(|s| is_tls(s))
+            .map(|s| s.align)
+            .max();
+        if let (Some(first), Some(tls_align)) =
+            (output_sections.iter_mut().find(|s| is_tls(s)), tls_align)
+        {
+            first.align = first.align.max(tls_align);
+        }
+
+        // Place after ELF header and program headers
+        let phnum = self.create_program_headers(&output_sections).len() as u64;
+        let class = self.class();
+        let headers_size = align(
+            class.header_size() + phnum * class.program_header_size(),
+            HEADERS_ALIGN,
+        );
+
+        // the layout is repeated until every branch reaches its target and no call
+        // can be relaxed, and symbols are placed on a copy, as thunks and relaxation
+        // move the sections that follow them
+        let mut thunks = Thunks::new(self.target().thunk_size());
+        let placed_symbols = loop {
+            assign_addresses(
+                &mut output_sections,
+                headers_size,
+                base_addr,
+                self.target().page_size(),
+            );
 
-        let data_offset = text_offset + text_section.size;
-        let data_base_addr = align(text_section.addr + text_section.size + 0x10000, 4);
-        let data_section = Section {
-            name: Cow::Borrowed(".data"),
-            r#type: section::SectionType::ProgBits,
-            flags: vec![section::SectionFlag::Alloc, section::SectionFlag::Write],
-            addr: data_base_addr,
-            offset: data_offset,
-            size: raw_data_section.len() as u64,
-            data: Cow::Owned(raw_data_section),
-            align: 4,
+            let mut placed_symbols = resolved_symbols.clone();
+            self.place_symbols(&output_sections, &mut placed_symbols);
+            self.assign_array_boundaries(&output_sections, &mut placed_symbols);
+            self.assign_iplt_addresses(
+                &mut output_sections,
+                &dynamic_symbols,
+                &mut placed_symbols,
+            )?;
+            self.assign_plt_addresses(&output_sections, &dynamic_symbols, &mut placed_symbols);
+            self.assign_defsym_values(&mut placed_symbols)?;
+
+            let thunks_added =
+                self.insert_thunks(&mut output_sections, &placed_symbols, &mut thunks)?;
+            if !thunks_added && !self.relax_calls(&mut output_sections, &placed_symbols)? {
+                break placed_symbols;
+            }
         };
+        *resolved_symbols = placed_symbols;
+
+        let dynamic_relocations =
+            self.apply_relocations(&mut output_sections, resolved_symbols, &thunks)?;
+        self.fill_dynamic_sections(
+            &mut output_sections,
+            &dynamic_symbols,
+            dynamic_relocations,
+            resolved_symbols,
+        )?;
+        fill_eh_frame_hdr(&mut output_sections, self.encoding())?;
+
+        Ok((output_sections, thunks))
+    }
+
+    /// Drops the symbols of discarded sections, and turns the values of the others
+    /// from offsets in their input section into addresses. The sizes of symbols that
+    /// cover bytes deleted by relaxation shrink accordingly.
+    fn place_symbols(
+        &self,
+        output_sections: &[Section<'static>],
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) {
+        let input_sections = InputSectionMap::new(output_sections);
+
+        // symbols in discarded sections are dropped together with their section
+        resolved_symbols.retain(|_, symbol| {
+            symbol.object_index == SHARED_OBJECT
+                || SymbolIndex::Abs == symbol.shndx
+                || SymbolIndex::Common == symbol.shndx
+                || input_sections
+                    .get(symbol.object_index, symbol.shndx)
+                    .is_some()
+        });
 
         for symbol in resolved_symbols.values_mut() {
-            if let Some(&offset) = text_offsets.get(&(symbol.object_index, symbol.shndx)) {
-                // offset: section offset after merged
-                // symbol.value: offset in the section
-                symbol.value = text_section.addr + (offset + symbol.value as usize) as u64;
-            } else if let Some(&offset) = data_offsets.get(&(symbol.object_index, symbol.shndx)) {
-                symbol.value = data_section.addr + (offset + symbol.value as usize) as u64;
+            // symbol.value: offset in the input section
+            if let Some((out_idx, input)) = input_sections.input(symbol.object_index, symbol.shndx)
+            {
+                symbol.size = input.placed_size(symbol.value, symbol.size);
+                symbol.value = output_sections[out_idx].addr + input.output_offset(symbol.value);
             }
         }
-
-        let mut output_sections = vec![text_section, data_section];
-
-        self.apply_relocations(&mut output_sections, resolved_symbols)?;
-
-        Ok(output_sections)
     }
 
     pub(super) fn make_symbol_section(
         &self,
         latest_section_offset: u64,
+        output_sections: &[Section<'static>],
         resolved_symbols: &HashMap<String, ResolvedSymbol>,
     ) -> (Section<'static>, Section<'static>) {
+        let input_sections = InputSectionMap::new(output_sections);
+        let tls = tls_segment(output_sections);
+
         // symbol string table
         // includes null string
         let mut strtab: Vec<u8> = vec![0];
@@ -195,18 +428,25 @@ impl Linker {
             }
         });
 
+        let local_count = symbols
+            .iter()
+            .take_while(|s| s.info.binding == symbol::Binding::Local)
+            .count();
+
         // add null symbol
-        write_symbol_entry(&mut symtab, 0, 0, 0, 0, 0, 0);
+        let encoding = self.encoding();
+        write_symbol_entry(&mut symtab, encoding, 0, 0, 0, 0, 0, 0);
 
         for symbol in symbols.iter() {
             write_symbol_entry(
                 &mut symtab,
+                encoding,
                 strtab.len() as u32,
-                symbol.value,
+                symbol_value(symbol, tls.as_ref()),
                 symbol.size,
                 symbol.info.into(),
-                0, // st_other
-                symbol.shndx,
+                symbol.visibility as u8,
+                input_sections.output_shndx(symbol.object_index, symbol.shndx),
             );
 
             strtab.extend_from_slice(symbol.name.as_bytes());
@@ -218,10 +458,14 @@ impl Linker {
             r#type: SectionType::StrTab,
             flags: vec![],
             addr: 0,
-            offset: latest_section_offset + 1,
+            offset: latest_section_offset,
             size: strtab.len() as u64,
             data: Cow::Owned(strtab),
             align: 1,
+            link: 0,
+            info: 0,
+            entsize: 0,
+            inputs: vec![],
         };
 
         let symtab_section = Section {
@@ -229,18 +473,316 @@ impl Linker {
             r#type: SectionType::SymTab,
             flags: vec![],
             addr: 0,
-            offset: align(strtab_section.offset + strtab_section.size, 8),
+            offset: align(
+                strtab_section.offset + strtab_section.size,
+                encoding.class.word_size(),
+            ),
             size: symtab.len() as u64,
             data: Cow::Owned(symtab),
-            align: 8,
+            align: encoding.class.word_size(),
+            link: 0,
+            // one past the last local symbol
+            info: local_count as u32 + 1,
+            entsize: encoding.class.symbol_size(),
+            inputs: vec![],
         };
 
         (symtab_section, strtab_section)
     }
 }
 
-fn write_symbol_entry(
+/// Assigns file offsets and addresses to the sorted output sections, after
+/// `headers_size` bytes of headers, starting a new page of `page_size` bytes for
+/// each segment.
+fn assign_addresses(
+    output_sections: &mut [Section<'static>],
+    headers_size: u64,
+    base_addr: u64,
+    page_size: u64,
+) {
+    let mut offset = headers_size;
+    let mut addr = base_addr + offset;
+    let mut prev_flags = None;
+
+    for section in output_sections.iter_mut() {
+        let flags = segment_flags(section);
+        if prev_flags.as_ref().is_some_and(|prev| *prev != flags) {
+            // Each segment starts on a new page. The address stays congruent to
+            // the file offset modulo the page size, as required by the loader.
+            addr = align(addr, page_size) + offset % page_size;
+        }
+        prev_flags = Some(flags);
+
+        offset = align(offset, section.align);
+        addr = align(addr, section.align);
+        section.offset = offset;
+        section.addr = addr;
+
+        if section.r#type != SectionType::NoBits {
+            offset += section.size;
+        }
+        // each thread gets its own copy of .tbss, so it takes no space in the
+        // image and the sections that follow may share its addresses
+        if !(section.r#type == SectionType::NoBits && section.flags.contains(&SectionFlag::Tls)) {
+            addr += section.size;
+        }
+    }
+}
+
+/// Appends the contents of an input section to an output section. The gap left by
+/// the alignment of the input is filled with `nop` in code sections.
+///
+/// Returns the offset of the input section from the start of the output section.
+pub(super) fn append_input(
+    out: &mut Section<'static>,
+    r#type: SectionType,
+    data: &[u8],
+    size: u64,
+    input_align: u64,
+    nop: &[u8],
+) -> u64 {
+    let input_align = input_align.max(1);
+    let offset = align(out.size, input_align);
+    out.align = out.align.max(input_align);
+    out.size = offset + size;
+
+    // an output section only occupies no file space if all of its inputs are NOBITS
+    if out.r#type == SectionType::NoBits && r#type != SectionType::NoBits {
+        out.r#type = r#type;
+    }
+    if out.r#type != SectionType::NoBits {
+        let code = out.flags.contains(&SectionFlag::ExecInstr);
+        let out_data = out.data.to_mut();
+        while out_data.len() < offset as usize {
+            let byte = if code {
+                nop[out_data.len() % nop.len()]
+            } else {
+                0
+            };
+            out_data.push(byte);
+        }
+        if r#type == SectionType::NoBits {
+            out_data.resize(out.size as usize, 0);
+        } else {
+            out_data.extend_from_slice(data);
+        }
+    }
+
+    offset
+}
+
+/// Appends the symbol table, its string table and the section name string table
+/// (`.shstrtab`) to the output sections.
+///
+/// Returns the sections together with the offset of each section name in `.shstrtab`.
+pub(super) fn add_symbol_and_string_tables(
+    output_sections: Vec<Section<'static>>,
+    mut symtab_section: Section<'static>,
+    strtab_section: Section<'static>,
+) -> (Vec<Section<'static>>, HashMap<String, usize>) {
+    let mut shstrtab: Vec<u8> = Vec::new();
+    let mut section_name_offsets: HashMap<String, usize> = HashMap::new();
+
+    // add null string
+    shstrtab.push(0);
+
+    let names =
+        output_sections
+            .iter()
+            .map(|s| s.name.as_ref())
+            .chain([".strtab", ".symtab", ".shstrtab"]);
+    for name in names {
+        if section_name_offsets.contains_key(name) {
+            continue;
+        }
+        section_name_offsets.insert(name.to_string(), shstrtab.len());
+        shstrtab.extend_from_slice(name.as_bytes());
+        shstrtab.push(0);
+    }
+
+    // the string table follows the symbol table (includes null section)
+    symtab_section.link = output_sections.len() as u32 + 2;
+
+    let shstrtab_section = Section {
+        name: Cow::Borrowed(".shstrtab"),
+        r#type: SectionType::StrTab,
+        flags: vec![],
+        addr: 0,
+        offset: align(symtab_section.offset + symtab_section.size, 8),
+        size: shstrtab.len() as u64,
+        data: Cow::Owned(shstrtab),
+        align: 1,
+        link: 0,
+        info: 0,
+        entsize: 0,
+        inputs: vec![],
+    };
+
+    // Combine sections using push instead of concat to avoid clone
+    let mut section_tables = output_sections;
+    section_tables.push(symtab_section);
+    section_tables.push(strtab_section);
+    section_tables.push(shstrtab_section);
+
+    (section_tables, section_name_offsets)
+}
+
+/// Maps input sections to their location in the output sections.
+pub(super) struct InputSectionMap {
+    placements: HashMap<InputSectionId, (usize, InputSection)>,
+}
+
+impl InputSectionMap {
+    pub(super) fn new(output_sections: &[Section<'static>]) -> Self {
+        let mut placements = HashMap::new();
+        for (out_idx, section) in output_sections.iter().enumerate() {
+            for input in &section.inputs {
+                placements.insert(
+                    (input.object_index, input.section_index),
+                    (out_idx, input.clone()),
+                );
+            }
+        }
+        InputSectionMap { placements }
+    }
+
+    /// Returns the index of the output section containing the input section, and the
+    /// offset of the input section within it.
+    pub(super) fn get(&self, object_index: usize, section_index: u16) -> Option<(usize, u64)> {
+        self.placements
+            .get(&(object_index, section_index))
+            .map(|(out_idx, input)| (*out_idx, input.offset))
+    }
+
+    /// Returns the index of the output section containing the input section, and its
+    /// placement there.
+    pub(super) fn input(
+        &self,
+        object_index: usize,
+        section_index: u16,
+    ) -> Option<(usize, &InputSection)> {
+        self.placements
+            .get(&(object_index, section_index))
+            .map(|(out_idx, input)| (*out_idx, input))
+    }
+
+    /// Returns the address in the output of `offset` bytes into the input section.
+    ///
+    /// Offsets into mergeable sections are translated to the location of the piece
+    /// they point into.
+    pub(super) fn address(
+        &self,
+        output_sections: &[Section<'static>],
+        object_index: usize,
+        section_index: u16,
+        offset: u64,
+    ) -> Option<u64> {
+        self.placements
+            .get(&(object_index, section_index))
+            .map(|(out_idx, input)| output_sections[*out_idx].addr + input.output_offset(offset))
+    }
+
+    /// Returns true if the input section was folded into another section by `--icf`.
+    pub(super) fn is_folded(&self, object_index: usize, section_index: u16) -> bool {
+        self.placements
+            .get(&(object_index, section_index))
+            .is_some_and(|(_, input)| input.folded)
+    }
+
+    /// Translates a symbol's section index in its object into the index of the output
+    /// section header. Reserved indices such as `SHN_ABS` are kept as is.
+    pub(super) fn output_shndx(&self, object_index: usize, shndx: u16) -> u16 {
+        if SymbolIndex::Undefined == shndx
+            || SymbolIndex::Abs == shndx
+            || SymbolIndex::Common == shndx
+        {
+            return shndx;
+        }
+        self.get(object_index, shndx)
+            // includes null section
+            .map(|(out_idx, _)| out_idx as u16 + 1)
+            .unwrap_or(SymbolIndex::Undefined as u16)
+    }
+}
+
+/// Returns the name of the output section an input section is merged into.
+pub(super) fn output_section_name(name: &str) -> &str {
+    let name = OUTPUT_SECTION_PREFIXES
+        .into_iter()
+        .find(|prefix| {
+            name.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+        .unwrap_or(name);
+    array_section_name(name).unwrap_or(name)
+}
+
+/// Orders output sections as code, read-only data, TLS data and bss, writable data
+/// and bss, followed by non-allocated sections.
+fn section_rank(section: &Section<'static>) -> u8 {
+    let has = |flag| section.flags.contains(&flag);
+    if !has(SectionFlag::Alloc) {
+        return 6;
+    }
+    match (
+        has(SectionFlag::ExecInstr),
+        has(SectionFlag::Write),
+        has(SectionFlag::Tls),
+        section.r#type == SectionType::NoBits,
+    ) {
+        (true, _, _, _) => 0,
+        (false, false, _, _) => 1,
+        (false, true, true, false) => 2,
+        (false, true, true, true) => 3,
+        (false, true, false, false) => 4,
+        (false, true, false, true) => 5,
+    }
+}
+
+/// Returns the permissions of the segment an allocated section is loaded in.
+pub(super) fn segment_flags(section: &Section<'static>) -> Vec<segument::Flag> {
+    let mut flags = vec![segument::Flag::Readable];
+    if section.flags.contains(&SectionFlag::Write) {
+        flags.push(segument::Flag::Writable);
+    }
+    if section.flags.contains(&SectionFlag::ExecInstr) {
+        flags.push(segument::Flag::Executable);
+    }
+    flags
+}
+
+/// Splits the allocated sections into runs of sections that share a loadable segment.
+///
+/// Allocated sections are expected to come first and to be ordered by [`section_rank`].
+pub(super) fn segments<'s>(sections: &'s [Section<'static>]) -> Vec<&'s [Section<'static>]> {
+    let alloc_count = sections
+        .iter()
+        .take_while(|s| s.flags.contains(&SectionFlag::Alloc))
+        .count();
+    sections[..alloc_count]
+        .chunk_by(|a, b| segment_flags(a) == segment_flags(b))
+        .collect()
+}
+
+/// Returns the value of a symbol in a symbol table of the output: its address, or
+/// the offset in the TLS template for thread-local symbols.
+pub(super) fn symbol_value(symbol: &ResolvedSymbol, tls: Option<&TlsSegment>) -> u64 {
+    match tls {
+        Some(tls)
+            if symbol.info.r#type == symbol::Type::Tls && is_regular_section(symbol.shndx) =>
+        {
+            symbol.value - tls.addr
+        }
+        _ => symbol.value,
+    }
+}
+
+/// Appends an `Elf32_Sym` or `Elf64_Sym` entry, depending on the class of `encoding`:
+/// the value and size come right after the name in the former, and last in the latter.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn write_symbol_entry(
     data: &mut Vec<u8>,
+    encoding: Encoding,
     st_name: u32,
     st_value: u64,
     st_size: u64,
@@ -248,12 +790,19 @@ fn write_symbol_entry(
     st_other: u8,
     st_shndx: u16,
 ) {
-    data.extend_from_slice(&st_name.to_le_bytes());
+    let bit32 = encoding.class == Class::Bit32;
+    data.extend_from_slice(&encoding.u32_bytes(st_name));
+    if bit32 {
+        data.extend_from_slice(&encoding.word_bytes(st_value));
+        data.extend_from_slice(&encoding.word_bytes(st_size));
+    }
     data.push(st_info);
     data.push(st_other);
-    data.extend_from_slice(&st_shndx.to_le_bytes());
-    data.extend_from_slice(&st_value.to_le_bytes());
-    data.extend_from_slice(&st_size.to_le_bytes());
+    data.extend_from_slice(&encoding.u16_bytes(st_shndx));
+    if !bit32 {
+        data.extend_from_slice(&encoding.word_bytes(st_value));
+        data.extend_from_slice(&encoding.word_bytes(st_size));
+    }
 }
 
 /// Aligns a value to the specified power-of-2 alignment boundary.
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use crate::elf::ELF;
+use crate::elf::symbol::{Binding, Symbol, SymbolIndex, Visibility};
+
+use super::Linker;
+use super::output::ResolvedSymbol;
+
+/// Object index used for symbols that are resolved by the dynamic loader, such as
+/// the symbols defined by a shared object.
+pub const SHARED_OBJECT: usize = usize::MAX - 2;
+
+/// A shared library given as input. Only its dynamic symbols take part in the link.
+#[derive(Debug)]
+pub(super) struct SharedObject {
+    /// Name recorded in `DT_NEEDED`: the `DT_SONAME` of the library, or its file name.
+    pub soname: String,
+    /// Global symbols defined by the library.
+    pub symbols: Vec<Symbol>,
+}
+
+impl Linker {
+    /// Adds a shared object (`ET_DYN`) to the link.
+    pub(super) fn add_shared_object(&mut self, name: String, elf: ELF) {
+        let soname = elf.soname().unwrap_or_else(|| {
+            std::path::Path::new(&name)
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy().into_owned())
+                .unwrap_or(name)
+        });
+        let symbols = elf
+            .dynamic_symbols
+            .into_iter()
+            .skip(1)
+            .filter(|symbol| {
+                symbol.info.binding != Binding::Local && SymbolIndex::Undefined != symbol.shndx
+            })
+            .collect();
+
+        self.shared_objects.push(SharedObject { soname, symbols });
+    }
+
+    /// Returns true if the output is linked against shared objects or is
+    /// position-independent, and therefore needs dynamic sections.
+    pub(super) fn is_dynamic(&self) -> bool {
+        !self.options.relocatable
+            && (self.options.is_position_independent() || !self.shared_objects.is_empty())
+    }
+
+    /// Returns true if a shared object being linked exports `symbol` in `.dynsym`.
+    ///
+    /// Global symbols with default or protected visibility are exported, unless the
+    /// version script hides them.
+    pub(super) fn is_exported(&self, symbol: &ResolvedSymbol) -> bool {
+        self.options.shared
+            && symbol.is_defined
+            && symbol.object_index != SHARED_OBJECT
+            && symbol.info.binding != Binding::Local
+            && matches!(
+                symbol.visibility,
+                Visibility::Default | Visibility::Protected
+            )
+            && self
+                .options
+                .version_script
+                .as_ref()
+                .is_none_or(|script| script.is_exported(&symbol.name))
+    }
+
+    /// Resolves the symbols that no object defines to the shared objects that define
+    /// them, in command line order.
+    ///
+    /// A shared object being linked may leave symbols undefined; they are resolved by
+    /// the dynamic loader against the libraries loaded with it.
+    pub(super) fn resolve_shared_symbols(
+        &self,
+        resolved_symbols: &mut HashMap<String, ResolvedSymbol>,
+    ) {
+        // the first shared object on the command line that defines a symbol wins
+        let mut definitions = HashMap::new();
+        for definition in self
+            .shared_objects
+            .iter()
+            .flat_map(|shared| shared.symbols.iter())
+        {
+            definitions
+                .entry(definition.name.as_str())
+                .or_insert(definition);
+        }
+
+        for symbol in resolved_symbols.values_mut().filter(|s| !s.is_defined) {
+            let Some(definition) = definitions.get(symbol.name.as_str()) else {
+                continue;
+            };
+
+            symbol.value = 0;
+            symbol.size = definition.size;
+            symbol.info.r#type = definition.info.r#type;
+            symbol.shndx = SymbolIndex::Undefined as u16;
+            symbol.object_index = SHARED_OBJECT;
+            symbol.is_defined = true;
+        }
+
+        if self.options.shared {
+            for symbol in resolved_symbols.values_mut().filter(|s| !s.is_defined) {
+                symbol.object_index = SHARED_OBJECT;
+                symbol.is_defined = true;
+            }
+        }
+    }
+
+    /// Returns the resolution of `symbol` if it is resolved by the dynamic loader.
+    pub(super) fn shared_symbol<'a>(
+        &self,
+        symbol: &Symbol,
+        resolved_symbols: &'a HashMap<String, ResolvedSymbol>,
+    ) -> Option<&'a ResolvedSymbol> {
+        if symbol.info.binding == Binding::Local {
+            return None;
+        }
+        resolved_symbols
+            .get(self.reference_name(symbol).as_ref())
+            .filter(|resolved| resolved.object_index == SHARED_OBJECT)
+    }
+}
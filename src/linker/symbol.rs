@@ -1,6 +1,7 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 
-use crate::elf::symbol::SymbolIndex;
+use crate::elf::symbol::{Binding, Symbol, SymbolIndex};
 use crate::error::{LinkerError, ObjectContext, Result, UnresolvedSymbol};
 
 use super::Linker;
@@ -18,21 +19,32 @@ impl Linker {
                 .cloned()
                 .unwrap_or_else(|| format!("object_{}", obj_idx));
 
-            for symbol in &obj.symbols {
+            // the first entry is the reserved null symbol
+            for symbol in obj.symbols.iter().skip(1) {
+                let name = self.reference_name(symbol).into_owned();
+                // definitions in discarded COMDAT groups refer to the kept copy
+                let shndx = if symbol.info.binding != Binding::Local
+                    && self.is_discarded((obj_idx, symbol.shndx))
+                {
+                    SymbolIndex::Undefined as u16
+                } else {
+                    symbol.shndx
+                };
                 let new_symbol = ResolvedSymbol {
-                    name: symbol.name.clone(),
+                    name: name.clone(),
                     value: symbol.value,
                     size: symbol.size,
                     info: symbol.info,
-                    shndx: symbol.shndx,
+                    visibility: symbol.other,
+                    shndx,
                     object_index: obj_idx,
-                    is_defined: SymbolIndex::Undefined != symbol.shndx,
+                    is_defined: SymbolIndex::Undefined != shndx,
                 };
 
-                if let Some(existing) = resolved_symbols.get(&symbol.name) {
+                if let Some(existing) = resolved_symbols.get(&name) {
                     if new_symbol.is_defined && existing.is_defined {
                         if new_symbol.is_stronger_than(existing) {
-                            resolved_symbols.insert(symbol.name.clone(), new_symbol);
+                            resolved_symbols.insert(name, new_symbol);
                         } else {
                             let existing_file_name = self
                                 .object_names
@@ -40,7 +52,7 @@ impl Linker {
                                 .cloned()
                                 .unwrap_or_else(|| format!("object_{}", existing.object_index));
                             duplicate_symbols.insert(
-                                symbol.name.clone(),
+                                name,
                                 (
                                     ObjectContext {
                                         file_name: existing_file_name,
@@ -54,14 +66,19 @@ impl Linker {
                             );
                         }
                     } else if new_symbol.is_defined && !existing.is_defined {
-                        resolved_symbols.insert(symbol.name.clone(), new_symbol);
+                        resolved_symbols.insert(name, new_symbol);
                     }
                 } else {
-                    resolved_symbols.insert(symbol.name.clone(), new_symbol);
+                    resolved_symbols.insert(name, new_symbol);
                 }
             }
         }
 
+        self.define_command_line_symbols(&mut resolved_symbols);
+        self.define_synthetic_symbols(&mut resolved_symbols);
+        self.define_array_symbols(&mut resolved_symbols);
+        self.resolve_shared_symbols(&mut resolved_symbols);
+
         if let Some((symbol_name, (first_def, dup_def))) = duplicate_symbols.into_iter().next() {
             return Err(LinkerError::duplicate_symbol(
                 symbol_name,
@@ -90,10 +107,41 @@ impl Linker {
             })
             .collect();
 
-        if !unresolved_symbols.is_empty() {
+        // undefined symbols are left for the final link of a relocatable output
+        if !unresolved_symbols.is_empty() && !self.options.relocatable {
             return Err(LinkerError::unresolved_symbols(unresolved_symbols));
         }
 
+        if let Some(name) = self
+            .options
+            .require_defined
+            .iter()
+            .find(|name| !resolved_symbols.get(*name).is_some_and(|s| s.is_defined))
+        {
+            return Err(LinkerError::RequiredSymbolNotDefined {
+                symbol_name: name.clone(),
+            });
+        }
+
         Ok(resolved_symbols)
     }
+
+    /// Returns the name that a reference to `symbol` binds to.
+    ///
+    /// Definitions keep their own name. Undefined references are rewritten according
+    /// to `--wrap`: `sym` becomes `__wrap_sym`, and `__real_sym` becomes `sym`.
+    pub(super) fn reference_name<'a>(&self, symbol: &'a Symbol) -> Cow<'a, str> {
+        if self.options.wrap.is_empty() || SymbolIndex::Undefined != symbol.shndx {
+            return Cow::Borrowed(&symbol.name);
+        }
+
+        if self.options.wrap.contains(&symbol.name) {
+            return Cow::Owned(format!("__wrap_{}", symbol.name));
+        }
+
+        match symbol.name.strip_prefix("__real_") {
+            Some(real) if self.options.wrap.contains(real) => Cow::Borrowed(real),
+            _ => Cow::Borrowed(&symbol.name),
+        }
+    }
 }
@@ -0,0 +1,385 @@
+mod aarch64;
+mod riscv;
+mod x86_64;
+
+use crate::elf::header::{Class, Data, Encoding, Header, Machine};
+use crate::elf::relocation::RelocationType;
+use crate::error::{LinkerError, Result};
+
+use super::Linker;
+use super::tls::TlsLayout;
+
+/// Alignment of loadable segments on x86-64 and RISC-V, where pages are 4 KiB
+const SMALL_PAGE_SIZE: u64 = 0x1000;
+
+/// Error for the relocations that only the dynamic loader applies.
+const DYNAMIC_RELOCATION_ERROR: &str = "Dynamic relocation is not allowed in an object file";
+
+/// The parts of a link that depend on the instruction set: how relocations patch
+/// the code, and the code that the linker generates itself for PLT entries, thunks
+/// and padding. The implementation is chosen from the machine of the inputs, so the
+/// layout does not need to know which one it is.
+pub(super) trait Target: Sync {
+    /// Machine of the inputs and of the output (`e_machine`).
+    fn machine(&self) -> Machine;
+
+    /// Alignment of loadable segments, both in memory and in the file.
+    fn page_size(&self) -> u64;
+
+    /// Returns true if the output can be big-endian. Only the byte order of data
+    /// changes: instructions keep the order that the machine always uses.
+    fn supports_big_endian(&self) -> bool {
+        false
+    }
+
+    /// Encoding of a `nop`, repeated to fill the gaps between the inputs of code
+    /// sections.
+    fn nop(&self) -> &'static [u8];
+
+    /// Number of bytes patched by a relocation.
+    fn relocation_width(&self, r#type: RelocationType) -> usize;
+
+    /// Returns the relocated bytes of the place at `pos` in `data`. The bytes before
+    /// the place may be rewritten as well, as when relaxing x86-64 GOT loads.
+    fn apply_relocation(
+        &self,
+        r#type: RelocationType,
+        values: &RelocationValues,
+        data: &mut [u8],
+        pos: usize,
+    ) -> std::result::Result<Vec<u8>, String>;
+
+    /// Number of bytes patched by a relocation whose instructions were relaxed.
+    fn relaxed_width(&self, r#type: RelocationType) -> usize {
+        self.relocation_width(r#type)
+    }
+
+    /// Types of the relocations that the dynamic loader applies to the output.
+    fn dynamic_relocations(&self) -> DynamicRelocationTypes;
+
+    /// Returns true for the relocations that store an absolute address, which have
+    /// to be applied again at load time in position-independent output.
+    fn is_absolute_relocation(&self, r#type: RelocationType) -> bool;
+
+    /// Returns true for the relocations that refer to the GOT slot of a symbol.
+    fn is_got_relocation(&self, r#type: RelocationType) -> bool;
+
+    /// Returns true for the thread-local storage relocations.
+    fn is_tls_relocation(&self, _type: RelocationType) -> bool {
+        false
+    }
+
+    /// Returns true if a TLS relocation refers to a GOT slot that holds the offset of
+    /// the variable from the thread pointer. `initial_exec` is true where accesses
+    /// that can be relaxed are relaxed to initial-exec rather than local-exec: for
+    /// variables of shared objects in an executable.
+    fn uses_tls_got_slot(&self, _type: RelocationType, _initial_exec: bool) -> bool {
+        false
+    }
+
+    /// Where the thread pointer points relative to the TLS block of the executable.
+    fn tls_layout(&self) -> TlsLayout;
+
+    /// Returns true if the GOT load of a relocation at `offset` in `data`, the bytes
+    /// of its input section, can be rewritten to use the address of the symbol.
+    fn can_relax_got_load(
+        &self,
+        _type: RelocationType,
+        _addend: i64,
+        _data: &[u8],
+        _offset: usize,
+    ) -> bool {
+        false
+    }
+
+    /// Returns true if the GOT slot of a symbol holds its address plus the addend of
+    /// the GOT relocation, false if the addend applies to the place instead.
+    fn got_slot_includes_addend(&self) -> bool {
+        true
+    }
+
+    /// Returns the type of the relocation whose value a relocation of `type` takes:
+    /// the one at the address of its symbol, in the same section.
+    fn paired_relocation(&self, _type: RelocationType) -> Option<RelocationType> {
+        None
+    }
+
+    /// Sizes of the PLT header and of a PLT entry, or `None` if the machine has no
+    /// PLT, which limits it to static executables and relocatable output.
+    fn plt_sizes(&self) -> Option<(u64, u64)> {
+        None
+    }
+
+    /// Returns the PLT header at `plt`, which calls the lazy binding routine that the
+    /// dynamic loader stores in `.got.plt`.
+    fn plt_header(&self, _plt: u64, _got_plt: u64) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Returns a PLT entry at `entry`, which jumps to the address stored at `slot`.
+    fn plt_entry(&self, _entry: u64, _slot: u64) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Returns true for the relocations of direct branches and calls, which reach the
+    /// functions of shared objects through their PLT entry, and do not take the
+    /// address of their target.
+    fn is_branch(&self, _type: RelocationType) -> bool {
+        false
+    }
+
+    /// Returns true for the relocations of branches that go through a thunk when
+    /// their target is out of range.
+    fn uses_thunk(&self, _type: RelocationType) -> bool {
+        false
+    }
+
+    /// Returns true if a branch reaches a target `offset` bytes away.
+    fn in_branch_range(&self, _offset: i64) -> bool {
+        true
+    }
+
+    /// Size of a thunk.
+    fn thunk_size(&self) -> u64 {
+        0
+    }
+
+    /// Returns the code of a thunk at `addr` jumping to `target`.
+    fn thunk_code(&self, _addr: u64, _target: i64) -> std::result::Result<Vec<u8>, String> {
+        Ok(Vec::new())
+    }
+}
+
+/// Types of the relocations that the dynamic loader applies.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct DynamicRelocationTypes {
+    /// Adds the load address to the addend.
+    pub relative: RelocationType,
+    /// Stores the address of a symbol plus the addend.
+    pub absolute: RelocationType,
+    /// Stores the address of a symbol in its GOT slot.
+    pub glob_dat: RelocationType,
+    /// Stores the address of a function in its `.got.plt` slot.
+    pub jump_slot: RelocationType,
+    /// Stores the offset of a TLS variable from the thread pointer.
+    pub tprel: RelocationType,
+    /// Stores the address returned by the resolver of an IFUNC symbol.
+    pub irelative: RelocationType,
+}
+
+/// What a relocation is computed from.
+#[derive(Debug, Clone)]
+pub(super) struct RelocationValues {
+    /// S: address of the symbol
+    pub s: i64,
+    /// A: addend
+    pub a: i64,
+    /// P: address of the place being relocated
+    pub p: i64,
+    /// G: address of the GOT slot of the symbol, if it has one
+    pub g: i64,
+    /// GOT: address of the GOT, if the symbol has a slot
+    pub got: i64,
+    /// Offset of the symbol from the thread pointer, or why there is none.
+    pub tprel: std::result::Result<i64, String>,
+    /// The symbol is defined in a shared object, and resolved at load time.
+    pub shared: bool,
+    /// The output is a shared object.
+    pub shared_output: bool,
+    /// The instructions were relaxed: a GOT load uses the address of the symbol
+    /// directly, or a call was shortened.
+    pub relaxed: bool,
+    /// The initial-exec TLS access loads the offset of the variable from the GOT.
+    pub tls_got: bool,
+    /// Address of the thunk that the branch goes through.
+    pub thunk: Option<u64>,
+    /// Value (S + A - P) of the relocation that [`Target::paired_relocation`] pairs
+    /// this one with.
+    pub paired: i64,
+    /// Class and byte order of the output, which relocated data follows.
+    pub encoding: Encoding,
+}
+
+/// Returns the implementation for a machine, if it is supported.
+fn target(machine: Machine) -> Option<&'static dyn Target> {
+    match machine {
+        Machine::AArch64 => Some(&aarch64::AArch64),
+        Machine::X86_64 => Some(&x86_64::X86_64),
+        Machine::RiscV => Some(&riscv::RiscV),
+        _ => None,
+    }
+}
+
+impl Linker {
+    /// Records the machine, the class and the byte order of an input file. The first
+    /// input decides them for the output, and the other inputs must match.
+    ///
+    /// The flags of RISC-V inputs, which tell the ABI and the extensions used, are
+    /// combined into the flags of the output, starting with those of the first input.
+    pub(super) fn check_machine(&mut self, name: &str, header: &Header) -> Result<()> {
+        let machine = header.machine;
+        let first = self.machine == Machine::None;
+        if target(machine).is_none() {
+            return Err(LinkerError::Generic {
+                message: format!("{}: unsupported machine {}", name, machine),
+                context: None,
+            });
+        }
+        match self.machine {
+            Machine::None => self.machine = machine,
+            output if output == machine => {}
+            output => {
+                return Err(LinkerError::Generic {
+                    message: format!(
+                        "{} is for {}, but the other inputs are for {}",
+                        name, machine, output
+                    ),
+                    context: None,
+                });
+            }
+        }
+        let encoding = header.ident.encoding();
+        let invalid = if !matches!(encoding.class, Class::Bit32 | Class::Bit64) {
+            Some(format!("invalid ELF class {:?}", encoding.class))
+        } else if !matches!(encoding.data, Data::Lsb | Data::Msb) {
+            Some(format!("invalid ELF data encoding {:?}", encoding.data))
+        } else if encoding.is_big_endian() && !target(machine).unwrap().supports_big_endian() {
+            Some(format!("big-endian {} is not supported", machine))
+        } else {
+            None
+        };
+        if let Some(message) = invalid {
+            return Err(LinkerError::Generic {
+                message: format!("{}: {}", name, message),
+                context: None,
+            });
+        }
+        match self.encoding {
+            output if output.class == Class::None => self.encoding = encoding,
+            output if output == encoding => {}
+            output => {
+                let describe = |encoding: Encoding| {
+                    format!(
+                        "{}-bit {}",
+                        encoding.class.word_size() * 8,
+                        if encoding.is_big_endian() {
+                            "big-endian"
+                        } else {
+                            "little-endian"
+                        }
+                    )
+                };
+                return Err(LinkerError::Generic {
+                    message: format!(
+                        "{} is {}, but the other inputs are {}",
+                        name,
+                        describe(encoding),
+                        describe(output)
+                    ),
+                    context: None,
+                });
+            }
+        }
+        if machine == Machine::RiscV {
+            self.flags = if first {
+                header.flags
+            } else {
+                riscv::merge_flags(self.flags, header.flags).map_err(|message| {
+                    LinkerError::Generic {
+                        message: format!("{} {}", name, message),
+                        context: None,
+                    }
+                })?
+            };
+        }
+        Ok(())
+    }
+
+    /// Returns the machine of the output, AArch64 if there are no inputs.
+    pub(super) fn machine(&self) -> Machine {
+        match self.machine {
+            Machine::None => Machine::AArch64,
+            machine => machine,
+        }
+    }
+
+    /// Returns the class and the byte order of the output, 64-bit little-endian if
+    /// there are no inputs.
+    pub(super) fn encoding(&self) -> Encoding {
+        match self.encoding.class {
+            Class::None => Encoding {
+                class: Class::Bit64,
+                data: Data::Lsb,
+            },
+            _ => self.encoding,
+        }
+    }
+
+    /// Returns the class of the output, 64-bit if there are no inputs.
+    pub(super) fn class(&self) -> Class {
+        self.encoding().class
+    }
+
+    /// Returns the implementation of the machine of the output.
+    pub(super) fn target(&self) -> &'static dyn Target {
+        target(self.machine()).unwrap_or(&aarch64::AArch64)
+    }
+
+    /// Rejects the kinds of output that are not supported for the machine: output
+    /// for a machine without a PLT can only be a static executable or a relocatable
+    /// object, and 32-bit output can only be a static executable.
+    pub(super) fn check_target_options(&self) -> Result<()> {
+        let bit32 = self.class() == Class::Bit32;
+        let dynamic = self.target().plt_sizes().is_some() && !bit32;
+        let unsupported = if self.options.relocatable && bit32 {
+            Some("-r")
+        } else if dynamic {
+            None
+        } else if self.options.shared {
+            Some("-shared")
+        } else if self.options.pie {
+            Some("-pie")
+        } else if !self.shared_objects.is_empty() {
+            Some("linking against shared objects")
+        } else {
+            None
+        };
+        let output = if bit32 {
+            format!("32-bit {}", self.machine())
+        } else {
+            self.machine().to_string()
+        };
+        match unsupported {
+            Some(feature) => Err(LinkerError::Generic {
+                message: format!("{} is not supported for {}", feature, output),
+                context: None,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_select_target_from_machine() {
+        let targets: [(Machine, u64, &[u8]); 3] = [
+            (Machine::AArch64, 0x10000, &[0x1f, 0x20, 0x03, 0xd5]),
+            (Machine::X86_64, 0x1000, &[0x90]),
+            (Machine::RiscV, 0x1000, &[0x13, 0x00, 0x00, 0x00]),
+        ];
+        for (machine, page_size, nop) in targets {
+            let target = target(machine).unwrap();
+            assert_eq!(target.machine(), machine);
+            assert_eq!(target.page_size(), page_size);
+            assert_eq!(target.nop(), nop);
+        }
+        assert!(target(Machine::None).is_none());
+        // only AArch64 has a PLT, so the others cannot be linked dynamically
+        assert!(target(Machine::AArch64).unwrap().plt_sizes().is_some());
+        assert!(target(Machine::X86_64).unwrap().plt_sizes().is_none());
+    }
+}
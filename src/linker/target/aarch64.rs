@@ -0,0 +1,575 @@
+use crate::elf::header::Machine;
+use crate::elf::relocation::RelocationType;
+
+use super::super::dynamic::GOT_ENTRY_SIZE;
+use super::super::relocation::{check_alignment, check_range};
+use super::super::section::PAGE_SIZE;
+use super::super::tls::TlsLayout;
+use super::{DYNAMIC_RELOCATION_ERROR, DynamicRelocationTypes, RelocationValues, Target};
+
+/// Encoding of the `nop` instruction
+const NOP: u32 = 0xd503201f;
+
+/// Size of the PLT header, which calls the lazy binding routine of the dynamic loader
+const PLT_HEADER_SIZE: u64 = 32;
+/// Size of a PLT entry, which jumps through its `.got.plt` slot
+const PLT_ENTRY_SIZE: u64 = 16;
+
+/// Size of a thunk: `adrp x16, target; add x16, x16, :lo12:target; br x16`
+const THUNK_SIZE: u64 = 12;
+
+/// B and BL reach +/-128 MiB from the branch.
+const BRANCH_RANGE: i64 = 1 << 27;
+
+/// Size of the thread control block that the thread pointer points to, which the
+/// TLS block of the executable follows.
+const TCB_SIZE: u64 = 16;
+
+/// AArch64 (ARMv8-A), little-endian or big-endian (`aarch64_be`)
+pub(super) struct AArch64;
+
+impl Target for AArch64 {
+    fn machine(&self) -> Machine {
+        Machine::AArch64
+    }
+
+    fn page_size(&self) -> u64 {
+        PAGE_SIZE
+    }
+
+    fn supports_big_endian(&self) -> bool {
+        true
+    }
+
+    fn nop(&self) -> &'static [u8] {
+        const BYTES: [u8; 4] = NOP.to_le_bytes();
+        &BYTES
+    }
+
+    fn relocation_width(&self, r#type: RelocationType) -> usize {
+        match r#type {
+            RelocationType::Aarch64None => 0,
+            RelocationType::Aarch64Abs64 | RelocationType::Aarch64Prel64 => 8,
+            RelocationType::Aarch64Abs16 | RelocationType::Aarch64Prel16 => 2,
+            _ => 4,
+        }
+    }
+
+    fn apply_relocation(
+        &self,
+        r#type: RelocationType,
+        values: &RelocationValues,
+        data: &mut [u8],
+        pos: usize,
+    ) -> std::result::Result<Vec<u8>, String> {
+        let &RelocationValues {
+            s,
+            a,
+            p,
+            g,
+            got,
+            relaxed,
+            tls_got,
+            encoding,
+            ..
+        } = values;
+        // instructions are little-endian even when data is big-endian
+        let instruction = || u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+
+        let bytes = match r#type {
+            RelocationType::Aarch64Relative
+            | RelocationType::Aarch64Copy
+            | RelocationType::Aarch64GlobDat
+            | RelocationType::Aarch64JumpSlot
+            | RelocationType::Aarch64TlsDtpMod64
+            | RelocationType::Aarch64TlsDtpRel64
+            | RelocationType::Aarch64TlsTpRel64
+            | RelocationType::Aarch64TlsDesc
+            | RelocationType::Aarch64IRelative => {
+                return Err(DYNAMIC_RELOCATION_ERROR.to_string());
+            }
+            // symbolic dynamic relocations are resolved by the loader
+            RelocationType::Aarch64Abs64 if values.shared => encoding.u64_bytes(a as u64).to_vec(),
+            RelocationType::Aarch64Abs64 => encoding.u64_bytes((s + a) as u64).to_vec(),
+            RelocationType::Aarch64Prel64 => encoding.u64_bytes((s + a - p) as u64).to_vec(),
+            RelocationType::Aarch64Abs32 => {
+                let value = check_range(s + a, -(1 << 31), 1 << 32)?;
+                encoding.u32_bytes(value as u32).to_vec()
+            }
+            RelocationType::Aarch64Prel32 => {
+                let value = check_range(s + a - p, -(1 << 31), 1 << 32)?;
+                encoding.u32_bytes(value as u32).to_vec()
+            }
+            RelocationType::Aarch64Abs16 => {
+                let value = check_range(s + a, -(1 << 15), 1 << 16)?;
+                encoding.u16_bytes(value as u16).to_vec()
+            }
+            RelocationType::Aarch64Prel16 => {
+                let value = check_range(s + a - p, -(1 << 15), 1 << 16)?;
+                encoding.u16_bytes(value as u16).to_vec()
+            }
+            RelocationType::Aarch64AdrPrelLo21 => {
+                // Calculates relative address with symbol (target address - PC value)
+                let relative_addr = check_range(s + a - p, -(1 << 20), 1 << 20)?;
+                encode_adr(instruction(), relative_addr)
+                    .to_le_bytes()
+                    .to_vec()
+            }
+            RelocationType::Aarch64AdrPrelPgHi21 | RelocationType::Aarch64AdrPrelPgHi21Nc => {
+                // ADRP: distance between the 4 KiB pages of the target and the PC
+                let pages = (page(s + a) - page(p)) >> 12;
+                if r#type == RelocationType::Aarch64AdrPrelPgHi21 {
+                    check_range(pages, -(1 << 20), 1 << 20)?;
+                }
+                encode_adr(instruction(), pages).to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64AdrGotPage => {
+                // relaxed: adrp to the page of the symbol instead of its GOT slot
+                let target = if relaxed { s + a } else { g };
+                let pages = check_range((page(target) - page(p)) >> 12, -(1 << 20), 1 << 20)?;
+                encode_adr(instruction(), pages).to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64Ld64GotLo12Nc if relaxed => {
+                // ldr xd, [xn, :got_lo12:sym] becomes add xd, xn, :lo12:sym
+                let imm12 = ((s + a) & 0xFFF) as u32;
+                let new_instruction = 0x91000000 | (imm12 << 10) | (instruction() & 0x3FF);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64Ld64GotLo12Nc => {
+                // LDR of a 64-bit slot: the immediate is scaled by 8
+                let imm12 = ((g & 0xFFF) >> 3) as u32;
+                let new_instruction = (instruction() & !(0xFFF << 10)) | (imm12 << 10);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64Ld64GotPageLo15 => {
+                // offset of the slot from the page of the GOT, scaled by 8
+                let offset = check_range(g - page(got), 0, 1 << 15)?;
+                let imm12 = (offset >> 3) as u32;
+                let new_instruction = (instruction() & !(0xFFF << 10)) | (imm12 << 10);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64MovwUabsG0
+            | RelocationType::Aarch64MovwUabsG0Nc
+            | RelocationType::Aarch64MovwUabsG1
+            | RelocationType::Aarch64MovwUabsG1Nc
+            | RelocationType::Aarch64MovwUabsG2
+            | RelocationType::Aarch64MovwUabsG2Nc
+            | RelocationType::Aarch64MovwUabsG3 => {
+                let (shift, checked) = match r#type {
+                    RelocationType::Aarch64MovwUabsG0 => (0, true),
+                    RelocationType::Aarch64MovwUabsG0Nc => (0, false),
+                    RelocationType::Aarch64MovwUabsG1 => (16, true),
+                    RelocationType::Aarch64MovwUabsG1Nc => (16, false),
+                    RelocationType::Aarch64MovwUabsG2 => (32, true),
+                    RelocationType::Aarch64MovwUabsG2Nc => (32, false),
+                    _ => (48, false),
+                };
+                if checked {
+                    check_range(s + a, 0, 1 << (shift + 16))?;
+                }
+                // MOVZ/MOVK: imm16 in bits 5-20
+                let imm16 = (((s + a) >> shift) & 0xFFFF) as u32;
+                let new_instruction = (instruction() & !(0xFFFF << 5)) | (imm16 << 5);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64MovwSabsG0
+            | RelocationType::Aarch64MovwSabsG1
+            | RelocationType::Aarch64MovwSabsG2
+            | RelocationType::Aarch64MovwPrelG0
+            | RelocationType::Aarch64MovwPrelG0Nc
+            | RelocationType::Aarch64MovwPrelG1
+            | RelocationType::Aarch64MovwPrelG1Nc
+            | RelocationType::Aarch64MovwPrelG2
+            | RelocationType::Aarch64MovwPrelG2Nc
+            | RelocationType::Aarch64MovwPrelG3 => {
+                let (value, shift, checked) = match r#type {
+                    RelocationType::Aarch64MovwSabsG0 => (s + a, 0, true),
+                    RelocationType::Aarch64MovwSabsG1 => (s + a, 16, true),
+                    RelocationType::Aarch64MovwSabsG2 => (s + a, 32, true),
+                    RelocationType::Aarch64MovwPrelG0 => (s + a - p, 0, true),
+                    RelocationType::Aarch64MovwPrelG0Nc => (s + a - p, 0, false),
+                    RelocationType::Aarch64MovwPrelG1 => (s + a - p, 16, true),
+                    RelocationType::Aarch64MovwPrelG1Nc => (s + a - p, 16, false),
+                    RelocationType::Aarch64MovwPrelG2 => (s + a - p, 32, true),
+                    RelocationType::Aarch64MovwPrelG2Nc => (s + a - p, 32, false),
+                    _ => (s + a - p, 48, false),
+                };
+                if checked {
+                    let limit = 1 << (shift + 16);
+                    check_range(value, -limit, limit)?;
+                }
+                encode_signed_movw(instruction(), value >> shift)
+                    .to_le_bytes()
+                    .to_vec()
+            }
+            RelocationType::Aarch64TlsLeMovwTprelG2
+            | RelocationType::Aarch64TlsLeMovwTprelG1
+            | RelocationType::Aarch64TlsLeMovwTprelG1Nc
+            | RelocationType::Aarch64TlsLeMovwTprelG0
+            | RelocationType::Aarch64TlsLeMovwTprelG0Nc => {
+                let tprel = values.tprel.clone()?;
+                let (shift, checked) = match r#type {
+                    RelocationType::Aarch64TlsLeMovwTprelG2 => (32, true),
+                    RelocationType::Aarch64TlsLeMovwTprelG1 => (16, true),
+                    RelocationType::Aarch64TlsLeMovwTprelG1Nc => (16, false),
+                    RelocationType::Aarch64TlsLeMovwTprelG0 => (0, true),
+                    _ => (0, false),
+                };
+                if checked {
+                    check_range(tprel, 0, 1 << (shift + 16))?;
+                }
+                // MOVZ/MOVK: imm16 in bits 5-20
+                let imm16 = ((tprel >> shift) & 0xFFFF) as u32;
+                let new_instruction = (instruction() & !(0xFFFF << 5)) | (imm16 << 5);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64TlsLeAddTprelHi12 => {
+                let tprel = check_range(values.tprel.clone()?, 0, 1 << 24)?;
+                let imm12 = ((tprel >> 12) & 0xFFF) as u32;
+                let new_instruction = (instruction() & !(0xFFF << 10)) | (imm12 << 10);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64TlsLeAddTprelLo12
+            | RelocationType::Aarch64TlsLeAddTprelLo12Nc
+            | RelocationType::Aarch64TlsLeLdst8TprelLo12
+            | RelocationType::Aarch64TlsLeLdst8TprelLo12Nc
+            | RelocationType::Aarch64TlsLeLdst16TprelLo12
+            | RelocationType::Aarch64TlsLeLdst16TprelLo12Nc
+            | RelocationType::Aarch64TlsLeLdst32TprelLo12
+            | RelocationType::Aarch64TlsLeLdst32TprelLo12Nc
+            | RelocationType::Aarch64TlsLeLdst64TprelLo12
+            | RelocationType::Aarch64TlsLeLdst64TprelLo12Nc
+            | RelocationType::Aarch64TlsLeLdst128TprelLo12
+            | RelocationType::Aarch64TlsLeLdst128TprelLo12Nc => {
+                let tprel = values.tprel.clone()?;
+                let (shift, checked) = match r#type {
+                    RelocationType::Aarch64TlsLeAddTprelLo12
+                    | RelocationType::Aarch64TlsLeLdst8TprelLo12 => (0, true),
+                    RelocationType::Aarch64TlsLeLdst16TprelLo12 => (1, true),
+                    RelocationType::Aarch64TlsLeLdst16TprelLo12Nc => (1, false),
+                    RelocationType::Aarch64TlsLeLdst32TprelLo12 => (2, true),
+                    RelocationType::Aarch64TlsLeLdst32TprelLo12Nc => (2, false),
+                    RelocationType::Aarch64TlsLeLdst64TprelLo12 => (3, true),
+                    RelocationType::Aarch64TlsLeLdst64TprelLo12Nc => (3, false),
+                    RelocationType::Aarch64TlsLeLdst128TprelLo12 => (4, true),
+                    RelocationType::Aarch64TlsLeLdst128TprelLo12Nc => (4, false),
+                    _ => (0, false),
+                };
+                if checked {
+                    check_range(tprel, 0, 1 << 12)?;
+                }
+                let imm12 = ((tprel & 0xFFF) >> shift) as u32;
+                let new_instruction = (instruction() & !(0xFFF << 10)) | (imm12 << 10);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64TlsIeAdrGotTprelPage21 => {
+                let pages = check_range((page(g) - page(p)) >> 12, -(1 << 20), 1 << 20)?;
+                encode_adr(instruction(), pages).to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64TlsIeLd64GotTprelLo12Nc => {
+                let imm12 = ((g & 0xFFF) >> 3) as u32;
+                let new_instruction = (instruction() & !(0xFFF << 10)) | (imm12 << 10);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            // TLS descriptors are relaxed, as the dynamic loader is not asked to
+            // resolve them:
+            //   adrp x0, :tlsdesc:v            -> movz x0, #:tprel_g1:v | adrp x0, :gottprel:v
+            //   ldr x1, [x0, :tlsdesc_lo12:v]  -> movk x0, #:tprel_g0_nc:v | ldr x0, [x0, :gottprel_lo12:v]
+            //   add x0, x0, :tlsdesc_lo12:v    -> nop
+            //   blr x1                         -> nop
+            RelocationType::Aarch64TlsDescAdrPage21
+            | RelocationType::Aarch64TlsDescLd64Lo12
+            | RelocationType::Aarch64TlsDescAddLo12
+            | RelocationType::Aarch64TlsDescCall
+                if values.shared_output =>
+            {
+                return Err("TLS descriptors are not supported in shared objects".to_string());
+            }
+            RelocationType::Aarch64TlsDescAdrPage21 if tls_got => {
+                let pages = check_range((page(g) - page(p)) >> 12, -(1 << 20), 1 << 20)?;
+                encode_adr(0x90000000, pages).to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64TlsDescLd64Lo12 if tls_got => {
+                let imm12 = ((g & 0xFFF) >> 3) as u32;
+                (0xf9400000 | (imm12 << 10)).to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64TlsDescAdrPage21 => {
+                let tprel = check_range(values.tprel.clone()?, 0, 1 << 32)?;
+                let imm16 = ((tprel >> 16) & 0xFFFF) as u32;
+                (0xd2a00000 | (imm16 << 5)).to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64TlsDescLd64Lo12 => {
+                let imm16 = (values.tprel.clone()? & 0xFFFF) as u32;
+                (0xf2800000 | (imm16 << 5)).to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64TlsDescAddLo12 | RelocationType::Aarch64TlsDescCall => {
+                NOP.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64AddAbsLo12Nc
+            | RelocationType::Aarch64Ldst8AbsLo12Nc
+            | RelocationType::Aarch64Ldst16AbsLo12Nc
+            | RelocationType::Aarch64Ldst32AbsLo12Nc
+            | RelocationType::Aarch64Ldst64AbsLo12Nc
+            | RelocationType::Aarch64Ldst128AbsLo12Nc => {
+                // the immediate of loads and stores is scaled by the access size
+                let shift = match r#type {
+                    RelocationType::Aarch64Ldst16AbsLo12Nc => 1,
+                    RelocationType::Aarch64Ldst32AbsLo12Nc => 2,
+                    RelocationType::Aarch64Ldst64AbsLo12Nc => 3,
+                    RelocationType::Aarch64Ldst128AbsLo12Nc => 4,
+                    _ => 0,
+                };
+                let imm12 = (((s + a) & 0xFFF) >> shift) as u32;
+                // imm12: bits 10-21
+                let new_instruction = (instruction() & !(0xFFF << 10)) | (imm12 << 10);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64Jump26 | RelocationType::Aarch64Call26 => {
+                let target = values.thunk.map_or(s + a, |thunk| thunk as i64);
+                // B/BL: word offset in imm26 (bits 0-25), +/-128 MiB
+                let offset = check_range(target - p, -(1 << 27), 1 << 27)?;
+                let imm26 = ((offset >> 2) & 0x3FFFFFF) as u32;
+                let new_instruction = (instruction() & !0x3FFFFFF) | imm26;
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64Condbr19 | RelocationType::Aarch64LdPrelLo19 => {
+                // B.cond, CBZ/CBNZ and LDR (literal): word offset in imm19 (bits 5-23),
+                // +/-1 MiB
+                let offset = check_range(s + a - p, -(1 << 20), 1 << 20)?;
+                check_alignment(offset, 4)?;
+                let imm19 = ((offset >> 2) & 0x7FFFF) as u32;
+                let new_instruction = (instruction() & !(0x7FFFF << 5)) | (imm19 << 5);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            RelocationType::Aarch64Tstbr14 => {
+                // TBZ/TBNZ: word offset in imm14 (bits 5-18), +/-32 KiB
+                let offset = check_range(s + a - p, -(1 << 15), 1 << 15)?;
+                check_alignment(offset, 4)?;
+                let imm14 = ((offset >> 2) & 0x3FFF) as u32;
+                let new_instruction = (instruction() & !(0x3FFF << 5)) | (imm14 << 5);
+                new_instruction.to_le_bytes().to_vec()
+            }
+            _ => {
+                return Err(format!(
+                    "Relocation is not supported for {}",
+                    self.machine()
+                ));
+            }
+        };
+        Ok(bytes)
+    }
+
+    fn dynamic_relocations(&self) -> DynamicRelocationTypes {
+        DynamicRelocationTypes {
+            relative: RelocationType::Aarch64Relative,
+            absolute: RelocationType::Aarch64Abs64,
+            glob_dat: RelocationType::Aarch64GlobDat,
+            jump_slot: RelocationType::Aarch64JumpSlot,
+            tprel: RelocationType::Aarch64TlsTpRel64,
+            irelative: RelocationType::Aarch64IRelative,
+        }
+    }
+
+    fn is_absolute_relocation(&self, r#type: RelocationType) -> bool {
+        matches!(
+            r#type,
+            RelocationType::Aarch64Abs64
+                | RelocationType::Aarch64Abs32
+                | RelocationType::Aarch64Abs16
+                | RelocationType::Aarch64MovwUabsG0
+                | RelocationType::Aarch64MovwUabsG0Nc
+                | RelocationType::Aarch64MovwUabsG1
+                | RelocationType::Aarch64MovwUabsG1Nc
+                | RelocationType::Aarch64MovwUabsG2
+                | RelocationType::Aarch64MovwUabsG2Nc
+                | RelocationType::Aarch64MovwUabsG3
+                | RelocationType::Aarch64MovwSabsG0
+                | RelocationType::Aarch64MovwSabsG1
+                | RelocationType::Aarch64MovwSabsG2
+        )
+    }
+
+    fn is_got_relocation(&self, r#type: RelocationType) -> bool {
+        matches!(
+            r#type,
+            RelocationType::Aarch64AdrGotPage
+                | RelocationType::Aarch64Ld64GotLo12Nc
+                | RelocationType::Aarch64Ld64GotPageLo15
+        )
+    }
+
+    /// The ELF ABI numbers the TLS relocations from 512 to 573.
+    fn is_tls_relocation(&self, r#type: RelocationType) -> bool {
+        (512..=573).contains(&(r#type as u32))
+    }
+
+    fn uses_tls_got_slot(&self, r#type: RelocationType, initial_exec: bool) -> bool {
+        match r#type {
+            RelocationType::Aarch64TlsIeAdrGotTprelPage21
+            | RelocationType::Aarch64TlsIeLd64GotTprelLo12Nc => true,
+            RelocationType::Aarch64TlsDescAdrPage21 | RelocationType::Aarch64TlsDescLd64Lo12 => {
+                initial_exec
+            }
+            _ => false,
+        }
+    }
+
+    fn tls_layout(&self) -> TlsLayout {
+        TlsLayout::AfterTcb(TCB_SIZE)
+    }
+
+    /// `adrp` + `ldr` of the slot becomes `adrp` + `add` of the symbol.
+    fn can_relax_got_load(
+        &self,
+        r#type: RelocationType,
+        _addend: i64,
+        _data: &[u8],
+        _offset: usize,
+    ) -> bool {
+        matches!(
+            r#type,
+            RelocationType::Aarch64AdrGotPage | RelocationType::Aarch64Ld64GotLo12Nc
+        )
+    }
+
+    fn plt_sizes(&self) -> Option<(u64, u64)> {
+        Some((PLT_HEADER_SIZE, PLT_ENTRY_SIZE))
+    }
+
+    fn plt_header(&self, plt: u64, got_plt: u64) -> Vec<u8> {
+        // stp x16, x30, [sp, #-16]!; load .got.plt[2]; br x17; nop; nop; nop
+        let mut instructions = vec![0xa9bf7bf0];
+        instructions.extend(load_slot(plt + 4, got_plt + 2 * GOT_ENTRY_SIZE));
+        instructions.extend([0xd61f0220, NOP, NOP, NOP]);
+        to_bytes(&instructions)
+    }
+
+    fn plt_entry(&self, entry: u64, slot: u64) -> Vec<u8> {
+        let [adrp, ldr, add] = load_slot(entry, slot);
+        // br x17
+        to_bytes(&[adrp, ldr, add, 0xd61f0220])
+    }
+
+    fn is_branch(&self, r#type: RelocationType) -> bool {
+        matches!(
+            r#type,
+            RelocationType::Aarch64Call26
+                | RelocationType::Aarch64Jump26
+                | RelocationType::Aarch64Condbr19
+                | RelocationType::Aarch64Tstbr14
+        )
+    }
+
+    /// Conditional branches only reach +/-1 MiB or +/-32 KiB, and have no thunks.
+    fn uses_thunk(&self, r#type: RelocationType) -> bool {
+        matches!(
+            r#type,
+            RelocationType::Aarch64Call26 | RelocationType::Aarch64Jump26
+        )
+    }
+
+    fn in_branch_range(&self, offset: i64) -> bool {
+        (-BRANCH_RANGE..BRANCH_RANGE).contains(&offset)
+    }
+
+    fn thunk_size(&self) -> u64 {
+        THUNK_SIZE
+    }
+
+    /// `x16` is the intra-procedure-call scratch register, which the caller does not
+    /// expect to keep.
+    fn thunk_code(&self, addr: u64, target: i64) -> std::result::Result<Vec<u8>, String> {
+        let pages = check_range(
+            (page(target) - page(addr as i64)) >> 12,
+            -(1 << 20),
+            1 << 20,
+        )?;
+        let lo12 = (target & 0xFFF) as u32;
+        Ok(to_bytes(&[
+            // adrp x16, target
+            encode_adr(0x90000010, pages),
+            // add x16, x16, :lo12:target
+            0x91000210 | (lo12 << 10),
+            // br x16
+            0xd61f0200,
+        ]))
+    }
+}
+
+/// Returns the bytes of instructions.
+fn to_bytes(instructions: &[u32]) -> Vec<u8> {
+    instructions
+        .iter()
+        .flat_map(|instruction| instruction.to_le_bytes())
+        .collect()
+}
+
+/// Loads the slot at `slot` into x17, from an instruction at `pc`:
+/// `adrp x16, slot; ldr x17, [x16, :lo12:slot]; add x16, x16, :lo12:slot`
+fn load_slot(pc: u64, slot: u64) -> [u32; 3] {
+    let pages = (page(slot as i64) - page(pc as i64)) >> 12;
+    let lo12 = (slot & 0xFFF) as u32;
+    [
+        encode_adr(0x90000010, pages),
+        0xf9400211 | ((lo12 >> 3) << 10),
+        0x91000210 | (lo12 << 10),
+    ]
+}
+
+/// Returns the address of the 4 KiB page containing `addr`.
+fn page(addr: i64) -> i64 {
+    addr & !0xFFF
+}
+
+/// Encodes the 16 bits of a signed value starting at `imm` into a MOVZ, MOVN or
+/// MOVK instruction. MOVZ and MOVN are switched on the sign: a negative value is
+/// built by MOVN from its inverted bits.
+fn encode_signed_movw(instruction: u32, imm: i64) -> u32 {
+    // opc (bits 29-30): 00 for MOVN, 10 for MOVZ, 11 for MOVK
+    let mut instruction = instruction & !(0xFFFF << 5);
+    let mut imm = imm;
+    if instruction & (1 << 29) == 0 {
+        if imm < 0 {
+            imm = !imm;
+            instruction &= !(1 << 30);
+        } else {
+            instruction |= 1 << 30;
+        }
+    }
+    instruction | (((imm & 0xFFFF) as u32) << 5)
+}
+
+/// Encodes a 21-bit immediate into an ADR/ADRP instruction.
+fn encode_adr(instruction: u32, imm: i64) -> u32 {
+    // Keeps opcode and register portion of the ADR instruction
+    // ADR instruction format: 0bxxx10000 iiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiiii
+    // x: opcode, i: immbit, d: destination register
+    let opcode_rd = instruction & 0x9F00001F;
+
+    // Encoding of ADR instructions (based on the ARMv8 Architecture Reference Manual)
+    // immhi: upper 19 bits of immediate (bits 5-23)
+    // immlo: lower 2 bits of immediate (bits 29-30)
+    let immlo = ((imm & 0x3) as u32) << 29;
+    let immhi = (((imm >> 2) & 0x7FFFF) as u32) << 5;
+
+    opcode_rd | immlo | immhi
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_encode_thunk() {
+        let code = AArch64.thunk_code(0x400ffc, 0x10000123).unwrap();
+        let words: Vec<u32> = code
+            .chunks(4)
+            .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+            .collect();
+        // adrp x16, 0x10000000; add x16, x16, #0x123; br x16
+        assert_eq!(words, vec![0x9007e010, 0x91048e10, 0xd61f0200]);
+        assert!(AArch64.in_branch_range(-(1 << 27)));
+        assert!(!AArch64.in_branch_range(1 << 27));
+    }
+}
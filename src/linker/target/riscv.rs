@@ -0,0 +1,314 @@
+use crate::elf::header::Machine;
+use crate::elf::relocation::RelocationType;
+
+use super::super::relocation::{check_alignment, check_range};
+use super::super::tls::TlsLayout;
+use super::{
+    DYNAMIC_RELOCATION_ERROR, DynamicRelocationTypes, RelocationValues, SMALL_PAGE_SIZE, Target,
+};
+
+/// Encoding of the `nop` (`addi x0, x0, 0`)
+const NOP: u32 = 0x00000013;
+
+/// `EF_RISCV_RVC`: the code uses compressed instructions
+const EF_RISCV_RVC: u32 = 0x1;
+/// `EF_RISCV_FLOAT_ABI`: the floating-point calling convention
+const EF_RISCV_FLOAT_ABI: u32 = 0x6;
+/// `EF_RISCV_RVE`: the code uses the RV32E/RV64E base ISA, with 16 registers
+const EF_RISCV_RVE: u32 = 0x8;
+
+/// RISC-V 64 (RV64)
+pub(super) struct RiscV;
+
+impl Target for RiscV {
+    fn machine(&self) -> Machine {
+        Machine::RiscV
+    }
+
+    fn page_size(&self) -> u64 {
+        SMALL_PAGE_SIZE
+    }
+
+    fn nop(&self) -> &'static [u8] {
+        const BYTES: [u8; 4] = NOP.to_le_bytes();
+        &BYTES
+    }
+
+    fn relocation_width(&self, r#type: RelocationType) -> usize {
+        match r#type {
+            RelocationType::RiscvNone | RelocationType::RiscvRelax | RelocationType::RiscvAlign => {
+                0
+            }
+            RelocationType::Riscv64
+            | RelocationType::RiscvAdd64
+            | RelocationType::RiscvSub64
+            | RelocationType::RiscvCall
+            | RelocationType::RiscvCallPlt => 8,
+            RelocationType::RiscvAdd16
+            | RelocationType::RiscvSub16
+            | RelocationType::RiscvSet16 => 2,
+            RelocationType::RiscvAdd8
+            | RelocationType::RiscvSub8
+            | RelocationType::RiscvSet8
+            | RelocationType::RiscvSet6
+            | RelocationType::RiscvSub6 => 1,
+            _ => 4,
+        }
+    }
+
+    fn apply_relocation(
+        &self,
+        r#type: RelocationType,
+        values: &RelocationValues,
+        data: &mut [u8],
+        pos: usize,
+    ) -> std::result::Result<Vec<u8>, String> {
+        let &RelocationValues {
+            s,
+            a,
+            p,
+            relaxed,
+            paired,
+            ..
+        } = values;
+        let width = self.relocation_width(r#type);
+        let instruction = || u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+
+        let bytes = match r#type {
+            RelocationType::RiscvRelative
+            | RelocationType::RiscvCopy
+            | RelocationType::RiscvJumpSlot
+            | RelocationType::RiscvTlsTpRel64
+            | RelocationType::RiscvIRelative => {
+                return Err(DYNAMIC_RELOCATION_ERROR.to_string());
+            }
+            RelocationType::Riscv64 => (s + a).to_le_bytes().to_vec(),
+            RelocationType::Riscv32 => {
+                let value = check_range(s + a, -(1 << 31), 1 << 32)?;
+                (value as u32).to_le_bytes().to_vec()
+            }
+            RelocationType::Riscv32Pcrel => {
+                let value = check_range(s + a - p, -(1 << 31), 1 << 31)?;
+                (value as u32).to_le_bytes().to_vec()
+            }
+            RelocationType::RiscvHi20 | RelocationType::RiscvPcrelHi20 => {
+                // LUI/AUIPC: the upper 20 bits, which the 12 bits added later sign-extend
+                let value = if r#type == RelocationType::RiscvHi20 {
+                    s + a
+                } else {
+                    s + a - p
+                };
+                check_range(value, -(1 << 31) - 0x800, (1 << 31) - 0x800)?;
+                encode_u_type(instruction(), value).to_le_bytes().to_vec()
+            }
+            RelocationType::RiscvLo12I => {
+                encode_i_type(instruction(), s + a).to_le_bytes().to_vec()
+            }
+            RelocationType::RiscvLo12S => {
+                encode_s_type(instruction(), s + a).to_le_bytes().to_vec()
+            }
+            RelocationType::RiscvPcrelLo12I => {
+                encode_i_type(instruction(), paired).to_le_bytes().to_vec()
+            }
+            RelocationType::RiscvPcrelLo12S => {
+                encode_s_type(instruction(), paired).to_le_bytes().to_vec()
+            }
+            // relaxation already replaced the auipc + jalr with a jal
+            RelocationType::RiscvCall | RelocationType::RiscvCallPlt if relaxed => {
+                let offset = check_range(s + a - p, -(1 << 20), 1 << 20)?;
+                encode_j_type(instruction(), offset).to_le_bytes().to_vec()
+            }
+            // auipc + jalr
+            RelocationType::RiscvCall | RelocationType::RiscvCallPlt => {
+                let offset = check_range(s + a - p, -(1 << 31) - 0x800, (1 << 31) - 0x800)?;
+                let jalr = u32::from_le_bytes(data[pos + 4..pos + 8].try_into().unwrap());
+                [
+                    encode_u_type(instruction(), offset),
+                    encode_i_type(jalr, offset),
+                ]
+                .iter()
+                .flat_map(|i| i.to_le_bytes())
+                .collect()
+            }
+            RelocationType::RiscvBranch => {
+                // conditional branches: +/-4 KiB
+                let offset = check_range(s + a - p, -(1 << 12), 1 << 12)?;
+                check_alignment(offset, 2)?;
+                encode_b_type(instruction(), offset).to_le_bytes().to_vec()
+            }
+            RelocationType::RiscvJal => {
+                let offset = check_range(s + a - p, -(1 << 20), 1 << 20)?;
+                check_alignment(offset, 2)?;
+                encode_j_type(instruction(), offset).to_le_bytes().to_vec()
+            }
+            // differences between symbols, such as `.word end - start`, which
+            // relaxation may change, are built from an ADD and a SUB relocation
+            RelocationType::RiscvAdd8
+            | RelocationType::RiscvAdd16
+            | RelocationType::RiscvAdd32
+            | RelocationType::RiscvAdd64 => read_value(&data[pos..pos + width])
+                .wrapping_add((s + a) as u64)
+                .to_le_bytes()[..width]
+                .to_vec(),
+            RelocationType::RiscvSub8
+            | RelocationType::RiscvSub16
+            | RelocationType::RiscvSub32
+            | RelocationType::RiscvSub64 => read_value(&data[pos..pos + width])
+                .wrapping_sub((s + a) as u64)
+                .to_le_bytes()[..width]
+                .to_vec(),
+            RelocationType::RiscvSet8 | RelocationType::RiscvSet16 | RelocationType::RiscvSet32 => {
+                (s + a).to_le_bytes()[..width].to_vec()
+            }
+            // the low 6 bits of a byte, as in DW_CFA_advance_loc
+            RelocationType::RiscvSet6 => vec![(data[pos] & 0xC0) | ((s + a) as u8 & 0x3F)],
+            RelocationType::RiscvSub6 => {
+                vec![(data[pos] & 0xC0) | (data[pos].wrapping_sub((s + a) as u8) & 0x3F)]
+            }
+            _ => {
+                return Err(format!(
+                    "Relocation is not supported for {}",
+                    self.machine()
+                ));
+            }
+        };
+        Ok(bytes)
+    }
+
+    /// A relaxed call is a single `jal`.
+    fn relaxed_width(&self, r#type: RelocationType) -> usize {
+        match r#type {
+            RelocationType::RiscvCall | RelocationType::RiscvCallPlt => 4,
+            _ => self.relocation_width(r#type),
+        }
+    }
+
+    fn dynamic_relocations(&self) -> DynamicRelocationTypes {
+        DynamicRelocationTypes {
+            relative: RelocationType::RiscvRelative,
+            absolute: RelocationType::Riscv64,
+            glob_dat: RelocationType::Riscv64,
+            jump_slot: RelocationType::RiscvJumpSlot,
+            tprel: RelocationType::RiscvTlsTpRel64,
+            irelative: RelocationType::RiscvIRelative,
+        }
+    }
+
+    fn is_absolute_relocation(&self, r#type: RelocationType) -> bool {
+        matches!(
+            r#type,
+            RelocationType::Riscv64
+                | RelocationType::Riscv32
+                | RelocationType::RiscvHi20
+                | RelocationType::RiscvLo12I
+                | RelocationType::RiscvLo12S
+        )
+    }
+
+    fn is_got_relocation(&self, _type: RelocationType) -> bool {
+        false
+    }
+
+    /// The thread pointer points at the start of the TLS block.
+    fn tls_layout(&self) -> TlsLayout {
+        TlsLayout::AfterTcb(0)
+    }
+
+    /// PCREL_LO12 relocations refer to the `auipc` of their PCREL_HI20 relocation,
+    /// and take the low bits of the value computed there.
+    fn paired_relocation(&self, r#type: RelocationType) -> Option<RelocationType> {
+        match r#type {
+            RelocationType::RiscvPcrelLo12I | RelocationType::RiscvPcrelLo12S => {
+                Some(RelocationType::RiscvPcrelHi20)
+            }
+            _ => None,
+        }
+    }
+
+    fn is_branch(&self, r#type: RelocationType) -> bool {
+        matches!(
+            r#type,
+            RelocationType::RiscvCall
+                | RelocationType::RiscvCallPlt
+                | RelocationType::RiscvJal
+                | RelocationType::RiscvBranch
+        )
+    }
+}
+
+/// Reads a little-endian value.
+fn read_value(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| (value << 8) | byte as u64)
+}
+
+/// Encodes the upper 20 bits of `value` into a U-type instruction (`lui`, `auipc`),
+/// rounded so that adding the sign-extended lower 12 bits gives `value`.
+fn encode_u_type(instruction: u32, value: i64) -> u32 {
+    (instruction & 0xFFF) | ((value + 0x800) as u32 & 0xFFFFF000)
+}
+
+/// Encodes the lower 12 bits of `value` into an I-type instruction (bits 20-31).
+fn encode_i_type(instruction: u32, value: i64) -> u32 {
+    (instruction & 0xFFFFF) | (((value & 0xFFF) as u32) << 20)
+}
+
+/// Encodes the lower 12 bits of `value` into an S-type instruction (stores):
+/// imm[11:5] in bits 25-31, imm[4:0] in bits 7-11.
+fn encode_s_type(instruction: u32, value: i64) -> u32 {
+    let imm = (value & 0xFFF) as u32;
+    (instruction & 0x1FFF07F) | ((imm >> 5) << 25) | ((imm & 0x1F) << 7)
+}
+
+/// Encodes a branch offset into a B-type instruction:
+/// imm[12|10:5] in bits 31 and 25-30, imm[4:1|11] in bits 8-11 and 7.
+fn encode_b_type(instruction: u32, offset: i64) -> u32 {
+    let imm = offset as u32;
+    (instruction & 0x1FFF07F)
+        | (((imm >> 12) & 0x1) << 31)
+        | (((imm >> 5) & 0x3F) << 25)
+        | (((imm >> 1) & 0xF) << 8)
+        | (((imm >> 11) & 0x1) << 7)
+}
+
+/// Encodes a jump offset into a J-type instruction (`jal`):
+/// imm[20|10:1|11|19:12] in bits 31, 21-30, 20 and 12-19.
+fn encode_j_type(instruction: u32, offset: i64) -> u32 {
+    let imm = offset as u32;
+    (instruction & 0xFFF)
+        | (((imm >> 20) & 0x1) << 31)
+        | (((imm >> 1) & 0x3FF) << 21)
+        | (((imm >> 11) & 0x1) << 20)
+        | (((imm >> 12) & 0xFF) << 12)
+}
+
+/// Combines the flags (`e_flags`) of an input into those of the output. All inputs
+/// must use the same floating-point ABI and base ISA, and the output uses compressed
+/// instructions if any input does.
+pub(super) fn merge_flags(output: u32, input: u32) -> Result<u32, String> {
+    let abi = EF_RISCV_FLOAT_ABI | EF_RISCV_RVE;
+    if output & abi != input & abi {
+        return Err(format!(
+            "uses the {} ABI, but the other inputs use the {} ABI",
+            describe_abi(input),
+            describe_abi(output)
+        ));
+    }
+    Ok(output | (input & EF_RISCV_RVC))
+}
+
+fn describe_abi(flags: u32) -> String {
+    let float = match flags & EF_RISCV_FLOAT_ABI {
+        0x0 => "soft-float",
+        0x2 => "single-float",
+        0x4 => "double-float",
+        _ => "quad-float",
+    };
+    if flags & EF_RISCV_RVE != 0 {
+        format!("{} RVE", float)
+    } else {
+        float.to_string()
+    }
+}
@@ -0,0 +1,193 @@
+use crate::elf::header::Machine;
+use crate::elf::relocation::RelocationType;
+
+use super::super::relocation::check_range;
+use super::super::tls::TlsLayout;
+use super::{
+    DYNAMIC_RELOCATION_ERROR, DynamicRelocationTypes, RelocationValues, SMALL_PAGE_SIZE, Target,
+};
+
+/// x86-64 (AMD64)
+pub(super) struct X86_64;
+
+impl Target for X86_64 {
+    fn machine(&self) -> Machine {
+        Machine::X86_64
+    }
+
+    fn page_size(&self) -> u64 {
+        SMALL_PAGE_SIZE
+    }
+
+    fn nop(&self) -> &'static [u8] {
+        &[0x90]
+    }
+
+    fn relocation_width(&self, r#type: RelocationType) -> usize {
+        match r#type {
+            RelocationType::X86_64None => 0,
+            RelocationType::X86_64_64 => 8,
+            _ => 4,
+        }
+    }
+
+    fn apply_relocation(
+        &self,
+        r#type: RelocationType,
+        values: &RelocationValues,
+        data: &mut [u8],
+        pos: usize,
+    ) -> std::result::Result<Vec<u8>, String> {
+        let &RelocationValues {
+            s,
+            a,
+            p,
+            g,
+            relaxed,
+            ..
+        } = values;
+
+        let bytes = match r#type {
+            RelocationType::X86_64Copy
+            | RelocationType::X86_64GlobDat
+            | RelocationType::X86_64JumpSlot
+            | RelocationType::X86_64Relative
+            | RelocationType::X86_64DtpMod64
+            | RelocationType::X86_64DtpOff64
+            | RelocationType::X86_64TpOff64
+            | RelocationType::X86_64IRelative => {
+                return Err(DYNAMIC_RELOCATION_ERROR.to_string());
+            }
+            RelocationType::X86_64_64 => (s + a).to_le_bytes().to_vec(),
+            RelocationType::X86_64_32 => {
+                // zero-extended by the instruction
+                let value = check_range(s + a, 0, 1 << 32)?;
+                (value as u32).to_le_bytes().to_vec()
+            }
+            RelocationType::X86_64_32S => {
+                // sign-extended by the instruction
+                let value = check_range(s + a, -(1 << 31), 1 << 31)?;
+                (value as u32).to_le_bytes().to_vec()
+            }
+            RelocationType::X86_64Pc32 | RelocationType::X86_64Plt32 => {
+                let value = check_range(s + a - p, -(1 << 31), 1 << 31)?;
+                (value as u32).to_le_bytes().to_vec()
+            }
+            // GOT loads of symbols with a fixed address use the address directly:
+            //   mov foo@GOTPCREL(%rip), %reg  -> lea foo(%rip), %reg
+            //   call *foo@GOTPCREL(%rip)      -> addr32 call foo
+            //   jmp *foo@GOTPCREL(%rip)       -> jmp foo; nop
+            RelocationType::X86_64GotPcRelX | RelocationType::X86_64RexGotPcRelX if relaxed => {
+                let value = check_range(s + a - p, -(1 << 31), 1 << 31)?;
+                match got_relaxation(r#type, &data[..pos], pos) {
+                    Some(GotRelaxation::Lea) => {
+                        data[pos - 2] = 0x8d;
+                        (value as u32).to_le_bytes().to_vec()
+                    }
+                    Some(GotRelaxation::Call) => {
+                        data[pos - 2..pos].copy_from_slice(&[0x67, 0xe8]);
+                        (value as u32).to_le_bytes().to_vec()
+                    }
+                    // the displacement starts one byte earlier, and so does the next instruction
+                    _ => {
+                        let displacement = ((value + 1) as u32).to_le_bytes();
+                        data[pos - 2..pos].copy_from_slice(&[0xe9, displacement[0]]);
+                        vec![displacement[1], displacement[2], displacement[3], 0x90]
+                    }
+                }
+            }
+            RelocationType::X86_64GotPcRel
+            | RelocationType::X86_64GotPcRelX
+            | RelocationType::X86_64RexGotPcRelX => {
+                let value = check_range(g + a - p, -(1 << 31), 1 << 31)?;
+                (value as u32).to_le_bytes().to_vec()
+            }
+            _ => {
+                return Err(format!(
+                    "Relocation is not supported for {}",
+                    self.machine()
+                ));
+            }
+        };
+        Ok(bytes)
+    }
+
+    fn dynamic_relocations(&self) -> DynamicRelocationTypes {
+        DynamicRelocationTypes {
+            relative: RelocationType::X86_64Relative,
+            absolute: RelocationType::X86_64_64,
+            glob_dat: RelocationType::X86_64GlobDat,
+            jump_slot: RelocationType::X86_64JumpSlot,
+            tprel: RelocationType::X86_64TpOff64,
+            irelative: RelocationType::X86_64IRelative,
+        }
+    }
+
+    fn is_absolute_relocation(&self, r#type: RelocationType) -> bool {
+        matches!(
+            r#type,
+            RelocationType::X86_64_64 | RelocationType::X86_64_32 | RelocationType::X86_64_32S
+        )
+    }
+
+    fn is_got_relocation(&self, r#type: RelocationType) -> bool {
+        matches!(
+            r#type,
+            RelocationType::X86_64GotPcRel
+                | RelocationType::X86_64GotPcRelX
+                | RelocationType::X86_64RexGotPcRelX
+        )
+    }
+
+    fn tls_layout(&self) -> TlsLayout {
+        TlsLayout::BeforeTp
+    }
+
+    /// Only `R_X86_64_GOTPCRELX` and `R_X86_64_REX_GOTPCRELX` relocations with the
+    /// usual addend of -4 mark instructions that load the whole GOT slot.
+    fn can_relax_got_load(
+        &self,
+        r#type: RelocationType,
+        addend: i64,
+        data: &[u8],
+        offset: usize,
+    ) -> bool {
+        addend == -4 && got_relaxation(r#type, data, offset).is_some()
+    }
+
+    /// The addend of GOT loads is the distance from the end of the instruction to
+    /// the displacement, so it applies to the place.
+    fn got_slot_includes_addend(&self) -> bool {
+        false
+    }
+
+    fn is_branch(&self, r#type: RelocationType) -> bool {
+        r#type == RelocationType::X86_64Plt32
+    }
+}
+
+/// How a relaxed GOT load is rewritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GotRelaxation {
+    /// `mov` of the slot becomes `lea` of the symbol.
+    Lea,
+    /// Indirect call through the slot becomes a direct call.
+    Call,
+    /// Indirect jump through the slot becomes a direct jump.
+    Jump,
+}
+
+/// Returns how the instruction of a GOT load, whose displacement is at `offset` in
+/// `data`, can be rewritten to use the address of the symbol, if it can be.
+fn got_relaxation(r#type: RelocationType, data: &[u8], offset: usize) -> Option<GotRelaxation> {
+    // opcode and ModRM byte of the instruction, which the displacement follows
+    let (opcode, modrm) = (*data.get(offset.checked_sub(2)?)?, *data.get(offset - 1)?);
+    match (r#type, opcode, modrm) {
+        (RelocationType::X86_64GotPcRelX | RelocationType::X86_64RexGotPcRelX, 0x8b, _) => {
+            Some(GotRelaxation::Lea)
+        }
+        (RelocationType::X86_64GotPcRelX, 0xff, 0x15) => Some(GotRelaxation::Call),
+        (RelocationType::X86_64GotPcRelX, 0xff, 0x25) => Some(GotRelaxation::Jump),
+        _ => None,
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::elf::symbol;
+use crate::error::{LinkerError, Result};
+
+use super::Linker;
+use super::dynamic::GotEntry;
+use super::gc::InputSectionId;
+use super::output::{InputSection, ResolvedSymbol, Section};
+use super::section::{InputSectionMap, align};
+
+/// Object index of the input sections holding thunks. Their section index is the
+/// index of the [`ThunkArea`].
+pub(super) const THUNK_OBJECT: usize = usize::MAX - 3;
+
+/// What a thunk jumps to: the symbol referenced by the branch and its addend.
+pub(super) type ThunkTarget = (GotEntry, i64);
+
+/// The thunks placed right after an input section, for the branches of that
+/// section that do not reach their target.
+#[derive(Debug)]
+pub(super) struct ThunkArea {
+    /// The input section whose branches go through the thunks.
+    pub caller: InputSectionId,
+    /// Targets of the thunks, in placement order.
+    pub targets: Vec<ThunkTarget>,
+    /// Bytes reserved in the output section, padded to its alignment.
+    size: u64,
+}
+
+/// Long-branch thunks (veneers) inserted by [`Linker::insert_thunks`].
+#[derive(Debug, Default)]
+pub(super) struct Thunks {
+    pub areas: Vec<ThunkArea>,
+    /// Size of a thunk of the target.
+    pub thunk_size: u64,
+    /// Index of the area placed after each calling input section.
+    by_caller: HashMap<InputSectionId, usize>,
+}
+
+impl Thunks {
+    pub(super) fn new(thunk_size: u64) -> Self {
+        Thunks {
+            thunk_size,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the offset of the thunk for the branches of `caller` to `target`
+    /// within the thunk area input section, and the index of that area.
+    fn find(&self, caller: InputSectionId, target: &ThunkTarget) -> Option<(u16, u64)> {
+        let area_idx = *self.by_caller.get(&caller)?;
+        let idx = self.areas[area_idx]
+            .targets
+            .iter()
+            .position(|t| t == target)?;
+        Some((area_idx as u16, idx as u64 * self.thunk_size))
+    }
+
+    /// Returns the address of the thunk for the branches of `caller` to `target`.
+    pub(super) fn address(
+        &self,
+        output_sections: &[Section<'static>],
+        input_sections: &InputSectionMap,
+        caller: InputSectionId,
+        target: &ThunkTarget,
+    ) -> Option<u64> {
+        let (area_idx, offset) = self.find(caller, target)?;
+        input_sections.address(output_sections, THUNK_OBJECT, area_idx, offset)
+    }
+
+    /// Adds a thunk for the branches of `caller` to `target`, growing the area after
+    /// `caller` and moving the input sections that follow it.
+    fn add(
+        &mut self,
+        section: &mut Section<'static>,
+        caller: InputSectionId,
+        caller_size: u64,
+        target: ThunkTarget,
+    ) -> Result<()> {
+        let area_idx = match self.by_caller.get(&caller) {
+            Some(&area_idx) => area_idx,
+            None => {
+                let area_idx =
+                    u16::try_from(self.areas.len()).map_err(|_| LinkerError::Generic {
+                        message: "Too many input sections need thunks".to_string(),
+                        context: Some("inserting thunks".to_string()),
+                    })?;
+                let offset = input_offset(section, caller.0, caller.1) + caller_size;
+                section.inputs.push(InputSection {
+                    object_index: THUNK_OBJECT,
+                    section_index: area_idx,
+                    offset,
+                    folded: false,
+                    pieces: vec![],
+                });
+                self.areas.push(ThunkArea {
+                    caller,
+                    targets: vec![],
+                    size: 0,
+                });
+                self.by_caller.insert(caller, area_idx as usize);
+                area_idx as usize
+            }
+        };
+
+        let area = &mut self.areas[area_idx];
+        area.targets.push(target);
+        let needed = area.targets.len() as u64 * self.thunk_size;
+        if needed > area.size {
+            // the sections that follow keep their alignment
+            let grow = align(needed - area.size, section.align.max(4));
+            let at = input_offset(section, THUNK_OBJECT, area_idx as u16) + area.size;
+            insert_space(section, at, grow, area_idx as u16);
+            area.size += grow;
+        }
+        Ok(())
+    }
+}
+
+impl Linker {
+    /// Adds thunks for the branches (`CALL26`/`JUMP26`) that do not reach their
+    /// target in the current layout, and writes the code of the existing thunks.
+    ///
+    /// Thunks are placed right after the input section of the branch, so adding
+    /// them moves the sections that follow. The layout is repeated until this
+    /// returns false, when every branch reaches its target directly or through a thunk.
+    pub(super) fn insert_thunks(
+        &self,
+        output_sections: &mut [Section<'static>],
+        resolved_symbols: &HashMap<String, ResolvedSymbol>,
+        thunks: &mut Thunks,
+    ) -> Result<bool> {
+        let target = self.target();
+        let input_sections = InputSectionMap::new(output_sections);
+        let mut missing = Vec::new();
+        let mut seen = HashSet::new();
+
+        for (obj_idx, obj) in self.objects.iter().enumerate() {
+            for relocation_section in &obj.relocations {
+                let caller = (obj_idx, relocation_section.target_section);
+                let Some((out_idx, input)) = input_sections
+                    .input(caller.0, caller.1)
+                    .filter(|(_, input)| !input.folded)
+                else {
+                    continue;
+                };
+                let branches = relocation_section
+                    .entries
+                    .iter()
+                    .filter(|r| target.uses_thunk(r.info.r#type));
+                for reloc in branches {
+                    let Some(place) = input.placed_offset(reloc.offset) else {
+                        continue;
+                    };
+                    let p = (output_sections[out_idx].addr + place) as i64;
+                    let s = self.relocation_symbol_address(
+                        obj_idx,
+                        reloc,
+                        output_sections,
+                        &input_sections,
+                        resolved_symbols,
+                    )? as i64;
+                    let thunk_target = (self.got_entry(obj_idx, reloc), reloc.addend);
+
+                    if let Some((area_idx, offset)) = thunks.find(caller, &thunk_target) {
+                        let Some((thunk_out_idx, area)) =
+                            input_sections.input(THUNK_OBJECT, area_idx)
+                        else {
+                            continue;
+                        };
+                        let thunk_section = &mut output_sections[thunk_out_idx];
+                        let pos = area.offset + offset;
+                        let code = target
+                            .thunk_code(thunk_section.addr + pos, s + reloc.addend)
+                            .map_err(|message| LinkerError::Generic {
+                                message,
+                                context: Some("writing thunks".to_string()),
+                            })?;
+                        thunk_section.data.to_mut()[pos as usize..pos as usize + code.len()]
+                            .copy_from_slice(&code);
+                    } else if !target.in_branch_range(s + reloc.addend - p)
+                        && seen.insert((caller, thunk_target.clone()))
+                    {
+                        missing.push((out_idx, caller, thunk_target));
+                    }
+                }
+            }
+        }
+
+        for (out_idx, caller, target) in &missing {
+            let caller_size = self.objects[caller.0].section_headers[caller.1 as usize].size;
+            thunks.add(
+                &mut output_sections[*out_idx],
+                *caller,
+                caller_size,
+                target.clone(),
+            )?;
+        }
+        Ok(!missing.is_empty())
+    }
+
+    /// Returns a readable name for the target of a thunk, as in
+    /// `__AArch64ADRPThunk_<name>`.
+    pub(super) fn thunk_target_name(&self, (entry, addend): &ThunkTarget) -> String {
+        let name = match entry {
+            GotEntry::Global(name) => name.clone(),
+            GotEntry::Local(obj_idx, symbol_idx) => {
+                let obj = &self.objects[*obj_idx];
+                match obj.symbols.get(*symbol_idx as usize) {
+                    Some(s) if s.info.r#type == symbol::Type::Section => obj
+                        .section_headers
+                        .get(s.shndx as usize)
+                        .map(|h| h.name.clone())
+                        .unwrap_or_default(),
+                    Some(s) => s.name.clone(),
+                    None => String::new(),
+                }
+            }
+        };
+        match addend {
+            0 => name,
+            _ => format!("{}+{:#x}", name, addend),
+        }
+    }
+}
+
+/// Returns the offset of an input section within its output section.
+fn input_offset(section: &Section<'static>, object_index: usize, section_index: u16) -> u64 {
+    section
+        .inputs
+        .iter()
+        .find(|input| input.object_index == object_index && input.section_index == section_index)
+        .map_or(0, |input| input.offset)
+}
+
+/// Inserts `size` bytes at `at` in the output section, moving the input sections
+/// from there on, except the thunk area being grown.
+fn insert_space(section: &mut Section<'static>, at: u64, size: u64, area_idx: u16) {
+    for input in section.inputs.iter_mut() {
+        let is_area = input.object_index == THUNK_OBJECT && input.section_index == area_idx;
+        if input.offset >= at && !is_area {
+            input.offset += size;
+        }
+    }
+    section.size += size;
+    let data = section.data.to_mut();
+    data.splice(
+        at as usize..at as usize,
+        std::iter::repeat_n(0, size as usize),
+    );
+}
@@ -0,0 +1,77 @@
+use crate::elf::relocation::RelocationType;
+use crate::elf::section::{SectionFlag, SectionType};
+
+use super::Linker;
+use super::output::Section;
+use super::section::align;
+
+/// Where the thread pointer points relative to the TLS block of the executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum TlsLayout {
+    /// Variant 1: the thread pointer points to a thread control block of this size,
+    /// which the TLS block follows.
+    AfterTcb(u64),
+    /// Variant 2: the TLS block ends at the thread pointer.
+    BeforeTp,
+}
+
+/// The TLS template described by `PT_TLS`: the initialized data of `.tdata`
+/// followed by the zero-initialized data of `.tbss`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct TlsSegment {
+    pub offset: u64,
+    pub addr: u64,
+    pub filesz: u64,
+    pub memsz: u64,
+    pub align: u64,
+}
+
+impl TlsSegment {
+    /// Returns the offset of `addr`, an address in the TLS template, from the
+    /// thread pointer.
+    pub fn tp_offset(&self, addr: i64, layout: TlsLayout) -> i64 {
+        let offset = addr.wrapping_sub(self.addr as i64);
+        match layout {
+            TlsLayout::AfterTcb(tcb_size) => offset + align(tcb_size, self.align) as i64,
+            TlsLayout::BeforeTp => offset - align(self.memsz, self.align) as i64,
+        }
+    }
+}
+
+/// Returns the TLS template of the output, if it has TLS sections.
+pub(super) fn tls_segment(output_sections: &[Section<'static>]) -> Option<TlsSegment> {
+    let sections: Vec<_> = output_sections
+        .iter()
+        .filter(|s| s.flags.contains(&SectionFlag::Alloc) && s.flags.contains(&SectionFlag::Tls))
+        .collect();
+    let first = sections.first()?;
+    let size = |with_bss: bool| {
+        sections
+            .iter()
+            .filter(|s| with_bss || s.r#type != SectionType::NoBits)
+            .map(|s| s.addr + s.size - first.addr)
+            .max()
+            .unwrap_or(0)
+    };
+
+    Some(TlsSegment {
+        offset: first.offset,
+        addr: first.addr,
+        filesz: size(false),
+        memsz: size(true),
+        align: sections.iter().map(|s| s.align).max().unwrap_or(1),
+    })
+}
+
+impl Linker {
+    /// Returns true if a TLS relocation refers to a GOT slot that holds the offset of
+    /// the variable from the thread pointer.
+    ///
+    /// Initial-exec accesses use such a slot. So do TLS descriptors for variables of
+    /// shared objects in an executable, which are relaxed to initial-exec; the other
+    /// descriptors are relaxed to local-exec.
+    pub(super) fn uses_tls_got_slot(&self, r#type: RelocationType, shared: bool) -> bool {
+        self.target()
+            .uses_tls_got_slot(r#type, shared && !self.options.shared)
+    }
+}
@@ -0,0 +1,221 @@
+use crate::error::{LinkerError, Result};
+
+/// The symbols to export from a shared object, given with `--version-script`.
+///
+/// Only the `global:` and `local:` lists of the version nodes are used; the symbols
+/// are not versioned.
+///
+/// ```text
+/// script := node*
+/// node   := name? '{' (('global' | 'local') ':' | pattern ';')* '}' name? ';'
+/// ```
+///
+/// Patterns may use the `*` and `?` wildcards.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VersionScript {
+    pub global: Vec<String>,
+    pub local: Vec<String>,
+}
+
+impl VersionScript {
+    /// Returns true if the script exports `name`.
+    ///
+    /// Exact names take precedence over patterns, and symbols that match neither
+    /// list are exported.
+    pub fn is_exported(&self, name: &str) -> bool {
+        let exact = |patterns: &[String]| patterns.iter().any(|p| p == name);
+        let matches = |patterns: &[String]| patterns.iter().any(|p| glob_match(p, name));
+
+        if exact(&self.global) {
+            true
+        } else if exact(&self.local) {
+            false
+        } else {
+            matches(&self.global) || !matches(&self.local)
+        }
+    }
+}
+
+impl std::str::FromStr for VersionScript {
+    type Err = LinkerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = tokenize(s)?;
+        let mut tokens = tokens.iter().map(String::as_str).peekable();
+        let mut script = VersionScript::default();
+
+        while let Some(token) = tokens.next() {
+            // anonymous nodes start with '{', others with their version name
+            if token != "{" && tokens.next() != Some("{") {
+                return Err(LinkerError::invalid_version_script(format!(
+                    "expected '{{' after '{}'",
+                    token
+                )));
+            }
+
+            let mut list = &mut script.global;
+            loop {
+                match tokens.next() {
+                    Some("}") => break,
+                    Some("extern") => {
+                        return Err(LinkerError::invalid_version_script(
+                            "extern blocks are not supported",
+                        ));
+                    }
+                    Some(name @ ("global" | "local")) if tokens.peek() == Some(&":") => {
+                        tokens.next();
+                        list = if name == "global" {
+                            &mut script.global
+                        } else {
+                            &mut script.local
+                        };
+                    }
+                    Some(pattern) if tokens.next() == Some(";") => list.push(pattern.to_string()),
+                    Some(pattern) => {
+                        return Err(LinkerError::invalid_version_script(format!(
+                            "expected ';' after '{}'",
+                            pattern
+                        )));
+                    }
+                    None => return Err(LinkerError::invalid_version_script("missing '}'")),
+                }
+            }
+
+            // the names of the nodes this one depends on
+            while tokens.next_if(|&token| token != ";").is_some() {}
+            if tokens.next() != Some(";") {
+                return Err(LinkerError::invalid_version_script("missing ';' after '}'"));
+            }
+        }
+
+        Ok(script)
+    }
+}
+
+/// Splits a version script into words and the punctuation `{`, `}`, `;` and `:`,
+/// dropping comments.
+fn tokenize(s: &str) -> Result<Vec<String>> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if prev == '*' => break,
+                        Some(c) => prev = c,
+                        None => {
+                            return Err(LinkerError::invalid_version_script(
+                                "unterminated comment",
+                            ));
+                        }
+                    }
+                }
+            }
+            '{' | '}' | ';' | ':' => tokens.push(c.to_string()),
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) =
+                    chars.next_if(|c| !c.is_whitespace() && !matches!(c, '{' | '}' | ';' | ':'))
+                {
+                    word.push(c);
+                }
+                tokens.push(word);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Matches `name` against a pattern where `*` matches any sequence of characters
+/// and `?` any single character.
+///
+/// On a mismatch after a `*`, the match resumes right after that `*`, with the
+/// `*` covering one more character of `name`. Only the last `*` matters, which
+/// keeps the match linear in the length of `name` for each `*`.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // position of the last `*` in the pattern, and of the name where it matched
+    let mut star = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    star = Some((star_p, star_n + 1));
+                    p = star_p + 1;
+                    n = star_n + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_parse_version_script() {
+        let script: VersionScript = "
+            # exported API
+            LIBGREET_1.0 {
+                global: greet; greet_*;
+                local: *;
+            };
+            LIBGREET_2.0 { global: farewell; /* added later */ } LIBGREET_1.0;
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(
+            script,
+            VersionScript {
+                global: vec![
+                    "greet".to_string(),
+                    "greet_*".to_string(),
+                    "farewell".to_string()
+                ],
+                local: vec!["*".to_string()],
+            }
+        );
+
+        assert!(script.is_exported("greet"));
+        assert!(script.is_exported("greet_twice"));
+        assert!(!script.is_exported("helper"));
+
+        let err = "{ global: greet }".parse::<VersionScript>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid version script: expected ';' after 'greet'"
+        );
+    }
+
+    #[test]
+    fn should_match_wildcards() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("g?eet*", "greeting"));
+        assert!(!glob_match("g?eet", "greeting"));
+        assert!(glob_match("*_v*_*", "greet_v2_internal"));
+        assert!(!glob_match("*a*a*a*b", &"a".repeat(64)));
+    }
+}
@@ -1,12 +1,15 @@
 use std::collections::HashMap;
 use std::io::{SeekFrom, Write};
 
+use crate::elf::header::{Class, Encoding};
+use crate::elf::section::SectionType;
 use crate::elf::{header, program_header, segument};
 use crate::error::{LinkerError, Result};
 
 use super::Linker;
 use super::output::{ResolvedSymbol, Section};
-use super::section::{BASE_ADDR, align};
+use super::section::{align, segment_flags, segments};
+use super::tls::tls_segment;
 
 #[derive(Debug, Default)]
 struct SectionHeaderEntry {
@@ -23,18 +26,18 @@ struct SectionHeaderEntry {
 }
 
 impl SectionHeaderEntry {
-    fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+    fn write<W: Write>(&self, writer: &mut W, encoding: Encoding) -> Result<()> {
         let bytes = [
-            self.name.to_le_bytes().as_slice(),
-            self.sh_type.to_le_bytes().as_slice(),
-            self.sh_flags.to_le_bytes().as_slice(),
-            self.sh_addr.to_le_bytes().as_slice(),
-            self.sh_offset.to_le_bytes().as_slice(),
-            self.sh_size.to_le_bytes().as_slice(),
-            self.sh_link.to_le_bytes().as_slice(),
-            self.sh_info.to_le_bytes().as_slice(),
-            self.sh_addralign.to_le_bytes().as_slice(),
-            self.sh_entsize.to_le_bytes().as_slice(),
+            encoding.u32_bytes(self.name).as_slice(),
+            encoding.u32_bytes(self.sh_type).as_slice(),
+            encoding.word_bytes(self.sh_flags).as_slice(),
+            encoding.word_bytes(self.sh_addr).as_slice(),
+            encoding.word_bytes(self.sh_offset).as_slice(),
+            encoding.word_bytes(self.sh_size).as_slice(),
+            encoding.u32_bytes(self.sh_link).as_slice(),
+            encoding.u32_bytes(self.sh_info).as_slice(),
+            encoding.word_bytes(self.sh_addralign).as_slice(),
+            encoding.word_bytes(self.sh_entsize).as_slice(),
         ]
         .concat();
 
@@ -46,20 +49,48 @@ impl SectionHeaderEntry {
 }
 
 impl Linker {
-    pub(super) fn write_executable<W: std::io::Write + std::io::Seek>(
+    /// Writes the output file: an executable, a shared object with `-shared`, or a
+    /// relocatable object with `-r`.
+    pub(super) fn write_output<W: std::io::Write + std::io::Seek>(
         &self,
         writer: &mut W,
         resolved_symbols: HashMap<String, ResolvedSymbol>,
         section_tables: Vec<Section<'static>>,
         section_name_offsets: HashMap<String, usize>,
     ) -> Result<()> {
-        let Some(ResolvedSymbol { value: entry, .. }) = resolved_symbols.get("_start") else {
-            return Err(LinkerError::MissingEntryPoint {
-                entry_symbol: "_start".to_string(),
-            });
+        let entry = if self.options.relocatable {
+            0
+        } else if self.options.shared {
+            // shared objects rarely have an entry point
+            resolved_symbols
+                .get(self.options.entry_symbol())
+                .map_or(0, |symbol| symbol.value)
+        } else {
+            let entry_symbol = self.options.entry_symbol();
+            let Some(ResolvedSymbol { value: entry, .. }) = resolved_symbols.get(entry_symbol)
+            else {
+                return Err(LinkerError::MissingEntryPoint {
+                    entry_symbol: entry_symbol.to_string(),
+                });
+            };
+            *entry
         };
 
-        let elf_header = self.create_elf_header(*entry, &section_tables);
+        // addresses and offsets of 32-bit output are written in 4 bytes
+        let encoding = self.encoding();
+        let too_far =
+            |s: &&Section<'static>| (s.addr + s.size).max(s.offset + s.size) > u32::MAX as u64;
+        if let Some(section) = section_tables
+            .iter()
+            .find(|s| encoding.class == Class::Bit32 && too_far(s))
+        {
+            return Err(LinkerError::Generic {
+                message: format!("{} does not fit in a 32-bit output", section.name),
+                context: None,
+            });
+        }
+
+        let elf_header = self.create_elf_header(entry, &section_tables);
 
         writer
             .write_all(&elf_header.to_vec())
@@ -81,7 +112,7 @@ impl Linker {
             })?;
 
         // null section header
-        SectionHeaderEntry::default().write(writer)?;
+        SectionHeaderEntry::default().write(writer, encoding)?;
 
         // section headers
         for section in section_tables.iter() {
@@ -94,24 +125,6 @@ impl Linker {
                 sh_flags |= *f as u64;
             }
 
-            let (sh_link, sh_info) = if section.name == ".symtab" {
-                let strtab_idx = section_tables
-                    .iter()
-                    .position(|s| s.name == ".strtab")
-                    .map(|i| i + 1) // includes null section
-                    .unwrap_or(0) as u32;
-
-                let local_sym_count = resolved_symbols
-                    .values()
-                    .filter(|s| s.info.binding == crate::elf::symbol::Binding::Local)
-                    .count() as u32
-                    + 1; // include null symbol
-
-                (strtab_idx, local_sym_count)
-            } else {
-                (0, 0)
-            };
-
             let entry = SectionHeaderEntry {
                 name: name_offset as u32,
                 sh_type: section.r#type as u32,
@@ -119,12 +132,12 @@ impl Linker {
                 sh_addr: section.addr,
                 sh_offset: section.offset,
                 sh_size: section.size,
-                sh_link,
-                sh_info,
+                sh_link: section.link,
+                sh_info: section.info,
                 sh_addralign: section.align,
-                sh_entsize: if section.name == ".symtab" { 24 } else { 0 },
+                sh_entsize: section.entsize,
             };
-            entry.write(writer)?;
+            entry.write(writer, encoding)?;
         }
 
         Ok(())
@@ -134,7 +147,8 @@ impl Linker {
         // section header offset is after all sections
         let shoff = section_tables
             .iter()
-            .map(|s| s.offset + align(s.size, 8))
+            .filter(|s| s.r#type != SectionType::NoBits)
+            .map(|s| align(s.offset + s.size, 8))
             .max()
             .unwrap();
 
@@ -147,113 +161,202 @@ impl Linker {
             .map(|i| (i + 1))
             .unwrap_or(0) as u16;
 
+        let phnum = self.create_program_headers(section_tables).len() as u16;
+        let encoding = self.encoding();
+        let class = encoding.class;
+        let r#type = if self.options.relocatable {
+            header::Type::Rel
+        } else if self.options.is_position_independent() {
+            header::Type::Dyn
+        } else {
+            header::Type::Exec
+        };
+
         header::Header {
             ident: header::Ident {
-                class: header::Class::Bit64,
-                data: header::Data::Lsb,
+                class,
+                data: encoding.data,
                 version: header::IdentVersion::Current,
                 os_abi: header::OSABI::SystemV,
                 abi_version: 0,
             },
-            r#type: header::Type::Exec,
-            machine: header::Machine::AArch64,
+            r#type,
+            machine: self.machine(),
             version: header::Version::Current,
             entry,
-            phoff: 64,
+            phoff: if phnum > 0 { class.header_size() } else { 0 },
             shoff,
-            flags: 0,
-            ehsize: 64,
-            phentsize: 56,
-            phnum: self.count_program_headers(section_tables),
-            shentsize: 64, // the entry size for the section header table is 64 bytes
+            flags: self.flags,
+            ehsize: class.header_size() as u16,
+            phentsize: class.program_header_size() as u16,
+            phnum,
+            shentsize: class.section_header_size() as u16,
             shnum,
             shstrndx,
         }
     }
 
-    fn count_program_headers(&self, output_sections: &[Section<'static>]) -> u16 {
-        let sym_map = HashMap::<&str, ()>::from_iter([
-            (".text", ()),
-            (".data", ()),
-            (".bss", ()),
-            (".rodata", ()),
-            (".got", ()),
-            (".plt", ()),
-            (".dynamic", ()),
-            (".interp", ()),
-            (".note", ()),
-        ]);
-        output_sections
-            .iter()
-            .filter(|s| sym_map.contains_key(s.name.as_ref()))
-            .count() as u16
-    }
-
-    fn create_program_headers(
+    /// Creates a `PT_LOAD` program header for each run of allocated sections with the
+    /// same permissions. The first segment also maps the ELF header and program headers.
+    /// `.dynamic`, if any, is also described by a `PT_DYNAMIC` header, and `.interp` by
+    /// a `PT_INTERP` header that follows the `PT_PHDR` header. TLS sections are
+    /// described by a `PT_TLS` header, and `.eh_frame_hdr` by a `PT_GNU_EH_FRAME` header.
+    /// Relocatable objects have no program headers.
+    pub(super) fn create_program_headers(
         &self,
         output_sections: &[Section<'static>],
     ) -> Vec<program_header::ProgramHeader> {
         let mut program_headers = Vec::new();
+        if self.options.relocatable {
+            return program_headers;
+        }
 
-        for s in output_sections.iter() {
-            match s.name.as_ref() {
-                ".text" => {
-                    let text_ph = program_header::ProgramHeader {
-                        r#type: segument::Type::Load,
-                        flags: vec![segument::Flag::Readable, segument::Flag::Executable],
-                        offset: 0,
-                        vaddr: BASE_ADDR,
-                        paddr: BASE_ADDR,
-                        filesz: s.size,
-                        memsz: s.size,
-                        align: 0x10000,
-                    };
-                    program_headers.push(text_ph);
-                }
-                ".data" => {
-                    let data_ph = program_header::ProgramHeader {
-                        r#type: segument::Type::Load,
-                        flags: vec![segument::Flag::Readable, segument::Flag::Writable],
-                        offset: s.offset,
-                        vaddr: s.addr,
-                        paddr: s.addr,
-                        filesz: s.size,
-                        memsz: s.size,
-                        align: 0x10000,
-                    };
-                    program_headers.push(data_ph);
-                }
-                _ => {
-                    // TODO
-                }
-            }
+        // the loader finds the program headers through PT_PHDR, which has to come
+        // first, followed by PT_INTERP
+        let class = self.class();
+        if let Some(interp) = output_sections.iter().find(|s| s.name == ".interp") {
+            let first = &output_sections[0];
+            let vaddr = first.addr - first.offset + class.header_size();
+            // the size is set once all program headers are known
+            program_headers.push(program_header::ProgramHeader {
+                r#type: segument::Type::Phdr,
+                flags: vec![segument::Flag::Readable],
+                offset: class.header_size(),
+                vaddr,
+                paddr: vaddr,
+                filesz: 0,
+                memsz: 0,
+                align: 8,
+            });
+            program_headers.push(program_header::ProgramHeader {
+                r#type: segument::Type::Interp,
+                flags: vec![segument::Flag::Readable],
+                offset: interp.offset,
+                vaddr: interp.addr,
+                paddr: interp.addr,
+                filesz: interp.size,
+                memsz: interp.size,
+                align: interp.align,
+            });
+        }
+
+        for (i, segment) in segments(output_sections).into_iter().enumerate() {
+            let first = &segment[0];
+            let last = &segment[segment.len() - 1];
+
+            let (offset, vaddr) = if i == 0 {
+                (0, first.addr - first.offset)
+            } else {
+                (first.offset, first.addr)
+            };
+            let file_end = segment
+                .iter()
+                .filter(|s| s.r#type != SectionType::NoBits)
+                .map(|s| s.offset + s.size)
+                .max()
+                .unwrap_or(offset);
+
+            program_headers.push(program_header::ProgramHeader {
+                r#type: segument::Type::Load,
+                flags: segment_flags(first),
+                offset,
+                vaddr,
+                paddr: vaddr,
+                filesz: file_end.max(offset) - offset,
+                memsz: last.addr + last.size - vaddr,
+                align: self.target().page_size(),
+            });
+        }
+
+        if let Some(dynamic) = output_sections.iter().find(|s| s.name == ".dynamic") {
+            program_headers.push(program_header::ProgramHeader {
+                r#type: segument::Type::Dynamic,
+                flags: segment_flags(dynamic),
+                offset: dynamic.offset,
+                vaddr: dynamic.addr,
+                paddr: dynamic.addr,
+                filesz: dynamic.size,
+                memsz: dynamic.size,
+                align: dynamic.align,
+            });
+        }
+
+        if let Some(tls) = tls_segment(output_sections) {
+            program_headers.push(program_header::ProgramHeader {
+                r#type: segument::Type::Tls,
+                flags: vec![segument::Flag::Readable],
+                offset: tls.offset,
+                vaddr: tls.addr,
+                paddr: tls.addr,
+                filesz: tls.filesz,
+                memsz: tls.memsz,
+                align: tls.align,
+            });
+        }
+
+        if let Some(hdr) = output_sections.iter().find(|s| s.name == ".eh_frame_hdr") {
+            program_headers.push(program_header::ProgramHeader {
+                r#type: segument::Type::GnuEhFrame,
+                flags: vec![segument::Flag::Readable],
+                offset: hdr.offset,
+                vaddr: hdr.addr,
+                paddr: hdr.addr,
+                filesz: hdr.size,
+                memsz: hdr.size,
+                align: hdr.align,
+            });
+        }
+
+        let size = program_headers.len() as u64 * class.program_header_size();
+        if let Some(phdr) = program_headers
+            .first_mut()
+            .filter(|ph| matches!(ph.r#type, segument::Type::Phdr))
+        {
+            phdr.filesz = size;
+            phdr.memsz = size;
         }
 
         program_headers
     }
 
+    /// Writes `Elf64_Phdr` entries, or `Elf32_Phdr` entries for 32-bit output, where
+    /// the flags come after the sizes instead of after the type.
     fn write_program_headers<W: std::io::Write>(
         &self,
         writer: &mut W,
         headers: &[program_header::ProgramHeader],
     ) -> Result<()> {
+        let encoding = self.encoding();
         for ph in headers {
             let mut flag: u32 = 0;
             for f in &ph.flags {
                 flag |= *f as u32;
             }
 
-            let bytes = &[
-                (ph.r#type as u32).to_le_bytes().as_slice(),
-                flag.to_le_bytes().as_slice(),
-                ph.offset.to_le_bytes().as_slice(),
-                ph.vaddr.to_le_bytes().as_slice(),
-                ph.paddr.to_le_bytes().as_slice(),
-                ph.filesz.to_le_bytes().as_slice(),
-                ph.memsz.to_le_bytes().as_slice(),
-                ph.align.to_le_bytes().as_slice(),
+            let fields = [
+                encoding.word_bytes(ph.offset),
+                encoding.word_bytes(ph.vaddr),
+                encoding.word_bytes(ph.paddr),
+                encoding.word_bytes(ph.filesz),
+                encoding.word_bytes(ph.memsz),
             ]
             .concat();
+            let bytes = &match encoding.class {
+                Class::Bit32 => [
+                    encoding.u32_bytes(ph.r#type as u32).as_slice(),
+                    fields.as_slice(),
+                    encoding.u32_bytes(flag).as_slice(),
+                    encoding.word_bytes(ph.align).as_slice(),
+                ]
+                .concat(),
+                _ => [
+                    encoding.u32_bytes(ph.r#type as u32).as_slice(),
+                    encoding.u32_bytes(flag).as_slice(),
+                    fields.as_slice(),
+                    encoding.word_bytes(ph.align).as_slice(),
+                ]
+                .concat(),
+            };
 
             writer.write_all(bytes).map_err(|e| LinkerError::Io {
                 error: e,
@@ -3,31 +3,62 @@ use std::io::Write as _;
 use std::path::Path;
 use std::process;
 
+use yui::cli;
 use yui::error::LinkerError;
 use yui::linker::Linker;
 
 fn main() -> Result<(), LinkerError> {
-    let args: Vec<String> = env::args().collect();
-
-    if args.len() < 3 {
-        eprintln!("Usage: {} <output> <input1> [<input2> ...]", args[0]);
-        process::exit(1);
-    }
+    let program = env::args().next().unwrap_or_else(|| "yui".to_string());
+    let args = match cli::parse_args(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!(
+                "Usage: {} [options] <output> <input1> [<input2> ...]",
+                program
+            );
+            process::exit(1);
+        }
+    };
 
-    let input_paths: Vec<&Path> = args[2..].iter().map(Path::new).collect();
     let inputs = {
-        let mut inputs = Vec::with_capacity(input_paths.len());
-        for path in input_paths.iter() {
+        let mut inputs = Vec::with_capacity(args.inputs.len());
+        for path in args.inputs.iter() {
             let data = std::fs::read(path)?;
             inputs.push(data);
         }
         inputs
     };
 
-    let mut linker = Linker::new();
+    let print_gc_sections = args.options.print_gc_sections;
+    let print_icf_sections = args.options.print_icf_sections;
+    let mut linker = Linker::with_options(args.options);
 
-    let mut out = create_output_file(Path::new(&args[1]))?;
+    let mut out = create_output_file(&args.output)?;
     out.write_all(&linker.link_to_file(inputs)?)?;
+
+    if print_gc_sections {
+        for removed in &linker.report().removed_sections {
+            eprintln!(
+                "yui: removing unused section '{}' in file '{}'",
+                removed.section, removed.file
+            );
+        }
+    }
+    if print_icf_sections {
+        for (kept, folded) in &linker.report().folded_sections {
+            eprintln!(
+                "yui: selected section '{}' in file '{}'",
+                kept.section, kept.file
+            );
+            for folded in folded {
+                eprintln!(
+                    "yui:   removing identical section '{}' in file '{}'",
+                    folded.section, folded.file
+                );
+            }
+        }
+    }
     Ok(())
 }
 
@@ -1,6 +1,9 @@
 mod helper;
 
+pub mod archive;
+pub mod dynamic;
 pub mod error;
+pub mod group;
 pub mod header;
 pub mod relocation;
 pub mod section;
@@ -26,17 +29,23 @@ pub type ParseResult<'a, T> = IResult<&'a [u8], T, ParseError>;
 pub fn parse_elf(raw: &[u8]) -> ParseResult<ELF> {
     let header = header::parse(raw)?.1;
 
+    let encoding = header.ident.encoding();
     let section_headers = section::parse_header(
         raw,
+        encoding,
         header.shoff as usize,
         header.shstrndx as usize,
         header.shnum as usize,
     )?
     .1;
 
-    let symbols = symbol::parse(raw, &section_headers)?.1;
+    let symbols = symbol::parse(raw, &section_headers, encoding)?.1;
+    let dynamic_symbols = symbol::parse_dynamic(raw, &section_headers, encoding)?.1;
+    let dynamic = dynamic::parse(&section_headers, encoding)?.1;
 
-    let relocations = relocation::parse(&section_headers)?.1;
+    let relocations = relocation::parse(&section_headers, header.machine, encoding)?.1;
+
+    let groups = group::parse(&section_headers, &symbols, encoding)?.1;
 
     Ok((
         &[],
@@ -45,6 +54,9 @@ pub fn parse_elf(raw: &[u8]) -> ParseResult<ELF> {
             section_headers,
             symbols,
             relocations,
+            groups,
+            dynamic_symbols,
+            dynamic,
         },
     ))
 }
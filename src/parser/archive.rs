@@ -0,0 +1,196 @@
+use nom::Parser as _;
+use nom::bytes::complete::take;
+use nom::multi::count;
+
+use super::ParseResult;
+use super::helper::word;
+use crate::bail_nom_error;
+use crate::elf::archive::{Archive, MAGIC, Member};
+use crate::elf::header::{Class, Data, Encoding};
+use crate::parser::error::ParseError;
+
+const MEMBER_HEADER_SIZE: usize = 60;
+const MEMBER_HEADER_END: &[u8; 2] = b"`\n";
+
+/// Returns true if `raw` starts with the `ar` archive magic.
+pub fn is_archive(raw: &[u8]) -> bool {
+    raw.starts_with(MAGIC)
+}
+
+fn parse_decimal(field: &[u8]) -> Result<usize, ParseError> {
+    let text = String::from_utf8_lossy(field);
+    text.trim()
+        .parse()
+        .map_err(|_| ParseError::InvalidArchiveHeader(text.trim().to_string()))
+}
+
+/// Parses an `ar` archive in either the GNU (System V) or BSD variant.
+///
+/// Member header layout (60 bytes):
+///
+/// ```text
+/// +------+------+-----+-----+------+------+------+
+/// | name | date | uid | gid | mode | size | "`\n"|
+/// |  16  |  12  |  6  |  6  |  8   |  10  |  2   |
+/// +------+------+-----+-----+------+------+------+
+/// ```
+///
+/// Member data follows the header and is padded to an even offset.
+pub fn parse(raw: &[u8]) -> ParseResult<Archive> {
+    if !is_archive(raw) {
+        bail_nom_error!(ParseError::InvalidArchiveHeader(
+            String::from_utf8_lossy(&raw[..raw.len().min(MAGIC.len())]).to_string()
+        ));
+    }
+
+    let mut rest = &raw[MAGIC.len()..];
+    let mut long_names: &[u8] = &[];
+    let mut symbol_table = None;
+    let mut members = Vec::new();
+    // file offsets of the headers of `members`, which the symbol table refers to
+    let mut member_offsets = Vec::new();
+
+    while !rest.is_empty() {
+        let offset = raw.len() - rest.len();
+        let (after_header, header) = take(MEMBER_HEADER_SIZE)(rest)?;
+        if &header[58..60] != MEMBER_HEADER_END {
+            bail_nom_error!(ParseError::InvalidArchiveHeader(
+                String::from_utf8_lossy(header).to_string()
+            ));
+        }
+
+        let size = parse_decimal(&header[48..58]).map_err(nom::Err::Error)?;
+        let (after_data, mut data) = take(size)(after_header)?;
+        rest = &after_data[(size % 2).min(after_data.len())..];
+
+        let raw_name = String::from_utf8_lossy(&header[..16])
+            .trim_end()
+            .to_string();
+        let name = match raw_name.as_str() {
+            // symbol table (GNU and 64-bit GNU variants)
+            "/" => {
+                symbol_table = Some(parse_symbol_table(data, Class::Bit32)?);
+                continue;
+            }
+            "/SYM64/" => {
+                symbol_table = Some(parse_symbol_table(data, Class::Bit64)?);
+                continue;
+            }
+            // BSD symbol table, whose members are found by scanning them instead
+            "__.SYMDEF" | "__.SYMDEF SORTED" => continue,
+            // long name table
+            "//" => {
+                long_names = data;
+                continue;
+            }
+            // GNU long name: offset into the long name table
+            name if name.starts_with('/') => {
+                let offset = parse_decimal(&name.as_bytes()[1..]).map_err(nom::Err::Error)?;
+                let Some(entry) = long_names.get(offset..) else {
+                    bail_nom_error!(ParseError::InvalidArchiveHeader(name.to_string()));
+                };
+                let end = entry
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .unwrap_or(entry.len());
+                String::from_utf8_lossy(&entry[..end])
+                    .trim_end_matches('/')
+                    .to_string()
+            }
+            // BSD long name: the name is stored at the start of the data
+            name if name.starts_with("#1/") => {
+                let len = parse_decimal(&name.as_bytes()[3..]).map_err(nom::Err::Error)?;
+                let (member_data, name) = take(len)(data)?;
+                data = member_data;
+                String::from_utf8_lossy(name)
+                    .trim_end_matches('\0')
+                    .to_string()
+            }
+            name => name.trim_end_matches('/').to_string(),
+        };
+
+        members.push(Member {
+            name,
+            data: data.to_vec(),
+        });
+        member_offsets.push(offset);
+    }
+
+    let symbols = symbol_table.map(|table| {
+        table
+            .into_iter()
+            .filter_map(|(name, offset)| {
+                let member = member_offsets.binary_search(&offset).ok()?;
+                Some((name, member))
+            })
+            .collect()
+    });
+
+    Ok((rest, Archive { members, symbols }))
+}
+
+/// Parses the GNU symbol table of an archive into the names of the symbols and
+/// the file offsets of the headers of the members that define them.
+///
+/// The table holds a big-endian count of symbols, one big-endian offset per
+/// symbol (4 bytes wide in `/`, 8 bytes wide in `/SYM64/`), and the NUL-terminated
+/// names.
+fn parse_symbol_table(
+    data: &[u8],
+    class: Class,
+) -> Result<Vec<(String, usize)>, nom::Err<ParseError>> {
+    let word = word(Encoding {
+        class,
+        data: Data::Msb,
+    });
+    let (rest, symbol_count) = word(data)?;
+    let (rest, offsets) = count(&word, symbol_count as usize).parse(rest)?;
+
+    let mut names = rest.split(|&b| b == 0);
+    offsets
+        .into_iter()
+        .map(|offset| {
+            let Some(name) = names.next() else {
+                bail_nom_error!(ParseError::InvalidArchiveHeader("/".to_string()));
+            };
+            Ok((String::from_utf8_lossy(name).to_string(), offset as usize))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn should_parse_archive() {
+        let raw = include_bytes!("../linker/fixtures/libplugin.a");
+        let (_, archive) = parse(raw).unwrap();
+
+        let names: Vec<_> = archive.members.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["plugin_registration_with_a_long_name.o", "plugin_unused.o"]
+        );
+        assert_eq!(
+            archive.symbols,
+            Some(vec![
+                ("plugin_register".to_string(), 0),
+                ("plugin_unused".to_string(), 1),
+            ])
+        );
+        assert!(
+            archive
+                .members
+                .iter()
+                .all(|m| crate::parser::header::parse(&m.data).is_ok())
+        );
+    }
+
+    #[test]
+    fn should_error_invalid_archive() {
+        let err = parse(b"!<arch>\nbroken").unwrap_err();
+        assert!(matches!(err, nom::Err::Error(ParseError::Nom(_))));
+    }
+}
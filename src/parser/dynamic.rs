@@ -0,0 +1,85 @@
+use nom::{Parser as _, multi::many0};
+
+use super::{ParseResult, helper};
+use crate::elf::dynamic::{Entry, Tag};
+use crate::elf::header::Encoding;
+use crate::elf::section::{Header, SectionType};
+
+impl TryFrom<u64> for Tag {
+    type Error = u64;
+
+    fn try_from(value: u64) -> Result<Self, Self::Error> {
+        let tag = match value {
+            0 => Self::Null,
+            1 => Self::Needed,
+            2 => Self::PltRelSz,
+            3 => Self::PltGot,
+            4 => Self::Hash,
+            5 => Self::StrTab,
+            6 => Self::SymTab,
+            7 => Self::Rela,
+            8 => Self::RelaSz,
+            9 => Self::RelaEnt,
+            10 => Self::StrSz,
+            11 => Self::SymEnt,
+            12 => Self::Init,
+            13 => Self::Fini,
+            14 => Self::SoName,
+            15 => Self::RPath,
+            16 => Self::Symbolic,
+            17 => Self::Rel,
+            18 => Self::RelSz,
+            19 => Self::RelEnt,
+            20 => Self::PltRel,
+            21 => Self::Debug,
+            22 => Self::TextRel,
+            23 => Self::JmpRel,
+            24 => Self::BindNow,
+            25 => Self::InitArray,
+            26 => Self::FiniArray,
+            27 => Self::InitArraySz,
+            28 => Self::FiniArraySz,
+            29 => Self::RunPath,
+            30 => Self::Flags,
+            32 => Self::PreInitArray,
+            33 => Self::PreInitArraySz,
+            0x6ffffef5 => Self::GnuHash,
+            0x6ffffff0 => Self::VerSym,
+            0x6ffffff9 => Self::RelaCount,
+            0x6ffffffb => Self::Flags1,
+            0x6ffffffc => Self::VerDef,
+            0x6ffffffd => Self::VerDefNum,
+            0x6ffffffe => Self::VerNeed,
+            0x6fffffff => Self::VerNeedNum,
+            _ => return Err(value),
+        };
+        Ok(tag)
+    }
+}
+
+/// Parses the entries of the dynamic section (`SHT_DYNAMIC`), up to `DT_NULL`.
+///
+/// Entries with tags that the linker does not know about are skipped. Tags and
+/// values are 4 bytes long in ELF32 and 8 bytes long in ELF64.
+pub fn parse(section_headers: &[Header], encoding: Encoding) -> ParseResult<Vec<Entry>> {
+    let Some(header) = section_headers
+        .iter()
+        .find(|s| s.r#type == SectionType::Dynamic)
+    else {
+        return Ok((&[], vec![]));
+    };
+
+    let word = helper::word(encoding);
+    let (_, values) = many0((&word, &word)).parse(header.section_raw_data.as_slice())?;
+
+    let mut entries = Vec::new();
+    for (tag, value) in values {
+        match Tag::try_from(tag) {
+            Ok(Tag::Null) => break,
+            Ok(tag) => entries.push(Entry { tag, value }),
+            Err(_) => continue,
+        }
+    }
+
+    Ok((&[], entries))
+}
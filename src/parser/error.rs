@@ -37,9 +37,19 @@ pub enum ParseError {
     InvalidSymbolType(u8),
     #[error("Invalid symbol binding: {0}")]
     InvalidSymbolBinding(u8),
+    // Section Group
+    #[error("Section group member {0} is not a valid section index")]
+    InvalidGroupMember(u32),
+    // Archive
+    #[error("Invalid archive header: {0}")]
+    InvalidArchiveHeader(String),
     // Relocation Addend
     #[error("Invalid relocation type: {0}")]
     InvalidRelocationType(u32),
+    #[error("Relocation offset {0:#x} is outside of the section")]
+    InvalidRelocationOffset(u64),
+    #[error("Implicit addend of {0} is not supported")]
+    UnsupportedImplicitAddend(String),
     #[error("Parser error: {0}")]
     Nom(String),
 }
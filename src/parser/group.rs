@@ -0,0 +1,111 @@
+use nom::{Parser as _, multi::many0, number::complete::u32};
+
+use super::{ParseResult, helper};
+use crate::bail_nom_error;
+use crate::elf::group::Group;
+use crate::elf::header::Encoding;
+use crate::elf::section::{Header, SectionType};
+use crate::elf::symbol::{self, Symbol};
+use crate::parser::error::ParseError;
+
+/// Parses every `SHT_GROUP` section in the object.
+///
+/// The section data is a flag word followed by the indices of the member sections.
+/// The signature symbol is the `sh_info`-th entry of the symbol table. If it is a
+/// section symbol, the group is identified by the name of that section instead.
+/// Members must be sections of the object other than the null section.
+pub fn parse<'a>(
+    section_headers: &'a [Header],
+    symbols: &[Symbol],
+    encoding: Encoding,
+) -> ParseResult<'a, Vec<Group>> {
+    let mut groups = Vec::new();
+    let endian = helper::endianness(encoding);
+
+    for (section_index, header) in section_headers
+        .iter()
+        .enumerate()
+        .filter(|(_, s)| s.r#type == SectionType::Group)
+    {
+        let (rest, flags) = u32(endian)(header.section_raw_data.as_slice())?;
+        let (_, members) = many0(u32(endian)).parse(rest)?;
+        let mut member_indices = Vec::with_capacity(members.len());
+        for member in members {
+            match u16::try_from(member) {
+                Ok(idx) if idx != 0 && (idx as usize) < section_headers.len() => {
+                    member_indices.push(idx)
+                }
+                _ => {
+                    bail_nom_error!(ParseError::InvalidGroupMember(member));
+                }
+            }
+        }
+
+        let signature = match symbols.get(header.info as usize) {
+            Some(symbol) if symbol.info.r#type == symbol::Type::Section => section_headers
+                .get(symbol.shndx as usize)
+                .map(|s| s.name.clone())
+                .unwrap_or_default(),
+            Some(symbol) => symbol.name.clone(),
+            None => String::new(),
+        };
+
+        groups.push(Group {
+            section_index: section_index as u16,
+            signature,
+            flags,
+            members: member_indices,
+        });
+    }
+
+    Ok((&[], groups))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::elf::header::{Class, Data};
+
+    fn section(r#type: SectionType, data: Vec<u8>) -> Header {
+        Header {
+            name_idx: 0,
+            name: String::new(),
+            r#type,
+            flags: Vec::new(),
+            addr: 0,
+            offset: 0,
+            size: data.len() as u64,
+            link: 0,
+            info: 0,
+            addralign: 4,
+            entsize: 4,
+            section_raw_data: data,
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_member() {
+        let encoding = Encoding {
+            class: Class::Bit64,
+            data: Data::Lsb,
+        };
+        let group = |members: &[u32]| {
+            let data = [1].iter().chain(members).flat_map(|w| w.to_le_bytes());
+            vec![
+                section(SectionType::Null, Vec::new()),
+                section(SectionType::Group, data.collect()),
+                section(SectionType::ProgBits, vec![0; 4]),
+            ]
+        };
+
+        let sections = group(&[2]);
+        let groups = parse(&sections, &[], encoding).unwrap().1;
+        assert_eq!(groups[0].members, vec![2]);
+
+        for member in [0, 3, 0x10002] {
+            let sections = group(&[2, member]);
+            let err = parse(&sections, &[], encoding).unwrap_err();
+            assert_eq!(err, nom::Err::Error(ParseError::InvalidGroupMember(member)));
+        }
+    }
+}
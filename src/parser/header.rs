@@ -1,4 +1,4 @@
-use super::ParseResult;
+use super::{ParseResult, helper};
 use crate::bail_nom_error;
 use crate::elf::header::{Class, Data, Header, Ident, IdentVersion, Machine, OSABI, Type, Version};
 use crate::parser::error::ParseError;
@@ -7,7 +7,8 @@ use nom::combinator::map_res;
 use nom::multi::count;
 use nom::{
     IResult,
-    number::complete::{le_u8, le_u16, le_u32, le_u64},
+    number::Endianness,
+    number::complete::{le_u8, u16, u32},
 };
 
 const ELF_MAGIC_NUMBER: [u8; 4] = [0x7f, 0x45, 0x4c, 0x46]; // 0x7f 'E' 'L' 'F'
@@ -154,16 +155,16 @@ fn parse_ident(raw: &[u8]) -> ParseResult<Ident> {
     Ok((rest, ident))
 }
 
-fn parse_type(raw: &[u8]) -> ParseResult<Type> {
-    map_res(le_u16, Type::try_from).parse(raw)
+fn parse_type(raw: &[u8], endian: Endianness) -> ParseResult<Type> {
+    map_res(u16(endian), Type::try_from).parse(raw)
 }
 
-fn parse_machine(raw: &[u8]) -> ParseResult<Machine> {
-    map_res(le_u16, Machine::try_from).parse(raw)
+fn parse_machine(raw: &[u8], endian: Endianness) -> ParseResult<Machine> {
+    map_res(u16(endian), Machine::try_from).parse(raw)
 }
 
-fn parse_version(raw: &[u8]) -> ParseResult<Version> {
-    map_res(le_u32, Version::try_from).parse(raw)
+fn parse_version(raw: &[u8], endian: Endianness) -> ParseResult<Version> {
+    map_res(u32(endian), Version::try_from).parse(raw)
 }
 
 fn parse_magic_number(raw: &[u8]) -> IResult<&[u8], (), ParseError> {
@@ -181,20 +182,23 @@ pub fn parse(raw: &[u8]) -> ParseResult<Header> {
     if raw.len() < ELF_IDENT_HEADER_SIZE {
         bail_nom_error!(ParseError::InvalidHeaderSize(raw.len() as u8));
     }
+    // the fields after the identification follow its class and byte order
     let (rest, ident) = parse_ident(raw)?;
-    let (rest, r#type) = parse_type(rest)?;
-    let (rest, machine) = parse_machine(rest)?;
-    let (rest, version) = parse_version(rest)?;
-    let (rest, entry) = le_u64(rest)?;
-    let (rest, phoff) = le_u64(rest)?;
-    let (rest, shoff) = le_u64(rest)?;
-    let (rest, flags) = le_u32(rest)?;
-    let (rest, ehsize) = le_u16(rest)?;
-    let (rest, phentsize) = le_u16(rest)?;
-    let (rest, phnum) = le_u16(rest)?;
-    let (rest, shentsize) = le_u16(rest)?;
-    let (rest, shnum) = le_u16(rest)?;
-    let (rest, shstrndx) = le_u16(rest)?;
+    let endian = helper::endianness(ident.encoding());
+    let (rest, r#type) = parse_type(rest, endian)?;
+    let (rest, machine) = parse_machine(rest, endian)?;
+    let (rest, version) = parse_version(rest, endian)?;
+    let word = helper::word(ident.encoding());
+    let (rest, entry) = word(rest)?;
+    let (rest, phoff) = word(rest)?;
+    let (rest, shoff) = word(rest)?;
+    let (rest, flags) = u32(endian)(rest)?;
+    let (rest, ehsize) = u16(endian)(rest)?;
+    let (rest, phentsize) = u16(endian)(rest)?;
+    let (rest, phnum) = u16(endian)(rest)?;
+    let (rest, shentsize) = u16(endian)(rest)?;
+    let (rest, shnum) = u16(endian)(rest)?;
+    let (rest, shstrndx) = u16(endian)(rest)?;
 
     Ok((
         rest,
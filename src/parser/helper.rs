@@ -1,3 +1,9 @@
+use nom::number::Endianness;
+use nom::number::complete::{u32, u64};
+
+use super::ParseResult;
+use crate::elf::header::{Class, Encoding};
+
 pub fn get_string_by_offset(raw: &[u8], offset: usize) -> String {
     let mut end = offset;
     while raw[end] != 0 {
@@ -7,9 +13,41 @@ pub fn get_string_by_offset(raw: &[u8], offset: usize) -> String {
     String::from_utf8_lossy(&raw[offset..end]).to_string()
 }
 
+/// Returns the byte order of the fields of a file.
+pub fn endianness(encoding: Encoding) -> Endianness {
+    if encoding.is_big_endian() {
+        Endianness::Big
+    } else {
+        Endianness::Little
+    }
+}
+
+/// Returns a parser for an address, offset or size field, which takes 4 bytes in
+/// ELF32 files and 8 bytes in ELF64 files.
+pub fn word(encoding: Encoding) -> impl Fn(&[u8]) -> ParseResult<u64> {
+    let endian = endianness(encoding);
+    move |raw| match encoding.class {
+        Class::Bit32 => u32(endian)(raw).map(|(rest, value)| (rest, value as u64)),
+        _ => u64(endian)(raw),
+    }
+}
+
 #[test]
 fn get_string_by_offset_ok() {
     let bytes = b".text\0";
     let name = get_string_by_offset(bytes, 0);
     assert_eq!(name, ".text");
 }
+
+#[test]
+fn word_follows_encoding() {
+    use crate::elf::header::Data;
+
+    let bytes = [0x78, 0x56, 0x34, 0x12, 0x01, 0, 0, 0];
+    let encoding = |class, data| Encoding { class, data };
+    let word = |class, data| word(encoding(class, data))(&bytes).unwrap().1;
+    assert_eq!(word(Class::Bit32, Data::Lsb), 0x12345678);
+    assert_eq!(word(Class::Bit64, Data::Lsb), 0x1_12345678);
+    assert_eq!(word(Class::Bit32, Data::Msb), 0x78563412);
+    assert_eq!(word(Class::Bit64, Data::Msb), 0x78563412_01000000);
+}
@@ -2,12 +2,13 @@ use nom::{
     Parser as _,
     combinator::map_res,
     multi::count,
-    number::complete::{le_i64, le_u64},
+    number::complete::{i32, i64},
 };
 
-use super::{ParseResult, error::ParseError};
+use super::{ParseResult, error::ParseError, helper};
 use crate::elf::{
-    relocation::{Info, RelocationAddend, RelocationType},
+    header::{Class, Encoding, Machine},
+    relocation::{Info, RelocationAddend, RelocationSection, RelocationType},
     section,
 };
 
@@ -16,18 +17,160 @@ impl TryFrom<u32> for RelocationType {
 
     fn try_from(value: u32) -> Result<Self, Self::Error> {
         match value {
+            0 => Ok(Self::Aarch64None),
+            257 => Ok(Self::Aarch64Abs64),
+            258 => Ok(Self::Aarch64Abs32),
+            259 => Ok(Self::Aarch64Abs16),
+            260 => Ok(Self::Aarch64Prel64),
+            261 => Ok(Self::Aarch64Prel32),
+            262 => Ok(Self::Aarch64Prel16),
+            263 => Ok(Self::Aarch64MovwUabsG0),
+            264 => Ok(Self::Aarch64MovwUabsG0Nc),
+            265 => Ok(Self::Aarch64MovwUabsG1),
+            266 => Ok(Self::Aarch64MovwUabsG1Nc),
+            267 => Ok(Self::Aarch64MovwUabsG2),
+            268 => Ok(Self::Aarch64MovwUabsG2Nc),
+            269 => Ok(Self::Aarch64MovwUabsG3),
+            270 => Ok(Self::Aarch64MovwSabsG0),
+            271 => Ok(Self::Aarch64MovwSabsG1),
+            272 => Ok(Self::Aarch64MovwSabsG2),
+            273 => Ok(Self::Aarch64LdPrelLo19),
             274 => Ok(Self::Aarch64AdrPrelLo21),
+            275 => Ok(Self::Aarch64AdrPrelPgHi21),
+            276 => Ok(Self::Aarch64AdrPrelPgHi21Nc),
+            277 => Ok(Self::Aarch64AddAbsLo12Nc),
+            278 => Ok(Self::Aarch64Ldst8AbsLo12Nc),
+            279 => Ok(Self::Aarch64Tstbr14),
+            280 => Ok(Self::Aarch64Condbr19),
+            282 => Ok(Self::Aarch64Jump26),
+            283 => Ok(Self::Aarch64Call26),
+            284 => Ok(Self::Aarch64Ldst16AbsLo12Nc),
+            285 => Ok(Self::Aarch64Ldst32AbsLo12Nc),
+            286 => Ok(Self::Aarch64Ldst64AbsLo12Nc),
+            287 => Ok(Self::Aarch64MovwPrelG0),
+            288 => Ok(Self::Aarch64MovwPrelG0Nc),
+            289 => Ok(Self::Aarch64MovwPrelG1),
+            290 => Ok(Self::Aarch64MovwPrelG1Nc),
+            291 => Ok(Self::Aarch64MovwPrelG2),
+            292 => Ok(Self::Aarch64MovwPrelG2Nc),
+            293 => Ok(Self::Aarch64MovwPrelG3),
+            299 => Ok(Self::Aarch64Ldst128AbsLo12Nc),
+            311 => Ok(Self::Aarch64AdrGotPage),
+            312 => Ok(Self::Aarch64Ld64GotLo12Nc),
+            313 => Ok(Self::Aarch64Ld64GotPageLo15),
+            541 => Ok(Self::Aarch64TlsIeAdrGotTprelPage21),
+            542 => Ok(Self::Aarch64TlsIeLd64GotTprelLo12Nc),
+            544 => Ok(Self::Aarch64TlsLeMovwTprelG2),
+            545 => Ok(Self::Aarch64TlsLeMovwTprelG1),
+            546 => Ok(Self::Aarch64TlsLeMovwTprelG1Nc),
+            547 => Ok(Self::Aarch64TlsLeMovwTprelG0),
+            548 => Ok(Self::Aarch64TlsLeMovwTprelG0Nc),
+            549 => Ok(Self::Aarch64TlsLeAddTprelHi12),
+            550 => Ok(Self::Aarch64TlsLeAddTprelLo12),
+            551 => Ok(Self::Aarch64TlsLeAddTprelLo12Nc),
+            552 => Ok(Self::Aarch64TlsLeLdst8TprelLo12),
+            553 => Ok(Self::Aarch64TlsLeLdst8TprelLo12Nc),
+            554 => Ok(Self::Aarch64TlsLeLdst16TprelLo12),
+            555 => Ok(Self::Aarch64TlsLeLdst16TprelLo12Nc),
+            556 => Ok(Self::Aarch64TlsLeLdst32TprelLo12),
+            557 => Ok(Self::Aarch64TlsLeLdst32TprelLo12Nc),
+            558 => Ok(Self::Aarch64TlsLeLdst64TprelLo12),
+            559 => Ok(Self::Aarch64TlsLeLdst64TprelLo12Nc),
+            562 => Ok(Self::Aarch64TlsDescAdrPage21),
+            563 => Ok(Self::Aarch64TlsDescLd64Lo12),
+            564 => Ok(Self::Aarch64TlsDescAddLo12),
+            569 => Ok(Self::Aarch64TlsDescCall),
+            570 => Ok(Self::Aarch64TlsLeLdst128TprelLo12),
+            571 => Ok(Self::Aarch64TlsLeLdst128TprelLo12Nc),
+            1024 => Ok(Self::Aarch64Copy),
+            1025 => Ok(Self::Aarch64GlobDat),
+            1026 => Ok(Self::Aarch64JumpSlot),
+            1027 => Ok(Self::Aarch64Relative),
+            1028 => Ok(Self::Aarch64TlsDtpMod64),
+            1029 => Ok(Self::Aarch64TlsDtpRel64),
+            1030 => Ok(Self::Aarch64TlsTpRel64),
+            1031 => Ok(Self::Aarch64TlsDesc),
+            1032 => Ok(Self::Aarch64IRelative),
             _ => Err(ParseError::InvalidRelocationType(value)),
         }
     }
 }
 
-impl TryFrom<u64> for Info {
+/// Returns the x86-64 relocation type numbered `value`.
+fn x86_64_relocation_type(value: u32) -> Result<RelocationType, ParseError> {
+    match value {
+        0 => Ok(RelocationType::X86_64None),
+        1 => Ok(RelocationType::X86_64_64),
+        2 => Ok(RelocationType::X86_64Pc32),
+        4 => Ok(RelocationType::X86_64Plt32),
+        5 => Ok(RelocationType::X86_64Copy),
+        6 => Ok(RelocationType::X86_64GlobDat),
+        7 => Ok(RelocationType::X86_64JumpSlot),
+        8 => Ok(RelocationType::X86_64Relative),
+        9 => Ok(RelocationType::X86_64GotPcRel),
+        10 => Ok(RelocationType::X86_64_32),
+        11 => Ok(RelocationType::X86_64_32S),
+        16 => Ok(RelocationType::X86_64DtpMod64),
+        17 => Ok(RelocationType::X86_64DtpOff64),
+        18 => Ok(RelocationType::X86_64TpOff64),
+        37 => Ok(RelocationType::X86_64IRelative),
+        41 => Ok(RelocationType::X86_64GotPcRelX),
+        42 => Ok(RelocationType::X86_64RexGotPcRelX),
+        _ => Err(ParseError::InvalidRelocationType(value)),
+    }
+}
+
+/// Returns the RISC-V relocation type numbered `value`.
+fn riscv_relocation_type(value: u32) -> Result<RelocationType, ParseError> {
+    match value {
+        0 => Ok(RelocationType::RiscvNone),
+        1 => Ok(RelocationType::Riscv32),
+        2 => Ok(RelocationType::Riscv64),
+        3 => Ok(RelocationType::RiscvRelative),
+        4 => Ok(RelocationType::RiscvCopy),
+        5 => Ok(RelocationType::RiscvJumpSlot),
+        11 => Ok(RelocationType::RiscvTlsTpRel64),
+        16 => Ok(RelocationType::RiscvBranch),
+        17 => Ok(RelocationType::RiscvJal),
+        18 => Ok(RelocationType::RiscvCall),
+        19 => Ok(RelocationType::RiscvCallPlt),
+        23 => Ok(RelocationType::RiscvPcrelHi20),
+        24 => Ok(RelocationType::RiscvPcrelLo12I),
+        25 => Ok(RelocationType::RiscvPcrelLo12S),
+        26 => Ok(RelocationType::RiscvHi20),
+        27 => Ok(RelocationType::RiscvLo12I),
+        28 => Ok(RelocationType::RiscvLo12S),
+        33 => Ok(RelocationType::RiscvAdd8),
+        34 => Ok(RelocationType::RiscvAdd16),
+        35 => Ok(RelocationType::RiscvAdd32),
+        36 => Ok(RelocationType::RiscvAdd64),
+        37 => Ok(RelocationType::RiscvSub8),
+        38 => Ok(RelocationType::RiscvSub16),
+        39 => Ok(RelocationType::RiscvSub32),
+        40 => Ok(RelocationType::RiscvSub64),
+        43 => Ok(RelocationType::RiscvAlign),
+        51 => Ok(RelocationType::RiscvRelax),
+        52 => Ok(RelocationType::RiscvSub6),
+        53 => Ok(RelocationType::RiscvSet6),
+        54 => Ok(RelocationType::RiscvSet8),
+        55 => Ok(RelocationType::RiscvSet16),
+        56 => Ok(RelocationType::RiscvSet32),
+        57 => Ok(RelocationType::Riscv32Pcrel),
+        58 => Ok(RelocationType::RiscvIRelative),
+        _ => Err(ParseError::InvalidRelocationType(value)),
+    }
+}
+
+impl TryFrom<(Machine, u64)> for Info {
     type Error = ParseError;
 
-    fn try_from(value: u64) -> Result<Self, Self::Error> {
-        // low 32 bits is the relocation type
-        let r#type = RelocationType::try_from((value & 0xffffffff) as u32)?;
+    fn try_from((machine, value): (Machine, u64)) -> Result<Self, Self::Error> {
+        // low 32 bits is the relocation type, whose meaning depends on the machine
+        let r#type = match machine {
+            Machine::X86_64 => x86_64_relocation_type((value & 0xffffffff) as u32)?,
+            Machine::RiscV => riscv_relocation_type((value & 0xffffffff) as u32)?,
+            _ => RelocationType::try_from((value & 0xffffffff) as u32)?,
+        };
         // high 32 bits is the symbol table index
         let symbol_index = (value >> 32) as u32;
         Ok(Info {
@@ -37,25 +180,81 @@ impl TryFrom<u64> for Info {
     }
 }
 
-fn parse_info(raw: &[u8]) -> ParseResult<Info> {
-    map_res(le_u64, Info::try_from).parse(raw)
+/// Parses `r_info`. In ELF32 it is 32 bits long, with the symbol index in the high
+/// 24 bits and the relocation type in the low 8 bits, and is read as its ELF64
+/// counterpart.
+fn parse_info(raw: &[u8], machine: Machine, encoding: Encoding) -> ParseResult<Info> {
+    let word = helper::word(encoding);
+    let info = |raw| match encoding.class {
+        Class::Bit32 => {
+            word(raw).map(|(rest, value)| (rest, ((value >> 8) << 32) | (value & 0xff)))
+        }
+        _ => word(raw),
+    };
+    map_res(info, |value| Info::try_from((machine, value))).parse(raw)
 }
 
-pub fn parse(section_headers: &[section::Header]) -> ParseResult<Vec<RelocationAddend>> {
-    let Some(header) = section_headers
-        .iter()
-        .find(|&s| s.r#type == section::SectionType::Rela)
-    else {
-        return Ok((&[], vec![]));
-    };
+/// Parses every `SHT_RELA` and `SHT_REL` section in the object.
+///
+/// Each relocation section is returned together with the index of the section
+/// its entries apply to. Relocation types are read as those of `machine`, and the
+/// entries as `Elf32_Rela`/`Elf32_Rel` or `Elf64_Rela`/`Elf64_Rel` depending on the
+/// class of `encoding`.
+///
+/// `SHT_REL` entries have no addend field: their addend is stored in the bytes
+/// they relocate. It is read from the target section, so that both forms are
+/// returned as [`RelocationAddend`].
+pub fn parse(
+    section_headers: &[section::Header],
+    machine: Machine,
+    encoding: Encoding,
+) -> ParseResult<Vec<RelocationSection>> {
+    let mut sections = Vec::new();
+    for header in section_headers.iter().filter(|&s| {
+        matches!(
+            s.r#type,
+            section::SectionType::Rela | section::SectionType::Rel
+        )
+    }) {
+        let mut entries = parse_entries(header, machine, encoding)?.1;
+        if header.r#type == section::SectionType::Rel {
+            let target = section_headers
+                .get(header.info as usize)
+                .map(|s| s.section_raw_data.as_slice())
+                .unwrap_or_default();
+            for entry in &mut entries {
+                entry.addend = implicit_addend(entry, target, encoding)?;
+            }
+        }
+        sections.push(RelocationSection {
+            target_section: header.info as u16,
+            entries,
+        });
+    }
+
+    Ok((&[], sections))
+}
 
+/// Parses the entries of a relocation section. The addend of `SHT_REL` entries,
+/// which have none, is left as 0.
+fn parse_entries(
+    header: &section::Header,
+    machine: Machine,
+    encoding: Encoding,
+) -> ParseResult<Vec<RelocationAddend>> {
     let entry_count = (header.size / header.entsize) as usize;
+    let endian = helper::endianness(encoding);
+    let has_addend = header.r#type == section::SectionType::Rela;
 
     let (rest, relocations) = count(
         |raw| {
-            let (rest, offset) = le_u64(raw)?;
-            let (rest, info) = parse_info(rest)?;
-            let (rest, addend) = le_i64(rest)?;
+            let (rest, offset) = helper::word(encoding)(raw)?;
+            let (rest, info) = parse_info(rest, machine, encoding)?;
+            let (rest, addend) = match encoding.class {
+                _ if !has_addend => (rest, 0),
+                Class::Bit32 => i32(endian)(rest).map(|(rest, addend)| (rest, addend as i64))?,
+                _ => i64(endian)(rest)?,
+            };
 
             let relocation = RelocationAddend {
                 offset,
@@ -72,17 +271,133 @@ pub fn parse(section_headers: &[section::Header]) -> ParseResult<Vec<RelocationA
     Ok((rest, relocations))
 }
 
+/// Returns the sign-extended value of the low `bits` bits of `value`.
+fn sign_extend(value: u64, bits: u32) -> i64 {
+    ((value << (64 - bits)) as i64) >> (64 - bits)
+}
+
+/// Reads the addend of a `SHT_REL` entry from the bytes it relocates in `data`.
+///
+/// Data relocations store the whole addend in the place, in the byte order of the
+/// object. Instructions, which are always little-endian, store it in their
+/// immediate field, scaled as the relocation would scale the value. Relocations
+/// that compute their value from the place itself, such as the RISC-V ADD and SUB
+/// relocations, get an addend of 0.
+fn implicit_addend(
+    relocation: &RelocationAddend,
+    data: &[u8],
+    encoding: Encoding,
+) -> Result<i64, nom::Err<ParseError>> {
+    use RelocationType::*;
+
+    let r#type = relocation.info.r#type;
+    let width = match r#type {
+        Aarch64None | X86_64None | RiscvNone | RiscvRelax | RiscvAlign | RiscvAdd8 | RiscvAdd16
+        | RiscvAdd32 | RiscvAdd64 | RiscvSub6 | RiscvSub8 | RiscvSub16 | RiscvSub32
+        | RiscvSub64 | RiscvPcrelLo12I | RiscvPcrelLo12S => return Ok(0),
+        Aarch64Abs64 | Aarch64Prel64 | X86_64_64 | Riscv64 | RiscvCall | RiscvCallPlt => 8,
+        Aarch64Abs16 | Aarch64Prel16 | RiscvSet16 => 2,
+        RiscvSet6 | RiscvSet8 => 1,
+        _ => 4,
+    };
+    let offset = relocation.offset as usize;
+    let Some(place) = offset
+        .checked_add(width)
+        .and_then(|end| data.get(offset..end))
+    else {
+        return Err(nom::Err::Failure(ParseError::InvalidRelocationOffset(
+            relocation.offset,
+        )));
+    };
+    let instruction = || u32::from_le_bytes(place[..4].try_into().unwrap()) as u64;
+    let field = |shift: u32, bits: u32| (instruction() >> shift) & ((1 << bits) - 1);
+
+    let addend = match r#type {
+        Aarch64Abs64 | Aarch64Prel64 | X86_64_64 | Riscv64 => encoding.read_u64(place) as i64,
+        Aarch64Abs32 | Aarch64Prel32 | X86_64_32 | X86_64_32S | X86_64Pc32 | X86_64Plt32
+        | X86_64GotPcRel | X86_64GotPcRelX | X86_64RexGotPcRelX | Riscv32 | Riscv32Pcrel => {
+            encoding.read_u32(place) as i32 as i64
+        }
+        Aarch64Abs16 | Aarch64Prel16 => encoding.read_u16(place) as i16 as i64,
+        RiscvSet6 => (place[0] & 0x3F) as i64,
+        RiscvSet8 => place[0] as i64,
+        RiscvSet16 => encoding.read_u16(place) as i64,
+        RiscvSet32 => encoding.read_u32(place) as i64,
+
+        // AArch64 instructions
+        Aarch64Call26 | Aarch64Jump26 => sign_extend(field(0, 26), 26) << 2,
+        Aarch64Condbr19 | Aarch64LdPrelLo19 => sign_extend(field(5, 19), 19) << 2,
+        Aarch64Tstbr14 => sign_extend(field(5, 14), 14) << 2,
+        Aarch64AdrPrelLo21 | Aarch64AdrPrelPgHi21 | Aarch64AdrPrelPgHi21Nc => {
+            // immhi in bits 5-23, immlo in bits 29-30
+            let imm = sign_extend((field(5, 19) << 2) | field(29, 2), 21);
+            if r#type == Aarch64AdrPrelLo21 {
+                imm
+            } else {
+                imm << 12
+            }
+        }
+        Aarch64AddAbsLo12Nc | Aarch64Ldst8AbsLo12Nc => field(10, 12) as i64,
+        Aarch64Ldst16AbsLo12Nc => (field(10, 12) << 1) as i64,
+        Aarch64Ldst32AbsLo12Nc => (field(10, 12) << 2) as i64,
+        Aarch64Ldst64AbsLo12Nc => (field(10, 12) << 3) as i64,
+        Aarch64Ldst128AbsLo12Nc => (field(10, 12) << 4) as i64,
+        Aarch64MovwUabsG0 | Aarch64MovwUabsG0Nc => field(5, 16) as i64,
+        Aarch64MovwUabsG1 | Aarch64MovwUabsG1Nc => (field(5, 16) << 16) as i64,
+        Aarch64MovwUabsG2 | Aarch64MovwUabsG2Nc => (field(5, 16) << 32) as i64,
+        Aarch64MovwUabsG3 => (field(5, 16) << 48) as i64,
+
+        // RISC-V instructions
+        RiscvHi20 | RiscvPcrelHi20 => sign_extend(field(12, 20) << 12, 32),
+        RiscvLo12I => sign_extend(field(20, 12), 12),
+        RiscvLo12S => sign_extend((field(25, 7) << 5) | field(7, 5), 12),
+        RiscvBranch => sign_extend(
+            (field(31, 1) << 12) | (field(7, 1) << 11) | (field(25, 6) << 5) | (field(8, 4) << 1),
+            13,
+        ),
+        RiscvJal => sign_extend(
+            (field(31, 1) << 20)
+                | (field(12, 8) << 12)
+                | (field(20, 1) << 11)
+                | (field(21, 10) << 1),
+            21,
+        ),
+        RiscvCall | RiscvCallPlt => {
+            // the offset is split between the `auipc` and the `jalr` after it
+            let jalr = u32::from_le_bytes(place[4..8].try_into().unwrap()) as u64;
+            sign_extend(field(12, 20) << 12, 32) + sign_extend(jalr >> 20, 12)
+        }
+
+        _ => {
+            return Err(nom::Err::Failure(ParseError::UnsupportedImplicitAddend(
+                r#type.to_string(),
+            )));
+        }
+    };
+    Ok(addend)
+}
+
 #[cfg(test)]
 mod tests {
     use super::parse;
-    use crate::elf::relocation::{Info, RelocationAddend, RelocationType};
+    use crate::elf::header::{Class, Data, Encoding, Machine};
+    use crate::elf::relocation::{Info, RelocationAddend, RelocationSection, RelocationType};
     use pretty_assertions::assert_eq;
 
+    const ELF64: Encoding = Encoding {
+        class: Class::Bit64,
+        data: Data::Lsb,
+    };
+    const ELF32: Encoding = Encoding {
+        class: Class::Bit32,
+        data: Data::Lsb,
+    };
+
     #[test]
     fn should_parse_info() {
         let value: u64 = 0x00000010_00000112;
         let raw = value.to_le_bytes();
-        let info = super::parse_info(&raw).unwrap().1;
+        let info = super::parse_info(&raw, Machine::AArch64, ELF64).unwrap().1;
         assert_eq!(
             info,
             Info {
@@ -92,29 +407,136 @@ mod tests {
         );
     }
 
+    #[test]
+    fn should_parse_x86_64_info() {
+        let value: u64 = 0x00000003_0000002a;
+        let raw = value.to_le_bytes();
+        let info = super::parse_info(&raw, Machine::X86_64, ELF64).unwrap().1;
+        assert_eq!(
+            info,
+            Info {
+                r#type: RelocationType::X86_64RexGotPcRelX,
+                symbol_index: 3,
+            }
+        );
+        assert_eq!(info.r#type.value(), 42);
+    }
+
+    #[test]
+    fn should_parse_riscv_info() {
+        // the same number is a different relocation for each machine
+        let raw = 0x00000005_00000012u64.to_le_bytes();
+        let info = super::parse_info(&raw, Machine::RiscV, ELF64).unwrap().1;
+        assert_eq!(info.r#type, RelocationType::RiscvCall);
+        assert_eq!(info.r#type.value(), 18);
+        let info = super::parse_info(&raw, Machine::X86_64, ELF64).unwrap().1;
+        assert_eq!(info.r#type, RelocationType::X86_64TpOff64);
+    }
+
+    #[test]
+    fn should_parse_elf32_info() {
+        // symbol 5 in the high 24 bits, R_RISCV_CALL in the low 8 bits
+        let raw = 0x00000512u32.to_le_bytes();
+        let info = super::parse_info(&raw, Machine::RiscV, ELF32).unwrap().1;
+        assert_eq!(
+            info,
+            Info {
+                r#type: RelocationType::RiscvCall,
+                symbol_index: 5,
+            }
+        );
+    }
+
     #[test]
     fn should_parse_relocation() {
         let raw = include_bytes!("./fixtures/main.o");
         let (_, header) = crate::parser::header::parse(raw).unwrap();
         let (_, section_headers) = crate::parser::section::parse_header(
             raw,
+            header.ident.encoding(),
             header.shoff as usize,
             header.shstrndx as usize,
             header.shnum as usize,
         )
         .unwrap();
 
-        let reloc = parse(&section_headers).unwrap().1;
+        let reloc = parse(&section_headers, header.machine, header.ident.encoding())
+            .unwrap()
+            .1;
         assert_eq!(
             reloc,
-            vec![RelocationAddend {
-                offset: 0,
+            vec![RelocationSection {
+                target_section: 1,
+                entries: vec![RelocationAddend {
+                    offset: 0,
+                    info: Info {
+                        r#type: RelocationType::Aarch64AdrPrelLo21,
+                        symbol_index: 9,
+                    },
+                    addend: 0,
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn should_read_implicit_addend() {
+        let implicit_addend_at = |offset, r#type, data: &[u8], encoding| {
+            let relocation = RelocationAddend {
+                offset,
                 info: Info {
-                    r#type: RelocationType::Aarch64AdrPrelLo21,
-                    symbol_index: 9,
+                    r#type,
+                    symbol_index: 0,
                 },
                 addend: 0,
-            }]
+            };
+            super::implicit_addend(&relocation, data, encoding)
+        };
+        let implicit_addend =
+            |r#type, data: &[u8], encoding| implicit_addend_at(0, r#type, data, encoding);
+        let big_endian = Encoding {
+            data: Data::Msb,
+            ..ELF64
+        };
+
+        // data follows the byte order of the object
+        let raw = (-8i64).to_be_bytes();
+        assert_eq!(
+            implicit_addend(RelocationType::Aarch64Abs64, &raw, big_endian),
+            Ok(-8)
+        );
+        let raw = 0x10u32.to_le_bytes();
+        assert_eq!(
+            implicit_addend(RelocationType::X86_64Pc32, &raw, ELF64),
+            Ok(0x10)
+        );
+        // `b .-4` encodes -1 in imm26, even in a big-endian object
+        let raw = 0x17ffffffu32.to_le_bytes();
+        assert_eq!(
+            implicit_addend(RelocationType::Aarch64Jump26, &raw, big_endian),
+            Ok(-4)
+        );
+        // `adrp x0, . + 0x3000`
+        let raw = 0xf0000000u32.to_le_bytes();
+        assert_eq!(
+            implicit_addend(RelocationType::Aarch64AdrPrelPgHi21, &raw, ELF64),
+            Ok(0x3000)
         );
+        // `ldr x1, [x0, #16]` scales imm12 by 8
+        let raw = 0xf9400801u32.to_le_bytes();
+        assert_eq!(
+            implicit_addend(RelocationType::Aarch64Ldst64AbsLo12Nc, &raw, ELF64),
+            Ok(16)
+        );
+        // `addi a0, a0, -1`
+        let raw = 0xfff50513u32.to_le_bytes();
+        assert_eq!(
+            implicit_addend(RelocationType::RiscvLo12I, &raw, ELF32),
+            Ok(-1)
+        );
+
+        assert!(implicit_addend(RelocationType::Aarch64Abs64, &raw, ELF64).is_err());
+        assert!(implicit_addend(RelocationType::Aarch64AdrGotPage, &raw, ELF64).is_err());
+        assert!(implicit_addend_at(u64::MAX, RelocationType::Aarch64Abs32, &raw, ELF64).is_err());
     }
 }
@@ -1,13 +1,9 @@
 use crate::{
+    elf::header::Encoding,
     elf::section::{Header, SectionFlag, SectionType},
     parser::error::ParseError,
 };
-use nom::{
-    Parser as _,
-    combinator::map_res,
-    multi::count,
-    number::complete::{le_u32, le_u64},
-};
+use nom::{Parser as _, combinator::map_res, multi::count, number::complete::u32};
 
 use super::{ParseResult, helper};
 
@@ -40,12 +36,13 @@ impl TryFrom<u32> for SectionType {
     }
 }
 
-fn parse_type(raw: &[u8]) -> ParseResult<SectionType> {
-    map_res(le_u32, SectionType::try_from).parse(raw)
+fn parse_type(raw: &[u8], encoding: Encoding) -> ParseResult<SectionType> {
+    map_res(u32(helper::endianness(encoding)), SectionType::try_from).parse(raw)
 }
 
-fn parse_flags(raw: &[u8]) -> ParseResult<Vec<SectionFlag>> {
-    map_res(le_u64, |mask| -> Result<Vec<SectionFlag>, ParseError> {
+fn parse_flags(raw: &[u8], encoding: Encoding) -> ParseResult<Vec<SectionFlag>> {
+    let word = helper::word(encoding);
+    map_res(word, |mask| -> Result<Vec<SectionFlag>, ParseError> {
         let flag_variants = [
             SectionFlag::Write,
             SectionFlag::Alloc,
@@ -57,6 +54,7 @@ fn parse_flags(raw: &[u8]) -> ParseResult<Vec<SectionFlag>> {
             SectionFlag::Group,
             SectionFlag::Tls,
             SectionFlag::Compressed,
+            SectionFlag::GnuRetain,
         ];
 
         if mask == 0 {
@@ -78,6 +76,9 @@ fn parse_flags(raw: &[u8]) -> ParseResult<Vec<SectionFlag>> {
 /// # Arguments
 ///
 /// * `raw` - A byte slice containing the raw ELF file data.
+/// * `encoding` - The class and byte order of the file. The class decides the size of
+///   the address, offset and size fields: section headers are 40 bytes long in ELF32
+///   and 64 in ELF64.
 /// * `shoff` - The offset in the file where the section header table begins.
 /// * `shstrndx` - The index of the section header string table in the section header table.
 /// * `shnum` - The number of section headers in the section header table.
@@ -93,6 +94,7 @@ fn parse_flags(raw: &[u8]) -> ParseResult<Vec<SectionFlag>> {
 /// names using the section header string table.
 pub fn parse_header(
     raw: &[u8],
+    encoding: Encoding,
     shoff: usize,
     shstrndx: usize,
     shnum: usize,
@@ -101,19 +103,26 @@ pub fn parse_header(
         return Ok((raw, vec![]));
     }
 
+    let word = helper::word(encoding);
+    let read_u32 = u32(helper::endianness(encoding));
     count(
         |rest| {
-            let (rest, name_idx) = le_u32(rest)?;
-            let (rest, r#type) = parse_type(rest)?;
-            let (rest, flags) = parse_flags(rest)?;
-            let (rest, addr) = le_u64(rest)?;
-            let (rest, offset) = le_u64(rest)?;
-            let (rest, size) = le_u64(rest)?;
-            let (rest, link) = le_u32(rest)?;
-            let (rest, info) = le_u32(rest)?;
-            let (rest, addralign) = le_u64(rest)?;
-            let (rest, entsize) = le_u64(rest)?;
-            let data = raw[offset as usize..(offset + size) as usize].to_vec();
+            let (rest, name_idx) = read_u32(rest)?;
+            let (rest, r#type) = parse_type(rest, encoding)?;
+            let (rest, flags) = parse_flags(rest, encoding)?;
+            let (rest, addr) = word(rest)?;
+            let (rest, offset) = word(rest)?;
+            let (rest, size) = word(rest)?;
+            let (rest, link) = read_u32(rest)?;
+            let (rest, info) = read_u32(rest)?;
+            let (rest, addralign) = word(rest)?;
+            let (rest, entsize) = word(rest)?;
+            // SHT_NOBITS sections occupy no space in the file
+            let data = if r#type == SectionType::NoBits {
+                vec![]
+            } else {
+                raw[offset as usize..(offset + size) as usize].to_vec()
+            };
 
             let header = Header {
                 name_idx,
@@ -157,6 +166,7 @@ mod tests {
 
         let (_, header_table) = parse_header(
             raw,
+            header.ident.encoding(),
             header.shoff as usize,
             header.shstrndx as usize,
             header.shnum as usize,
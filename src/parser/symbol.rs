@@ -1,5 +1,6 @@
 use super::{ParseResult, error::ParseError, helper};
 use crate::elf::{
+    header::{Class, Encoding},
     section::{Header, SectionType},
     symbol::{Binding, Info, Symbol, Type, Visibility},
 };
@@ -7,7 +8,7 @@ use nom::{
     Parser as _,
     combinator::map_res,
     multi::count,
-    number::complete::{le_u8, le_u16, le_u32, le_u64},
+    number::complete::{le_u8, u16, u32},
 };
 
 impl TryFrom<u8> for Visibility {
@@ -70,10 +71,32 @@ fn parse_info(raw: &[u8]) -> ParseResult<Info> {
     map_res(le_u8, Info::try_from).parse(raw)
 }
 
-pub fn parse<'a>(raw: &'a [u8], section_headers: &'a [Header]) -> ParseResult<'a, Vec<Symbol>> {
+pub fn parse<'a>(
+    raw: &'a [u8],
+    section_headers: &'a [Header],
+    encoding: Encoding,
+) -> ParseResult<'a, Vec<Symbol>> {
+    parse_table(raw, section_headers, encoding, SectionType::SymTab)
+}
+
+/// Parses the dynamic symbol table (`.dynsym`) of a shared object or executable.
+pub fn parse_dynamic<'a>(
+    raw: &'a [u8],
+    section_headers: &'a [Header],
+    encoding: Encoding,
+) -> ParseResult<'a, Vec<Symbol>> {
+    parse_table(raw, section_headers, encoding, SectionType::DynSym)
+}
+
+fn parse_table<'a>(
+    raw: &'a [u8],
+    section_headers: &'a [Header],
+    encoding: Encoding,
+    r#type: SectionType,
+) -> ParseResult<'a, Vec<Symbol>> {
     let Some(symbol_header) = section_headers
         .iter()
-        .find(|header| header.r#type == SectionType::SymTab)
+        .find(|header| header.r#type == r#type)
     else {
         return Ok((&[], vec![]));
     };
@@ -84,14 +107,27 @@ pub fn parse<'a>(raw: &'a [u8], section_headers: &'a [Header]) -> ParseResult<'a
 
     let entry_count = (symbol_header.size / symbol_header.entsize) as usize;
 
+    let word = helper::word(encoding);
+    let endian = helper::endianness(encoding);
     let (rest, symbols) = count(
         |raw| {
-            let (rest, name_idx) = le_u32(raw)?;
+            let (rest, name_idx) = u32(endian)(raw)?;
+            // Elf32_Sym has the value and size before the info, Elf64_Sym after the
+            // section index
+            let (rest, value_and_size) = match encoding.class {
+                Class::Bit32 => (&word, &word)
+                    .parse(rest)
+                    .map(|(rest, v)| (rest, Some(v)))?,
+                _ => (rest, None),
+            };
             let (rest, info) = parse_info(rest)?;
-            let (rest, other) = map_res(le_u8, Visibility::try_from).parse(rest)?;
-            let (rest, shndx) = le_u16(rest)?;
-            let (rest, value) = le_u64(rest)?;
-            let (rest, size) = le_u64(rest)?;
+            // the upper bits of st_other hold processor-specific flags
+            let (rest, other) = map_res(le_u8, |b| Visibility::try_from(b & 0x3)).parse(rest)?;
+            let (rest, shndx) = u16(endian)(rest)?;
+            let (rest, (value, size)) = match value_and_size {
+                Some(value_and_size) => (rest, value_and_size),
+                None => (&word, &word).parse(rest)?,
+            };
 
             let name = helper::get_string_by_offset(string_table, name_idx as usize);
             let symbol = Symbol {
@@ -139,13 +175,16 @@ mod tests {
         let (_, header) = crate::parser::header::parse(raw).unwrap();
         let (_, section_headers) = crate::parser::section::parse_header(
             raw,
+            header.ident.encoding(),
             header.shoff as usize,
             header.shstrndx as usize,
             header.shnum as usize,
         )
         .unwrap();
 
-        let symbols = parse(raw, &section_headers).unwrap().1;
+        let symbols = parse(raw, &section_headers, header.ident.encoding())
+            .unwrap()
+            .1;
         let want = [
             Symbol {
                 name: "".into(),